@@ -0,0 +1,198 @@
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use spl_associated_token_account_client::address::get_associated_token_address;
+
+use crate::bench::{Bench, Measurement};
+use crate::{DEPOSIT, FEE, SEED, SWAP, USER_BALANCE};
+
+fn instruction(accounts: Vec<AccountMeta>, data: impl InstructionData) -> Instruction {
+    Instruction::new_with_bytes(anchor_amm::ID, &data.data(), accounts)
+}
+
+pub async fn run(bench: &mut Bench) -> Vec<Measurement> {
+    let user = bench.payer();
+    let mint_x = bench.create_mint().await;
+    let mint_y = bench.create_mint().await;
+    let user_x = bench.create_ata(&mint_x, USER_BALANCE).await;
+    let user_y = bench.create_ata(&mint_y, USER_BALANCE).await;
+
+    let (config, _) =
+        Pubkey::find_program_address(&[b"config", SEED.to_le_bytes().as_ref()], &anchor_amm::ID);
+    let (mint_lp, _) = Pubkey::find_program_address(&[b"lp", config.as_ref()], &anchor_amm::ID);
+    let (oracle, _) = Pubkey::find_program_address(&[b"oracle", config.as_ref()], &anchor_amm::ID);
+    let vault_x = get_associated_token_address(&config, &mint_x);
+    let vault_y = get_associated_token_address(&config, &mint_y);
+    let associated_token_program = spl_associated_token_account_client::program::ID;
+
+    let mut measurements = vec![];
+
+    measurements.push(
+        bench
+            .measure(
+                "initialize",
+                instruction(
+                    anchor_amm::accounts::Initialize {
+                        initializer: user,
+                        mint_x,
+                        mint_y,
+                        mint_lp,
+                        vault_x,
+                        vault_y,
+                        config,
+                        oracle,
+                        token_program: spl_token::ID,
+                        associated_token_program,
+                        system_program: system_program::ID,
+                    }
+                    .to_account_metas(None),
+                    anchor_amm::instruction::Initialize {
+                        seed: SEED,
+                        fee: FEE,
+                        curve: anchor_amm::state::CurveType::ConstantProduct,
+                        // Even weights, so the constant product
+                        weight_x: 5_000,
+                        amp: 0,
+                    },
+                ),
+            )
+            .await,
+    );
+
+    // Created up front so `init_if_needed` does the same work as the native program
+    let user_lp = bench.create_ata(&mint_lp, 0).await;
+
+    let deposit_accounts = anchor_amm::accounts::Deposit {
+        user,
+        mint_x,
+        mint_y,
+        mint_lp,
+        vault_x,
+        vault_y,
+        user_x,
+        user_y,
+        user_lp,
+        config,
+        token_program: spl_token::ID,
+        associated_token_program,
+        system_program: system_program::ID,
+    }
+    .to_account_metas(None);
+
+    let swap_accounts = anchor_amm::accounts::Swap {
+        user,
+        mint_x,
+        mint_y,
+        mint_lp,
+        vault_x,
+        vault_y,
+        user_x,
+        user_y,
+        config,
+        oracle,
+        token_program: spl_token::ID,
+        associated_token_program,
+        system_program: system_program::ID,
+    }
+    .to_account_metas(None);
+
+    measurements.push(
+        bench
+            .measure(
+                "deposit (first)",
+                instruction(
+                    deposit_accounts.clone(),
+                    anchor_amm::instruction::AddLiquidity {
+                        amount: 1,
+                        max_x: DEPOSIT,
+                        max_y: DEPOSIT,
+                    },
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "deposit",
+                instruction(
+                    deposit_accounts,
+                    anchor_amm::instruction::AddLiquidity {
+                        amount: DEPOSIT,
+                        max_x: 2 * DEPOSIT,
+                        max_y: 2 * DEPOSIT,
+                    },
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "swap",
+                instruction(
+                    swap_accounts.clone(),
+                    anchor_amm::instruction::Swap {
+                        amount_in: SWAP,
+                        min_amount_out: 0,
+                        from_x: true,
+                    },
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "swap_exact_out",
+                instruction(
+                    swap_accounts,
+                    anchor_amm::instruction::SwapExactOut {
+                        amount_out: SWAP,
+                        max_amount_in: u64::MAX,
+                        from_x: false,
+                    },
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "withdraw",
+                instruction(
+                    anchor_amm::accounts::Withdraw {
+                        user,
+                        mint_x,
+                        mint_y,
+                        mint_lp,
+                        vault_x,
+                        vault_y,
+                        user_x,
+                        user_y,
+                        user_lp,
+                        config,
+                        token_program: spl_token::ID,
+                        associated_token_program,
+                        system_program: system_program::ID,
+                    }
+                    .to_account_metas(None),
+                    anchor_amm::instruction::Withdraw {
+                        amount: DEPOSIT,
+                        min_x: 0,
+                        min_y: 0,
+                    },
+                ),
+            )
+            .await,
+    );
+
+    measurements
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    bpf_loader,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use spl_associated_token_account_client::{
+    address::get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::state::Mint;
+
+/// Cost of one instruction in a scenario
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Measurement {
+    pub instruction: String,
+    pub compute_units: u64,
+    pub cpi_count: usize,
+}
+
+/// A bank with a single compiled program loaded next to the SPL programs
+pub struct Bench {
+    pub context: ProgramTestContext,
+}
+
+impl Bench {
+    pub async fn start(program_id: Pubkey, program: &[u8]) -> Self {
+        let mut program_test = ProgramTest::default();
+        program_test.add_account(
+            program_id,
+            Account {
+                lamports: Rent::default().minimum_balance(program.len()).max(1),
+                data: program.to_vec(),
+                owner: bpf_loader::id(),
+                executable: true,
+                rent_epoch: 0,
+            },
+        );
+
+        Self {
+            context: program_test.start_with_context().await,
+        }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    /// Sends `instructions` in one transaction, panicking with the program logs on failure.
+    /// Returns the compute units consumed and the transaction logs
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> (u64, Vec<String>) {
+        let blockhash = self
+            .context
+            .banks_client
+            .get_latest_blockhash()
+            .await
+            .unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        let result = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        let metadata = result
+            .metadata
+            .expect("the bank returns transaction metadata");
+
+        if let Err(error) = result.result {
+            panic!("{error}\n{}", metadata.log_messages.join("\n"));
+        }
+
+        (metadata.compute_units_consumed, metadata.log_messages)
+    }
+
+    /// Runs a single program instruction and records what it cost
+    pub async fn measure(&mut self, name: &str, instruction: Instruction) -> Measurement {
+        let (compute_units, logs) = self.process(&[instruction], &[]).await;
+
+        Measurement {
+            instruction: name.to_string(),
+            compute_units,
+            cpi_count: cpi_count(&logs),
+        }
+    }
+
+    pub async fn create_mint(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let payer = self.payer();
+
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    Rent::default().minimum_balance(Mint::LEN),
+                    Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint2(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &payer,
+                    None,
+                    6,
+                )
+                .unwrap(),
+            ],
+            &[&mint],
+        )
+        .await;
+
+        mint.pubkey()
+    }
+
+    /// Creates the payer's associated token account for `mint` and funds it with `amount`
+    pub async fn create_ata(&mut self, mint: &Pubkey, amount: u64) -> Pubkey {
+        let payer = self.payer();
+        let ata = get_associated_token_address(&payer, mint);
+
+        let mut instructions = vec![create_associated_token_account_idempotent(
+            &payer,
+            &payer,
+            mint,
+            &spl_token::ID,
+        )];
+        if amount > 0 {
+            instructions.push(
+                spl_token::instruction::mint_to(&spl_token::ID, mint, &ata, &payer, &[], amount)
+                    .unwrap(),
+            );
+        }
+
+        self.process(&instructions, &[]).await;
+
+        ata
+    }
+}
+
+/// Number of cross-program invocations, i.e. invokes below the top-level instruction
+fn cpi_count(logs: &[String]) -> usize {
+    logs.iter()
+        .filter(|log| log.starts_with("Program ") && log.contains(" invoke ["))
+        .filter(|log| !log.ends_with(" invoke [1]"))
+        .count()
+}
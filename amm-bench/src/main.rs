@@ -0,0 +1,106 @@
+//! Runs the same pool scenario against the compiled `native-amm` and `anchor-amm`
+//! programs and writes a compute-unit, CPI and binary-size comparison.
+//!
+//! Build both programs first (`cargo build-sbf` in `native-amm`, `anchor build` in
+//! `anchor-amm`), then run `cargo run --release` here. Pass `--native <so>`,
+//! `--anchor <so>` or `--out <dir>` to override the default paths. When a previous
+//! `results.json` exists in the output directory, every number is shown with its
+//! delta against that run.
+
+mod anchor;
+mod bench;
+mod native;
+mod report;
+
+use std::path::{Path, PathBuf};
+
+use bench::Bench;
+use report::{ProgramReport, Report};
+
+const SEED: u64 = 42;
+const FEE: u16 = 30;
+const USER_BALANCE: u64 = 10_000_000;
+const DEPOSIT: u64 = 1_000_000;
+const SWAP: u64 = 10_000;
+
+struct Args {
+    native: PathBuf,
+    anchor: PathBuf,
+    out: PathBuf,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut args = Self {
+            native: root.join("../native-amm/target/deploy/native_amm.so"),
+            anchor: root.join("../anchor-amm/target/deploy/anchor_amm.so"),
+            out: root.to_path_buf(),
+        };
+
+        let mut argv = std::env::args().skip(1);
+        while let Some(flag) = argv.next() {
+            let value = argv
+                .next()
+                .unwrap_or_else(|| panic!("missing value for {flag}"));
+
+            match flag.as_str() {
+                "--native" => args.native = value.into(),
+                "--anchor" => args.anchor = value.into(),
+                "--out" => args.out = value.into(),
+                _ => panic!("unknown argument {flag}"),
+            }
+        }
+
+        args
+    }
+}
+
+fn read_program(path: &Path) -> Vec<u8> {
+    std::fs::read(path).unwrap_or_else(|error| {
+        panic!(
+            "could not read {}: {error}. Build the program before benchmarking it",
+            path.display()
+        )
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let native_program = read_program(&args.native);
+    let mut native_bench = Bench::start(native_amm_client::ID, &native_program).await;
+    let native = ProgramReport {
+        program: "native-amm".to_string(),
+        binary_size: native_program.len() as u64,
+        measurements: native::run(&mut native_bench).await,
+    };
+
+    let anchor_program = read_program(&args.anchor);
+    let mut anchor_bench = Bench::start(anchor_amm::ID, &anchor_program).await;
+    let anchor = ProgramReport {
+        program: "anchor-amm".to_string(),
+        binary_size: anchor_program.len() as u64,
+        measurements: anchor::run(&mut anchor_bench).await,
+    };
+
+    let report = Report {
+        programs: vec![native, anchor],
+    };
+
+    let json_path = args.out.join("results.json");
+    let markdown_path = args.out.join("results.md");
+    let previous = Report::load(&json_path);
+
+    let markdown = report.to_markdown(previous.as_ref());
+    std::fs::write(&markdown_path, &markdown).unwrap();
+    std::fs::write(&json_path, serde_json::to_string_pretty(&report).unwrap()).unwrap();
+
+    println!("{markdown}");
+    println!(
+        "Wrote {} and {}",
+        markdown_path.display(),
+        json_path.display()
+    );
+}
@@ -0,0 +1,115 @@
+use native_amm_client::{
+    deposit_ix, initialize_ix, swap_exact_out_ix, swap_ix, withdraw_ix, Curve, Pool,
+};
+
+use crate::bench::{Bench, Measurement};
+use crate::{DEPOSIT, FEE, SEED, SWAP, USER_BALANCE};
+
+pub async fn run(bench: &mut Bench) -> Vec<Measurement> {
+    let user = bench.payer();
+    let mint_x = bench.create_mint().await;
+    let mint_y = bench.create_mint().await;
+    let user_x = bench.create_ata(&mint_x, USER_BALANCE).await;
+    let user_y = bench.create_ata(&mint_y, USER_BALANCE).await;
+
+    let pool = Pool::new(SEED, mint_x, mint_y, spl_token::ID, spl_token::ID);
+
+    let mut measurements = vec![];
+
+    measurements.push(
+        bench
+            .measure(
+                "initialize",
+                initialize_ix(&pool, &user, FEE, &user, Curve::ConstantProduct),
+            )
+            .await,
+    );
+
+    let user_lp = bench.create_ata(&pool.mint_lp, 0).await;
+
+    measurements.push(
+        bench
+            .measure(
+                "deposit (first)",
+                deposit_ix(
+                    &pool,
+                    &user,
+                    &user_x,
+                    &user_y,
+                    &user_lp,
+                    1,
+                    DEPOSIT,
+                    DEPOSIT,
+                    i64::MAX,
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "deposit",
+                deposit_ix(
+                    &pool,
+                    &user,
+                    &user_x,
+                    &user_y,
+                    &user_lp,
+                    DEPOSIT,
+                    2 * DEPOSIT,
+                    2 * DEPOSIT,
+                    i64::MAX,
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "swap",
+                swap_ix(&pool, &user, &user_x, &user_y, SWAP, 0, true, i64::MAX),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "swap_exact_out",
+                swap_exact_out_ix(
+                    &pool,
+                    &user,
+                    &user_x,
+                    &user_y,
+                    SWAP,
+                    u64::MAX,
+                    false,
+                    i64::MAX,
+                ),
+            )
+            .await,
+    );
+
+    measurements.push(
+        bench
+            .measure(
+                "withdraw",
+                withdraw_ix(
+                    &pool,
+                    &user,
+                    &user_x,
+                    &user_y,
+                    &user_lp,
+                    DEPOSIT,
+                    0,
+                    0,
+                    i64::MAX,
+                ),
+            )
+            .await,
+    );
+
+    measurements
+}
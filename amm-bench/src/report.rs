@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bench::Measurement;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProgramReport {
+    pub program: String,
+    pub binary_size: u64,
+    pub measurements: Vec<Measurement>,
+}
+
+impl ProgramReport {
+    fn measurement(&self, instruction: &str) -> Option<&Measurement> {
+        self.measurements
+            .iter()
+            .find(|measurement| measurement.instruction == instruction)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Report {
+    pub programs: Vec<ProgramReport>,
+}
+
+impl Report {
+    /// The report written by a previous run, if there is a readable one at `path`
+    pub fn load(path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn program(&self, program: &str) -> Option<&ProgramReport> {
+        self.programs
+            .iter()
+            .find(|report| report.program == program)
+    }
+
+    /// One row per instruction with the compute units and CPIs of every program,
+    /// followed by the binary sizes. Numbers that moved since `previous` show the delta
+    pub fn to_markdown(&self, previous: Option<&Report>) -> String {
+        let mut markdown = String::from("| Instruction |");
+        for report in &self.programs {
+            write!(
+                markdown,
+                " {} CU | {} CPIs |",
+                report.program, report.program
+            )
+            .unwrap();
+        }
+        markdown.push_str("\n|---|");
+        for _ in &self.programs {
+            markdown.push_str("---:|---:|");
+        }
+        markdown.push('\n');
+
+        let instructions = self
+            .programs
+            .first()
+            .map_or(&[][..], |report| &report.measurements);
+        for Measurement { instruction, .. } in instructions {
+            write!(markdown, "| {instruction} |").unwrap();
+
+            for report in &self.programs {
+                let before = previous
+                    .and_then(|previous| previous.program(&report.program))
+                    .and_then(|previous| previous.measurement(instruction));
+
+                match report.measurement(instruction) {
+                    Some(measurement) => write!(
+                        markdown,
+                        " {} | {} |",
+                        with_delta(
+                            measurement.compute_units,
+                            before.map(|before| before.compute_units)
+                        ),
+                        with_delta(
+                            measurement.cpi_count as u64,
+                            before.map(|before| before.cpi_count as u64)
+                        ),
+                    )
+                    .unwrap(),
+                    None => markdown.push_str(" - | - |"),
+                }
+            }
+
+            markdown.push('\n');
+        }
+
+        markdown.push_str("\n| Program | Binary size (bytes) |\n|---|---:|\n");
+        for report in &self.programs {
+            let before = previous
+                .and_then(|previous| previous.program(&report.program))
+                .map(|previous| previous.binary_size);
+
+            writeln!(
+                markdown,
+                "| {} | {} |",
+                report.program,
+                with_delta(report.binary_size, before)
+            )
+            .unwrap();
+        }
+
+        markdown
+    }
+}
+
+fn with_delta(value: u64, before: Option<u64>) -> String {
+    match before {
+        Some(before) if before != value => {
+            format!("{value} ({:+})", value as i128 - before as i128)
+        }
+        _ => value.to_string(),
+    }
+}
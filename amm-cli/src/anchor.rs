@@ -0,0 +1,235 @@
+use amm_math::{weighted::EVEN_WEIGHT, Curve};
+use anchor_amm::state::{Config, CurveType};
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+use spl_associated_token_account_client::address::get_associated_token_address_with_program_id;
+
+use crate::pool::{token_program, PoolAddress, PoolState};
+use crate::{Context, Result};
+
+fn instruction(accounts: Vec<AccountMeta>, data: impl InstructionData) -> Instruction {
+    Instruction::new_with_bytes(anchor_amm::ID, &data.data(), accounts)
+}
+
+fn config_address(address: PoolAddress) -> Pubkey {
+    match address {
+        PoolAddress::Seed(seed) => {
+            Pubkey::find_program_address(&[b"config", seed.to_le_bytes().as_ref()], &anchor_amm::ID)
+                .0
+        }
+        PoolAddress::Canonical {
+            mint_x,
+            mint_y,
+            fee_tier,
+        } => canonical_config_address(&mint_x, &mint_y, fee_tier),
+    }
+}
+
+/// Config of the factory's canonical pool for a mint pair at `fee_tier`, with
+/// `mint_x` the lower of the two
+fn canonical_config_address(mint_x: &Pubkey, mint_y: &Pubkey, fee_tier: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"config",
+            mint_x.as_ref(),
+            mint_y.as_ref(),
+            fee_tier.to_le_bytes().as_ref(),
+        ],
+        &anchor_amm::ID,
+    )
+    .0
+}
+
+fn oracle_address(config: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"oracle", config.as_ref()], &anchor_amm::ID).0
+}
+
+fn lp_mint_address(config: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"lp", config.as_ref()], &anchor_amm::ID).0
+}
+
+/// Both pool tokens and the LP mint live on the pool's single token program
+pub fn load(context: &Context, address: PoolAddress) -> Result<PoolState> {
+    let config = config_address(address);
+    let config_account = Config::try_deserialize(&mut &context.rpc.get_account_data(&config)?[..])?;
+    let token_program = token_program(context, &config_account.mint_x)?;
+
+    Ok(PoolState {
+        seed: config_account.seed,
+        fee_tier: config_account.factory.then_some(config_account.fee_tier),
+        config,
+        authority: config_account.authority,
+        mint_x: config_account.mint_x,
+        mint_y: config_account.mint_y,
+        mint_lp: lp_mint_address(&config),
+        oracle: oracle_address(&config),
+        vault_x: get_associated_token_address_with_program_id(
+            &config,
+            &config_account.mint_x,
+            &token_program,
+        ),
+        vault_y: get_associated_token_address_with_program_id(
+            &config,
+            &config_account.mint_y,
+            &token_program,
+        ),
+        token_program_x: token_program,
+        token_program_y: token_program,
+        lp_token_program: token_program,
+        fee: config_account.fee,
+        flash_fee: config_account.flash_fee,
+        curve: config_account.curve_at(context.now),
+        locked: config_account.locked,
+    })
+}
+
+pub fn create_pool(
+    context: &Context,
+    seed: u64,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    fee: u16,
+    curve: Curve,
+) -> Result<Vec<Instruction>> {
+    let config = config_address(PoolAddress::Seed(seed));
+    let (curve, weight_x, amp) = match curve {
+        Curve::ConstantProduct => (CurveType::ConstantProduct, EVEN_WEIGHT, 0),
+        Curve::Stable { amp } => (CurveType::Stable, EVEN_WEIGHT, amp),
+        Curve::Weighted { weight_x } => (CurveType::ConstantProduct, weight_x, 0),
+    };
+    let token_program = token_program(context, &mint_x)?;
+
+    Ok(vec![instruction(
+        anchor_amm::accounts::Initialize {
+            initializer: context.payer.pubkey(),
+            mint_x,
+            mint_y,
+            mint_lp: lp_mint_address(&config),
+            vault_x: get_associated_token_address_with_program_id(&config, &mint_x, &token_program),
+            vault_y: get_associated_token_address_with_program_id(&config, &mint_y, &token_program),
+            config,
+            oracle: oracle_address(&config),
+            token_program,
+            associated_token_program: spl_associated_token_account_client::program::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        anchor_amm::instruction::Initialize {
+            seed,
+            fee,
+            curve,
+            weight_x,
+            amp,
+        },
+    )])
+}
+
+pub fn deposit(
+    context: &Context,
+    state: &PoolState,
+    amount: u64,
+    max_x: u64,
+    max_y: u64,
+) -> Vec<Instruction> {
+    let user = context.payer.pubkey();
+
+    vec![instruction(
+        anchor_amm::accounts::Deposit {
+            user,
+            mint_x: state.mint_x,
+            mint_y: state.mint_y,
+            mint_lp: state.mint_lp,
+            vault_x: state.vault_x,
+            vault_y: state.vault_y,
+            user_x: state.user_x(&user),
+            user_y: state.user_y(&user),
+            user_lp: state.user_lp(&user),
+            config: state.config,
+            token_program: state.lp_token_program,
+            associated_token_program: spl_associated_token_account_client::program::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        anchor_amm::instruction::AddLiquidity {
+            amount,
+            max_x,
+            max_y,
+        },
+    )]
+}
+
+pub fn withdraw(
+    context: &Context,
+    state: &PoolState,
+    amount: u64,
+    min_x: u64,
+    min_y: u64,
+) -> Vec<Instruction> {
+    let user = context.payer.pubkey();
+
+    vec![instruction(
+        anchor_amm::accounts::Withdraw {
+            user,
+            mint_x: state.mint_x,
+            mint_y: state.mint_y,
+            mint_lp: state.mint_lp,
+            vault_x: state.vault_x,
+            vault_y: state.vault_y,
+            user_x: state.user_x(&user),
+            user_y: state.user_y(&user),
+            user_lp: state.user_lp(&user),
+            config: state.config,
+            token_program: state.lp_token_program,
+            associated_token_program: spl_associated_token_account_client::program::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        anchor_amm::instruction::Withdraw {
+            amount,
+            min_x,
+            min_y,
+        },
+    )]
+}
+
+pub fn swap(
+    context: &Context,
+    state: &PoolState,
+    amount: u64,
+    min_out: u64,
+    from_x: bool,
+) -> Vec<Instruction> {
+    let user = context.payer.pubkey();
+
+    let mut instructions = state.create_user_accounts(&user);
+    instructions.push(instruction(
+        anchor_amm::accounts::Swap {
+            user,
+            mint_x: state.mint_x,
+            mint_y: state.mint_y,
+            mint_lp: state.mint_lp,
+            vault_x: state.vault_x,
+            vault_y: state.vault_y,
+            user_x: state.user_x(&user),
+            user_y: state.user_y(&user),
+            config: state.config,
+            oracle: state.oracle,
+            token_program: state.lp_token_program,
+            associated_token_program: spl_associated_token_account_client::program::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        anchor_amm::instruction::Swap {
+            amount_in: amount,
+            min_amount_out: min_out,
+            from_x,
+        },
+    ));
+
+    instructions
+}
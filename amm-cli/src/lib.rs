@@ -0,0 +1,288 @@
+//! Pool operator tool for `native-amm` and `anchor-amm`.
+//!
+//! Every command signs with `--keypair` and talks to `--url`, which default to the
+//! Solana CLI wallet and a local `solana-test-validator`. Token accounts are the
+//! payer's associated token accounts and are created when an instruction needs them.
+//! Pools are named by `--seed`, or by `--mint-x`, `--mint-y` and `--fee-tier` for the
+//! factory's canonical pool for a pair. Withdraws and swaps always name the least
+//! they accept, so none goes out without slippage protection.
+//!
+//! The binary only parses its arguments and calls [`run`]. Commands and the
+//! instructions they build live in this library, where they can be tested
+//! without a cluster.
+
+pub mod anchor;
+pub mod native;
+pub mod pool;
+
+use std::error::Error;
+
+use amm_math::Curve;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::from_account,
+    clock::Clock,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+
+use crate::pool::PoolAddress;
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Parser)]
+#[command(name = "amm-cli", version, about)]
+pub struct Cli {
+    /// RPC endpoint of the cluster
+    #[arg(long, short, global = true, default_value = "http://127.0.0.1:8899")]
+    pub url: String,
+
+    /// Keypair that signs and pays for every transaction
+    #[arg(long, short, global = true, default_value_t = default_keypair())]
+    pub keypair: String,
+
+    /// Which implementation of the AMM to talk to
+    #[arg(long, short, global = true, value_enum, default_value_t = Program::Native)]
+    pub program: Program,
+
+    /// Seconds a deposit, withdraw or swap stays valid for (native program only)
+    #[arg(long, global = true, default_value_t = 60)]
+    pub ttl: i64,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Program {
+    Native,
+    Anchor,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create a pool for two existing mints, with the keypair as its authority
+    CreatePool {
+        #[arg(long)]
+        seed: u64,
+        #[arg(long)]
+        mint_x: Pubkey,
+        #[arg(long)]
+        mint_y: Pubkey,
+        /// Swap fee in basis points
+        #[arg(long)]
+        fee: u16,
+        /// Create a StableSwap pool with this amplification instead of a
+        /// constant-product one
+        #[arg(long, conflicts_with = "weight_x")]
+        amp: Option<u64>,
+        /// Share of the pool's value held in x, in basis points, for a
+        /// weighted pool such as 8000 for 80/20
+        #[arg(long)]
+        weight_x: Option<u16>,
+    },
+    /// Mint `amount` LP for at most `max_x` and `max_y`. The first deposit into an
+    /// empty pool takes both maxima and `amount` is the minimum LP to receive
+    Deposit {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        max_x: u64,
+        #[arg(long)]
+        max_y: u64,
+    },
+    /// Burn `amount` LP for at least `min_x` and `min_y`. Both minima are
+    /// required, so a withdraw never goes out unprotected by default
+    Withdraw {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        min_x: u64,
+        #[arg(long)]
+        min_y: u64,
+    },
+    /// Sell exactly `amount` of x, or of y with `--from-y`, for at least
+    /// `min_out`, which is required for the same reason
+    Swap {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        min_out: u64,
+        #[arg(long)]
+        from_y: bool,
+    },
+    /// Print the pool's config, reserves, LP supply and spot price
+    Show {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+}
+
+/// Names a pool by its seed, or as the factory's canonical pool for a mint
+/// pair at a fee tier
+#[derive(Args, Debug)]
+pub struct PoolArgs {
+    /// Seed the pool was created with
+    #[arg(
+        long,
+        required_unless_present = "fee_tier",
+        conflicts_with = "fee_tier"
+    )]
+    pub seed: Option<u64>,
+    /// One mint of a canonical pool, in either order with `--mint-y`
+    #[arg(long, requires = "fee_tier")]
+    pub mint_x: Option<Pubkey>,
+    /// The other mint of a canonical pool
+    #[arg(long, requires = "fee_tier")]
+    pub mint_y: Option<Pubkey>,
+    /// Fee tier in basis points the canonical pool was created at
+    #[arg(long, requires_all = ["mint_x", "mint_y"])]
+    pub fee_tier: Option<u16>,
+}
+
+impl PoolArgs {
+    pub fn address(&self) -> PoolAddress {
+        match (self.seed, self.mint_x, self.mint_y, self.fee_tier) {
+            (Some(seed), ..) => PoolAddress::Seed(seed),
+            // Canonical pools hold the lower mint as x
+            (None, Some(mint_a), Some(mint_b), Some(fee_tier)) => PoolAddress::Canonical {
+                mint_x: mint_a.min(mint_b),
+                mint_y: mint_a.max(mint_b),
+                fee_tier,
+            },
+            _ => unreachable!("clap requires --seed or --mint-x, --mint-y and --fee-tier"),
+        }
+    }
+}
+
+fn default_keypair() -> String {
+    std::env::var("HOME")
+        .map(|home| format!("{home}/.config/solana/id.json"))
+        .unwrap_or_else(|_| "id.json".to_string())
+}
+
+/// RPC connection and the signer every transaction is paid by
+pub struct Context {
+    pub rpc: RpcClient,
+    pub payer: Keypair,
+    /// Unix time on the cluster's Clock sysvar, which the programs check
+    /// deadlines and amplification ramps against
+    pub now: i64,
+    pub expiration: i64,
+}
+
+impl Context {
+    fn send(&self, instructions: &[Instruction]) -> Result<Signature> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            self.rpc.get_latest_blockhash()?,
+        );
+
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+}
+
+/// Unix time on the cluster, which a local validator started with a warped
+/// clock, or a lagging cluster, does not share with this machine
+fn cluster_time(rpc: &RpcClient) -> Result<i64> {
+    let clock: Clock =
+        from_account(&rpc.get_account(&sysvar::clock::ID)?).ok_or("could not decode the clock")?;
+
+    Ok(clock.unix_timestamp)
+}
+
+/// Runs the command `cli` was parsed into against its cluster
+pub fn run(cli: Cli) -> Result<()> {
+    let rpc = RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed());
+    let now = cluster_time(&rpc)?;
+    let context = Context {
+        rpc,
+        payer: read_keypair_file(&cli.keypair)
+            .map_err(|error| format!("could not read keypair {}: {error}", cli.keypair))?,
+        now,
+        expiration: now + cli.ttl,
+    };
+
+    let load = |pool: PoolArgs| match cli.program {
+        Program::Native => native::load(&context, pool.address()),
+        Program::Anchor => anchor::load(&context, pool.address()),
+    };
+
+    let instructions = match cli.command {
+        Command::CreatePool {
+            seed,
+            mint_x,
+            mint_y,
+            fee,
+            amp,
+            weight_x,
+        } => {
+            let curve = match (amp, weight_x) {
+                (Some(amp), _) => Curve::Stable { amp },
+                (None, Some(weight_x)) => Curve::Weighted { weight_x },
+                (None, None) => Curve::ConstantProduct,
+            };
+            match cli.program {
+                Program::Native => native::create_pool(&context, seed, mint_x, mint_y, fee, curve)?,
+                Program::Anchor => anchor::create_pool(&context, seed, mint_x, mint_y, fee, curve)?,
+            }
+        }
+        Command::Deposit {
+            pool,
+            amount,
+            max_x,
+            max_y,
+        } => {
+            let pool = load(pool)?;
+            match cli.program {
+                Program::Native => native::deposit(&context, &pool, amount, max_x, max_y),
+                Program::Anchor => anchor::deposit(&context, &pool, amount, max_x, max_y),
+            }
+        }
+        Command::Withdraw {
+            pool,
+            amount,
+            min_x,
+            min_y,
+        } => {
+            let pool = load(pool)?;
+            match cli.program {
+                Program::Native => native::withdraw(&context, &pool, amount, min_x, min_y),
+                Program::Anchor => anchor::withdraw(&context, &pool, amount, min_x, min_y),
+            }
+        }
+        Command::Swap {
+            pool,
+            amount,
+            min_out,
+            from_y,
+        } => {
+            let pool = load(pool)?;
+            match cli.program {
+                Program::Native => native::swap(&context, &pool, amount, min_out, !from_y),
+                Program::Anchor => anchor::swap(&context, &pool, amount, min_out, !from_y),
+            }
+        }
+        Command::Show { pool } => {
+            print!("{}", load(pool)?.display(&context)?);
+            return Ok(());
+        }
+    };
+
+    println!("{}", context.send(&instructions)?);
+
+    Ok(())
+}
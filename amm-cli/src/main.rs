@@ -0,0 +1,6 @@
+use amm_cli::{run, Cli, Result};
+use clap::Parser;
+
+fn main() -> Result<()> {
+    run(Cli::parse())
+}
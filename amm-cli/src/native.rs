@@ -0,0 +1,154 @@
+use native_amm_client::{
+    canonical_config_address, config_address, decode_config, deposit_ix, initialize_ix, swap_ix,
+    withdraw_ix, Curve, Pool,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signer};
+
+use crate::pool::{token_program, PoolAddress, PoolState};
+use crate::{Context, Result};
+
+fn pool(state: &PoolState) -> Pool {
+    Pool {
+        seed: state.seed,
+        config: state.config,
+        mint_x: state.mint_x,
+        mint_y: state.mint_y,
+        mint_lp: state.mint_lp,
+        oracle: state.oracle,
+        vault_x: state.vault_x,
+        vault_y: state.vault_y,
+        token_program_x: state.token_program_x,
+        token_program_y: state.token_program_y,
+    }
+}
+
+pub fn load(context: &Context, address: PoolAddress) -> Result<PoolState> {
+    let (config, _) = match address {
+        PoolAddress::Seed(seed) => config_address(seed),
+        PoolAddress::Canonical {
+            mint_x,
+            mint_y,
+            fee_tier,
+        } => canonical_config_address(&mint_x, &mint_y, fee_tier),
+    };
+    let config_account = decode_config(&context.rpc.get_account_data(&config)?)?;
+
+    let token_program_x = token_program(context, &config_account.mint_x)?;
+    let token_program_y = token_program(context, &config_account.mint_y)?;
+    let pool = Pool::from_config(&config_account, token_program_x, token_program_y);
+
+    Ok(PoolState {
+        seed: config_account.seed,
+        fee_tier: (config_account.factory == 1).then_some(config_account.fee_tier),
+        config,
+        authority: config_account.authority,
+        mint_x: pool.mint_x,
+        mint_y: pool.mint_y,
+        mint_lp: pool.mint_lp,
+        oracle: pool.oracle,
+        vault_x: pool.vault_x,
+        vault_y: pool.vault_y,
+        token_program_x,
+        token_program_y,
+        // The native program always issues LP on the original token program
+        lp_token_program: spl_token::ID,
+        fee: config_account.fee,
+        flash_fee: config_account.flash_fee,
+        curve: config_account.curve_at(context.now)?,
+        locked: config_account.locked != 0,
+    })
+}
+
+pub fn create_pool(
+    context: &Context,
+    seed: u64,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    fee: u16,
+    curve: Curve,
+) -> Result<Vec<Instruction>> {
+    let payer = context.payer.pubkey();
+    let pool = Pool::new(
+        seed,
+        mint_x,
+        mint_y,
+        token_program(context, &mint_x)?,
+        token_program(context, &mint_y)?,
+    );
+
+    Ok(vec![initialize_ix(&pool, &payer, fee, &payer, curve)])
+}
+
+pub fn deposit(
+    context: &Context,
+    state: &PoolState,
+    amount: u64,
+    max_x: u64,
+    max_y: u64,
+) -> Vec<Instruction> {
+    let payer = context.payer.pubkey();
+
+    vec![
+        state.create_user_lp(&payer),
+        deposit_ix(
+            &pool(state),
+            &payer,
+            &state.user_x(&payer),
+            &state.user_y(&payer),
+            &state.user_lp(&payer),
+            amount,
+            max_x,
+            max_y,
+            context.expiration,
+        ),
+    ]
+}
+
+pub fn withdraw(
+    context: &Context,
+    state: &PoolState,
+    amount: u64,
+    min_x: u64,
+    min_y: u64,
+) -> Vec<Instruction> {
+    let payer = context.payer.pubkey();
+
+    let mut instructions = state.create_user_accounts(&payer);
+    instructions.push(withdraw_ix(
+        &pool(state),
+        &payer,
+        &state.user_x(&payer),
+        &state.user_y(&payer),
+        &state.user_lp(&payer),
+        amount,
+        min_x,
+        min_y,
+        context.expiration,
+    ));
+
+    instructions
+}
+
+pub fn swap(
+    context: &Context,
+    state: &PoolState,
+    amount: u64,
+    min_out: u64,
+    from_x: bool,
+) -> Vec<Instruction> {
+    let payer = context.payer.pubkey();
+
+    let mut instructions = state.create_user_accounts(&payer);
+    instructions.push(swap_ix(
+        &pool(state),
+        &payer,
+        &state.user_x(&payer),
+        &state.user_y(&payer),
+        amount,
+        min_out,
+        from_x,
+        context.expiration,
+    ));
+
+    instructions
+}
@@ -0,0 +1,161 @@
+use std::fmt::Write;
+
+use amm_math::{weighted::WEIGHT_DENOMINATOR, Curve};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use spl_associated_token_account_client::{
+    address::get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account, Mint},
+};
+
+use crate::{Context, Result};
+
+/// Which pool a command acts on
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PoolAddress {
+    /// A pool created with its own seed
+    Seed(u64),
+    /// The factory's pool for a mint pair at `fee_tier`, with `mint_x` the
+    /// lower of the two
+    Canonical {
+        mint_x: Pubkey,
+        mint_y: Pubkey,
+        fee_tier: u16,
+    },
+}
+
+/// A pool's addresses and settings, read from either program's config account
+pub struct PoolState {
+    pub seed: u64,
+    /// The fee tier of a canonical pool, `None` for a pool with its own seed
+    pub fee_tier: Option<u16>,
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub mint_lp: Pubkey,
+    pub oracle: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+    pub token_program_x: Pubkey,
+    pub token_program_y: Pubkey,
+    pub lp_token_program: Pubkey,
+    pub fee: u16,
+    pub flash_fee: u16,
+    pub curve: Curve,
+    pub locked: bool,
+}
+
+impl PoolState {
+    pub fn user_x(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint_x, &self.token_program_x)
+    }
+
+    pub fn user_y(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint_y, &self.token_program_y)
+    }
+
+    pub fn user_lp(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint_lp, &self.lp_token_program)
+    }
+
+    /// Creates the payer's token accounts for both pool tokens, if they do not exist yet
+    pub fn create_user_accounts(&self, payer: &Pubkey) -> Vec<Instruction> {
+        vec![
+            create_associated_token_account_idempotent(
+                payer,
+                payer,
+                &self.mint_x,
+                &self.token_program_x,
+            ),
+            create_associated_token_account_idempotent(
+                payer,
+                payer,
+                &self.mint_y,
+                &self.token_program_y,
+            ),
+        ]
+    }
+
+    /// Creates the payer's LP token account, if it does not exist yet
+    pub fn create_user_lp(&self, payer: &Pubkey) -> Instruction {
+        create_associated_token_account_idempotent(
+            payer,
+            payer,
+            &self.mint_lp,
+            &self.lp_token_program,
+        )
+    }
+
+    pub fn display(&self, context: &Context) -> Result<String> {
+        let (reserve_x, decimals_x) = reserve(context, &self.vault_x, &self.mint_x)?;
+        let (reserve_y, decimals_y) = reserve(context, &self.vault_y, &self.mint_y)?;
+        let lp_data = context.rpc.get_account_data(&self.mint_lp)?;
+        let lp = StateWithExtensions::<Mint>::unpack(&lp_data)?.base;
+
+        // Q64.64 price of one whole x in whole y
+        let spot_price = match self.curve.spot_prices(reserve_x, reserve_y) {
+            (0, _) => None,
+            (price, _) => Some(
+                price as f64 / 2f64.powi(64) * 10f64.powi(decimals_x as i32 - decimals_y as i32),
+            ),
+        };
+
+        let mut output = String::new();
+        writeln!(output, "Config:        {}", self.config)?;
+        match self.fee_tier {
+            Some(fee_tier) => writeln!(output, "Fee tier:      {fee_tier} bps")?,
+            None => writeln!(output, "Seed:          {}", self.seed)?,
+        }
+        writeln!(output, "Authority:     {}", self.authority)?;
+        writeln!(output, "Mint X:        {}", self.mint_x)?;
+        writeln!(output, "Mint Y:        {}", self.mint_y)?;
+        writeln!(output, "Reserve X:     {reserve_x}")?;
+        writeln!(output, "Reserve Y:     {reserve_y}")?;
+        writeln!(output, "LP mint:       {}", self.mint_lp)?;
+        writeln!(output, "LP supply:     {}", lp.supply)?;
+        writeln!(output, "Oracle:        {}", self.oracle)?;
+        writeln!(output, "Fee:           {} bps", self.fee)?;
+        writeln!(output, "Flash fee:     {} bps", self.flash_fee)?;
+        match self.curve {
+            Curve::ConstantProduct => writeln!(output, "Curve:         constant product")?,
+            Curve::Stable { amp } => writeln!(output, "Curve:         stable, amp {amp}")?,
+            Curve::Weighted { weight_x } => writeln!(
+                output,
+                "Curve:         weighted, {}/{}",
+                weight_x as f64 / 100.0,
+                (WEIGHT_DENOMINATOR - weight_x) as f64 / 100.0
+            )?,
+        }
+        writeln!(output, "Locked:        {}", self.locked)?;
+        match spot_price {
+            Some(price) => writeln!(output, "Spot price:    {price} Y per X")?,
+            None => writeln!(output, "Spot price:    -")?,
+        }
+
+        Ok(output)
+    }
+}
+
+/// Owner of `mint`, i.e. SPL Token or Token-2022
+pub fn token_program(context: &Context, mint: &Pubkey) -> Result<Pubkey> {
+    Ok(context.rpc.get_account(mint)?.owner)
+}
+
+/// Balance of `vault` and the decimals of its `mint`
+fn reserve(context: &Context, vault: &Pubkey, mint: &Pubkey) -> Result<(u64, u8)> {
+    let vault_data = context.rpc.get_account_data(vault)?;
+    let mint_data = context.rpc.get_account_data(mint)?;
+
+    Ok((
+        StateWithExtensions::<Account>::unpack(&vault_data)?
+            .base
+            .amount,
+        StateWithExtensions::<Mint>::unpack(&mint_data)?
+            .base
+            .decimals,
+    ))
+}
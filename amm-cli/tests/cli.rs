@@ -0,0 +1,286 @@
+//! Parses command lines and builds the instructions commands send, neither of
+//! which needs a cluster.
+
+use amm_cli::{
+    anchor, native,
+    pool::{PoolAddress, PoolState},
+    Cli, Command, Context, Program,
+};
+use amm_math::Curve;
+use anchor_lang::InstructionData;
+use clap::{error::ErrorKind, Parser};
+use native_amm_client::{swap_ix, withdraw_ix, Pool};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_associated_token_account_client::address::get_associated_token_address_with_program_id;
+
+const SEED: u64 = 7;
+
+fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from(["amm-cli"].iter().chain(args))
+}
+
+/// A context that is never sent through, so its RPC client never connects
+fn context() -> Context {
+    Context {
+        rpc: RpcClient::new("http://127.0.0.1:8899".to_string()),
+        payer: Keypair::new(),
+        now: 1_000,
+        expiration: 1_060,
+    }
+}
+
+fn pool_state(
+    config: Pubkey,
+    pool: &Pool,
+    mint_lp: Pubkey,
+    oracle: Pubkey,
+    lp_token_program: Pubkey,
+) -> PoolState {
+    PoolState {
+        seed: SEED,
+        fee_tier: None,
+        config,
+        authority: Pubkey::new_unique(),
+        mint_x: pool.mint_x,
+        mint_y: pool.mint_y,
+        mint_lp,
+        oracle,
+        vault_x: pool.vault_x,
+        vault_y: pool.vault_y,
+        token_program_x: pool.token_program_x,
+        token_program_y: pool.token_program_y,
+        lp_token_program,
+        fee: 30,
+        flash_fee: 0,
+        curve: Curve::ConstantProduct,
+        locked: false,
+    }
+}
+
+fn native_pool() -> (Pool, PoolState) {
+    let pool = Pool::new(
+        SEED,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        spl_token::ID,
+        spl_token::ID,
+    );
+    let state = pool_state(pool.config, &pool, pool.mint_lp, pool.oracle, spl_token::ID);
+
+    (pool, state)
+}
+
+fn anchor_pool() -> PoolState {
+    let address = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &anchor_amm::ID).0;
+    let config = address(&[b"config", SEED.to_le_bytes().as_ref()]);
+    let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let vault = |mint| get_associated_token_address_with_program_id(&config, mint, &spl_token::ID);
+
+    let pool = Pool {
+        seed: SEED,
+        config,
+        mint_x,
+        mint_y,
+        mint_lp: address(&[b"lp", config.as_ref()]),
+        oracle: address(&[b"oracle", config.as_ref()]),
+        vault_x: vault(&mint_x),
+        vault_y: vault(&mint_y),
+        token_program_x: spl_token::ID,
+        token_program_y: spl_token::ID,
+    };
+
+    pool_state(config, &pool, pool.mint_lp, pool.oracle, spl_token::ID)
+}
+
+#[test]
+fn swap_and_withdraw_require_their_minimums() {
+    for args in [
+        &["swap", "--seed", "7", "--amount", "100"][..],
+        &["withdraw", "--seed", "7", "--amount", "100", "--min-x", "1"],
+        &["withdraw", "--seed", "7", "--amount", "100", "--min-y", "1"],
+    ] {
+        assert_eq!(
+            parse(args).unwrap_err().kind(),
+            ErrorKind::MissingRequiredArgument
+        );
+    }
+}
+
+#[test]
+fn swap_parses_its_arguments_and_the_global_flags() {
+    let cli = parse(&[
+        "swap",
+        "--seed",
+        "7",
+        "--amount",
+        "100",
+        "--min-out",
+        "95",
+        "--from-y",
+        "--program",
+        "anchor",
+        "--ttl",
+        "30",
+    ])
+    .unwrap();
+
+    assert_eq!(cli.program, Program::Anchor);
+    assert_eq!(cli.ttl, 30);
+    let Command::Swap {
+        pool,
+        amount,
+        min_out,
+        from_y,
+    } = cli.command
+    else {
+        panic!("expected a swap, got {:?}", cli.command);
+    };
+    assert_eq!(pool.address(), PoolAddress::Seed(SEED));
+    assert_eq!((amount, min_out, from_y), (100, 95, true));
+}
+
+#[test]
+fn canonical_pool_is_named_by_its_mints_in_either_order() {
+    let (low, high) = {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        (a.min(b), a.max(b))
+    };
+
+    for (mint_x, mint_y) in [(low, high), (high, low)] {
+        let cli = parse(&[
+            "show",
+            "--mint-x",
+            &mint_x.to_string(),
+            "--mint-y",
+            &mint_y.to_string(),
+            "--fee-tier",
+            "30",
+        ])
+        .unwrap();
+
+        let Command::Show { pool } = cli.command else {
+            panic!("expected show, got {:?}", cli.command);
+        };
+        assert_eq!(
+            pool.address(),
+            PoolAddress::Canonical {
+                mint_x: low,
+                mint_y: high,
+                fee_tier: 30,
+            }
+        );
+    }
+}
+
+#[test]
+fn conflicting_arguments_are_rejected() {
+    let mint = Pubkey::new_unique().to_string();
+
+    for args in [
+        &[
+            "show",
+            "--seed",
+            "7",
+            "--mint-x",
+            &mint,
+            "--mint-y",
+            &mint,
+            "--fee-tier",
+            "30",
+        ][..],
+        &[
+            "create-pool",
+            "--seed",
+            "7",
+            "--mint-x",
+            &mint,
+            "--mint-y",
+            &mint,
+            "--fee",
+            "30",
+            "--amp",
+            "100",
+            "--weight-x",
+            "8000",
+        ],
+    ] {
+        assert_eq!(parse(args).unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    // A canonical pool needs both of its mints
+    assert_eq!(
+        parse(&["show", "--mint-x", &mint, "--fee-tier", "30"])
+            .unwrap_err()
+            .kind(),
+        ErrorKind::MissingRequiredArgument
+    );
+}
+
+#[test]
+fn native_swap_and_withdraw_carry_their_minimums() {
+    let context = context();
+    let payer = context.payer.pubkey();
+    let (pool, state) = native_pool();
+    let (user_x, user_y, user_lp) = (
+        state.user_x(&payer),
+        state.user_y(&payer),
+        state.user_lp(&payer),
+    );
+
+    let instructions = native::swap(&context, &state, 100, 95, false);
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(
+        instructions[2],
+        swap_ix(&pool, &payer, &user_x, &user_y, 100, 95, false, 1_060)
+    );
+
+    let instructions = native::withdraw(&context, &state, 100, 40, 60);
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(
+        instructions[2],
+        withdraw_ix(&pool, &payer, &user_x, &user_y, &user_lp, 100, 40, 60, 1_060)
+    );
+}
+
+#[test]
+fn anchor_swap_and_withdraw_carry_their_minimums() {
+    let context = context();
+    let payer = context.payer.pubkey();
+    let state = anchor_pool();
+
+    let instructions = anchor::swap(&context, &state, 100, 95, true);
+    let swap = instructions.last().unwrap();
+    assert_eq!(swap.program_id, anchor_amm::ID);
+    assert_eq!(
+        swap.data,
+        anchor_amm::instruction::Swap {
+            amount_in: 100,
+            min_amount_out: 95,
+            from_x: true,
+        }
+        .data()
+    );
+    let keys: Vec<_> = swap.accounts.iter().map(|account| account.pubkey).collect();
+    assert!(keys.contains(&state.user_x(&payer)) && keys.contains(&state.user_y(&payer)));
+
+    let instructions = anchor::withdraw(&context, &state, 100, 40, 60);
+    let withdraw = instructions.last().unwrap();
+    assert_eq!(withdraw.program_id, anchor_amm::ID);
+    assert_eq!(
+        withdraw.data,
+        anchor_amm::instruction::Withdraw {
+            amount: 100,
+            min_x: 40,
+            min_y: 60,
+        }
+        .data()
+    );
+    assert!(withdraw
+        .accounts
+        .iter()
+        .any(|account| account.pubkey == state.user_lp(&payer) && account.is_writable));
+}
@@ -0,0 +1,226 @@
+//! Events `native-amm` and `anchor-amm` emit for indexers, and a decoder that
+//! reads them back out of transaction logs.
+//!
+//! Both programs log an event as a `Program data:` line holding, in base64, an
+//! 8-byte discriminator followed by the Borsh-encoded event. The discriminator
+//! is the one Anchor's `#[event]` derives, the first 8 bytes of
+//! `sha256("event:<Name>")`, so one decoder reads both programs.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+pub trait Event: BorshSerialize + BorshDeserialize {
+    const DISCRIMINATOR: [u8; 8];
+
+    /// The discriminator followed by the Borsh-encoded event, as it is logged
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::DISCRIMINATOR.to_vec();
+        // Writing to a `Vec` cannot fail
+        let _ = borsh::to_writer(&mut bytes, self);
+        bytes
+    }
+}
+
+/// A pool was created
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolInitialized {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub authority: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    /// `0` for the weighted product, `1` for StableSwap
+    pub curve: u8,
+    pub weight_x: u16,
+    pub amp: u64,
+}
+
+/// Liquidity was added. The amounts are what the user sent, before any
+/// transfer fee, and the reserves and LP supply are those after the deposit
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Deposit {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_minted: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+/// Liquidity was removed. The amounts are what the vaults sent, before any
+/// transfer fee, and the reserves and LP supply are those after the withdrawal
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Withdraw {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub lp_burned: u64,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+/// A trade against one pool, emitted for each pool a routed swap passes
+/// through. `amount_in` is what reached the input vault and `amount_out` what
+/// left the output vault, and `fee_paid` is the part of `amount_in` the swap
+/// fee kept in the pool. The reserves are those after the swap, with the LP
+/// supply alongside so each event prices the pool's LP token on its own
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Swap {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub from_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_paid: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+/// The authority changed a pool's settings. Carries all of them as they are
+/// after the change, with `user` the signer who made it
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConfigUpdated {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub authority: Pubkey,
+    /// Always the default key in `anchor-amm`, which transfers authority in one step
+    pub pending_authority: Pubkey,
+    pub fee: u16,
+    pub flash_fee: u16,
+    pub locked: bool,
+    pub target_amp: u64,
+    pub ramp_end: i64,
+}
+
+/// The factory admin started or stopped offering `fee` as a fee tier for
+/// canonical pools. Pools already created on it are unaffected
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeeTierSet {
+    pub factory: Pubkey,
+    pub admin: Pubkey,
+    pub fee: u16,
+    pub enabled: bool,
+}
+
+impl Event for PoolInitialized {
+    const DISCRIMINATOR: [u8; 8] = [100, 118, 173, 87, 12, 198, 254, 229];
+}
+
+impl Event for Deposit {
+    const DISCRIMINATOR: [u8; 8] = [62, 205, 242, 175, 244, 169, 136, 52];
+}
+
+impl Event for Withdraw {
+    const DISCRIMINATOR: [u8; 8] = [192, 241, 201, 217, 70, 150, 90, 247];
+}
+
+impl Event for Swap {
+    const DISCRIMINATOR: [u8; 8] = [81, 108, 227, 190, 205, 208, 10, 196];
+}
+
+impl Event for ConfigUpdated {
+    const DISCRIMINATOR: [u8; 8] = [40, 241, 230, 122, 11, 19, 198, 194];
+}
+
+impl Event for FeeTierSet {
+    const DISCRIMINATOR: [u8; 8] = [16, 152, 92, 70, 34, 177, 153, 27];
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AmmEvent {
+    PoolInitialized(PoolInitialized),
+    Deposit(Deposit),
+    Withdraw(Withdraw),
+    Swap(Swap),
+    ConfigUpdated(ConfigUpdated),
+    FeeTierSet(FeeTierSet),
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// A `Program data:` line is not valid base64
+    Base64(base64::DecodeError),
+    /// The bytes after a known discriminator are not that event
+    Borsh(borsh::io::Error),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Base64(e) => write!(f, "invalid base64 in program data: {e}"),
+            Self::Borsh(e) => write!(f, "invalid event data: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn decode<E: Event>(data: &[u8]) -> Result<E, DecodeError> {
+    E::try_from_slice(data).map_err(DecodeError::Borsh)
+}
+
+/// Decodes an event from its logged bytes, or `None` if the discriminator is
+/// not one of these events
+pub fn decode_event(bytes: &[u8]) -> Result<Option<AmmEvent>, DecodeError> {
+    let Some((discriminator, data)) = bytes.split_first_chunk::<8>() else {
+        return Ok(None);
+    };
+
+    Ok(Some(match *discriminator {
+        PoolInitialized::DISCRIMINATOR => AmmEvent::PoolInitialized(decode(data)?),
+        Deposit::DISCRIMINATOR => AmmEvent::Deposit(decode(data)?),
+        Withdraw::DISCRIMINATOR => AmmEvent::Withdraw(decode(data)?),
+        Swap::DISCRIMINATOR => AmmEvent::Swap(decode(data)?),
+        ConfigUpdated::DISCRIMINATOR => AmmEvent::ConfigUpdated(decode(data)?),
+        FeeTierSet::DISCRIMINATOR => AmmEvent::FeeTierSet(decode(data)?),
+        _ => return Ok(None),
+    }))
+}
+
+/// Decodes the events `program_id` emitted, in order, from a transaction's log
+/// messages. Data logged by any other program, including those the AMM calls
+/// into, is skipped
+pub fn parse_logs<S: AsRef<str>>(
+    logs: &[S],
+    program_id: &Pubkey,
+) -> Result<Vec<AmmEvent>, DecodeError> {
+    let program_id = program_id.to_string();
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+
+        if let Some(data) = log.strip_prefix("Program data: ") {
+            if invocations.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+
+            // `sol_log_data` logs each of its slices as its own base64 word
+            let mut bytes = Vec::new();
+            for word in data.split_whitespace() {
+                bytes.extend(STANDARD.decode(word).map_err(DecodeError::Base64)?);
+            }
+
+            events.extend(decode_event(&bytes)?);
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => invocations.push(program),
+                (Some(_), Some("success" | "failed:")) => {
+                    invocations.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(events)
+}
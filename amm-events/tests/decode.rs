@@ -0,0 +1,120 @@
+//! Decodes hand-built transaction logs, including the nesting and the other
+//! programs' output a real transaction interleaves with the AMM's events.
+
+use amm_events::{
+    decode_event, parse_logs, AmmEvent, ConfigUpdated, Deposit, Event, FeeTierSet, PoolInitialized,
+    Swap, Withdraw,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+use solana_program::pubkey::Pubkey;
+
+fn anchor_discriminator(name: &str) -> [u8; 8] {
+    Sha256::digest(format!("event:{name}")).as_slice()[..8]
+        .try_into()
+        .unwrap()
+}
+
+fn data_log(bytes: &[u8]) -> String {
+    format!("Program data: {}", STANDARD.encode(bytes))
+}
+
+fn swap(pool: Pubkey) -> Swap {
+    Swap {
+        pool,
+        user: Pubkey::new_unique(),
+        from_x: true,
+        amount_in: 1_000,
+        amount_out: 996,
+        fee_paid: 3,
+        reserve_x: 1_001_000,
+        reserve_y: 999_004,
+        lp_supply: 1_000_000,
+    }
+}
+
+#[test]
+fn discriminators_are_anchors() {
+    assert_eq!(
+        PoolInitialized::DISCRIMINATOR,
+        anchor_discriminator("PoolInitialized")
+    );
+    assert_eq!(Deposit::DISCRIMINATOR, anchor_discriminator("Deposit"));
+    assert_eq!(Withdraw::DISCRIMINATOR, anchor_discriminator("Withdraw"));
+    assert_eq!(Swap::DISCRIMINATOR, anchor_discriminator("Swap"));
+    assert_eq!(
+        ConfigUpdated::DISCRIMINATOR,
+        anchor_discriminator("ConfigUpdated")
+    );
+    assert_eq!(
+        FeeTierSet::DISCRIMINATOR,
+        anchor_discriminator("FeeTierSet")
+    );
+}
+
+#[test]
+fn parse_logs_keeps_only_the_programs_own_events() {
+    let amm = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let pool = Pubkey::new_unique();
+
+    let first = swap(pool);
+    let second = Swap {
+        from_x: false,
+        ..swap(pool)
+    };
+    let deposit = Deposit {
+        pool,
+        user: Pubkey::new_unique(),
+        amount_x: 10,
+        amount_y: 20,
+        lp_minted: 14,
+        reserve_x: 110,
+        reserve_y: 220,
+        lp_supply: 154,
+    };
+
+    // `sol_log_data` with two slices logs two base64 words
+    let bytes = second.to_bytes();
+    let split = format!(
+        "Program data: {} {}",
+        STANDARD.encode(&bytes[..8]),
+        STANDARD.encode(&bytes[8..])
+    );
+
+    let logs = vec![
+        format!("Program {amm} invoke [1]"),
+        data_log(&first.to_bytes()),
+        format!("Program {other} invoke [2]"),
+        data_log(&swap(pool).to_bytes()),
+        format!("Program {other} consumed 4645 of 190000 compute units"),
+        format!("Program {other} success"),
+        "Program log: not an event".to_string(),
+        split,
+        data_log(b"unknown discriminator"),
+        format!("Program {amm} success"),
+        format!("Program {other} invoke [1]"),
+        data_log(&deposit.to_bytes()),
+        format!("Program {other} success"),
+        format!("Program {amm} invoke [1]"),
+        data_log(&deposit.to_bytes()),
+        format!("Program {amm} failed: custom program error: 0x1"),
+    ];
+
+    assert_eq!(
+        parse_logs(&logs, &amm).unwrap(),
+        vec![
+            AmmEvent::Swap(first),
+            AmmEvent::Swap(second),
+            AmmEvent::Deposit(deposit),
+        ]
+    );
+}
+
+#[test]
+fn decode_event_rejects_a_truncated_event() {
+    let bytes = swap(Pubkey::new_unique()).to_bytes();
+
+    assert!(decode_event(&bytes[..bytes.len() - 1]).is_err());
+    assert_eq!(decode_event(&bytes[..4]).unwrap(), None);
+}
@@ -0,0 +1,109 @@
+//! The pricing function a pool is created with.
+
+use crate::{stable, weighted, MathError};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Curve {
+    /// `x * y = k`
+    ConstantProduct,
+    /// StableSwap for pegged pairs, at the amplification in effect now
+    Stable { amp: u64 },
+    /// `x^wx * y^wy = k`, with x weighted `weight_x` basis points of the pool's
+    /// value. At [`weighted::EVEN_WEIGHT`] this prices as `ConstantProduct`
+    Weighted { weight_x: u16 },
+}
+
+impl Curve {
+    /// Weight of the input token, when x is sold if `from_x`
+    #[inline]
+    fn weight_in(weight_x: u16, from_x: bool) -> u16 {
+        if from_x {
+            weight_x
+        } else {
+            weighted::WEIGHT_DENOMINATOR.saturating_sub(weight_x)
+        }
+    }
+
+    /// See [`crate::swap_exact_in`], [`stable::swap_exact_in`] and
+    /// [`weighted::swap_exact_in`]. The input is x if `from_x`, y otherwise
+    pub fn swap_exact_in(
+        &self,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        fee: u16,
+        from_x: bool,
+    ) -> Result<u64, MathError> {
+        match *self {
+            Curve::ConstantProduct => crate::swap_exact_in(amount_in, reserve_in, reserve_out, fee),
+            Curve::Stable { amp } => {
+                stable::swap_exact_in(amount_in, reserve_in, reserve_out, fee, amp)
+            }
+            Curve::Weighted { weight_x } => weighted::swap_exact_in(
+                amount_in,
+                reserve_in,
+                reserve_out,
+                fee,
+                Self::weight_in(weight_x, from_x),
+            ),
+        }
+    }
+
+    /// See [`crate::swap_exact_out`], [`stable::swap_exact_out`] and
+    /// [`weighted::swap_exact_out`]. The input is x if `from_x`, y otherwise
+    pub fn swap_exact_out(
+        &self,
+        amount_out: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        fee: u16,
+        from_x: bool,
+    ) -> Result<u64, MathError> {
+        match *self {
+            Curve::ConstantProduct => {
+                crate::swap_exact_out(amount_out, reserve_in, reserve_out, fee)
+            }
+            Curve::Stable { amp } => {
+                stable::swap_exact_out(amount_out, reserve_in, reserve_out, fee, amp)
+            }
+            Curve::Weighted { weight_x } => weighted::swap_exact_out(
+                amount_out,
+                reserve_in,
+                reserve_out,
+                fee,
+                Self::weight_in(weight_x, from_x),
+            ),
+        }
+    }
+
+    /// LP minted by the first deposit
+    pub fn initial_liquidity(&self, amount_x: u64, amount_y: u64) -> Result<u64, MathError> {
+        match *self {
+            Curve::ConstantProduct => Ok(crate::initial_liquidity(amount_x, amount_y)),
+            Curve::Stable { amp } => stable::initial_liquidity(amp, amount_x, amount_y),
+            Curve::Weighted { weight_x } => {
+                weighted::initial_liquidity(weight_x, amount_x, amount_y)
+            }
+        }
+    }
+
+    /// Q64.64 spot prices of x in y and of y in x, zero while a reserve is empty
+    pub fn spot_prices(&self, reserve_x: u64, reserve_y: u64) -> (u128, u128) {
+        let spot_price = |reserve_in, reserve_out, from_x| match *self {
+            Curve::ConstantProduct => crate::spot_price(reserve_in, reserve_out),
+            Curve::Stable { amp } => stable::spot_price(reserve_in, reserve_out, amp),
+            Curve::Weighted { weight_x } => {
+                weighted::spot_price(reserve_in, reserve_out, Self::weight_in(weight_x, from_x))
+            }
+        };
+
+        if reserve_x == 0 || reserve_y == 0 {
+            return (0, 0);
+        }
+
+        (
+            spot_price(reserve_x, reserve_y, true).unwrap_or(0),
+            spot_price(reserve_y, reserve_x, false).unwrap_or(0),
+        )
+    }
+}
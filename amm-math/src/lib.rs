@@ -0,0 +1,258 @@
+//! Pool math shared by `native-amm`, `anchor-amm` and off-chain quoters.
+//!
+//! Every function takes raw token amounts, does its intermediate arithmetic in
+//! 256 bits and states which way it rounds. Rounding always favours the pool:
+//! anything a trader or LP receives rounds down, anything they pay rounds up.
+//!
+//! The functions at the crate root price the constant-product curve, those in
+//! [`stable`] the StableSwap curve and those in [`weighted`] the weighted-product
+//! curve, and [`Curve`] picks between them per pool.
+#![no_std]
+
+mod u256 {
+    #![allow(clippy::manual_div_ceil)]
+
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+pub use u256::U256;
+
+pub mod curve;
+pub mod oracle;
+pub mod stable;
+pub mod weighted;
+
+pub use curve::Curve;
+
+/// Fees are expressed in basis points of this denominator
+pub const FEE_DENOMINATOR: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MathError {
+    /// An intermediate or the result does not fit its type
+    Overflow,
+    /// A reserve is empty or the trade would drain it
+    InsufficientLiquidity,
+    /// The fee is not below `FEE_DENOMINATOR`
+    InvalidFee,
+    /// The amount is zero where a positive amount is required
+    ZeroAmount,
+    /// The amplification or its ramp is outside the bounds in [`stable`]
+    InvalidAmp,
+    /// Newton's method did not settle on the invariant
+    NoConvergence,
+    /// A token weight is outside the bounds in [`weighted`]
+    InvalidWeight,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// `a * b / c` with a 256-bit intermediate
+pub fn mul_div(a: u128, b: u128, c: u128, rounding: Rounding) -> Result<u128, MathError> {
+    if c == 0 {
+        return Err(MathError::Overflow);
+    }
+
+    let product = U256::from(a) * U256::from(b);
+    let divisor = U256::from(c);
+    let (mut quotient, remainder) = product.div_mod(divisor);
+
+    if rounding == Rounding::Up && !remainder.is_zero() {
+        quotient += U256::one();
+    }
+
+    if quotient > U256::from(u128::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    Ok(quotient.as_u128())
+}
+
+#[inline]
+fn to_u64(value: u128) -> Result<u64, MathError> {
+    u64::try_from(value).map_err(|_| MathError::Overflow)
+}
+
+#[inline]
+fn check_fee(fee: u16) -> Result<(), MathError> {
+    if fee as u64 >= FEE_DENOMINATOR {
+        return Err(MathError::InvalidFee);
+    }
+
+    Ok(())
+}
+
+/// Output for an exact `amount_in`, with the fee taken from the input.
+///
+/// `out = reserve_out * in * (D - fee) / (reserve_in * D + in * (D - fee))`, rounded down
+pub fn swap_exact_in(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: u16,
+) -> Result<u64, MathError> {
+    check_fee(fee)?;
+
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let amount_in_with_fee = amount_in as u128 * (FEE_DENOMINATOR - fee as u64) as u128;
+    let denominator = reserve_in as u128 * FEE_DENOMINATOR as u128 + amount_in_with_fee;
+
+    to_u64(mul_div(
+        reserve_out as u128,
+        amount_in_with_fee,
+        denominator,
+        Rounding::Down,
+    )?)
+}
+
+/// Input required to receive an exact `amount_out`, with the fee taken from the input.
+///
+/// `in = reserve_in * out * D / ((reserve_out - out) * (D - fee))`, rounded up
+pub fn swap_exact_out(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: u16,
+) -> Result<u64, MathError> {
+    check_fee(fee)?;
+
+    if amount_out == 0 {
+        return Err(MathError::ZeroAmount);
+    }
+
+    if reserve_in == 0 || amount_out >= reserve_out {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let numerator = reserve_in as u128 * FEE_DENOMINATOR as u128;
+    let denominator = (reserve_out - amount_out) as u128 * (FEE_DENOMINATOR - fee as u64) as u128;
+
+    to_u64(mul_div(
+        numerator,
+        amount_out as u128,
+        denominator,
+        Rounding::Up,
+    )?)
+}
+
+/// LP minted by the first deposit: `sqrt(amount_x * amount_y)`, rounded down
+pub fn initial_liquidity(amount_x: u64, amount_y: u64) -> u64 {
+    sqrt(amount_x as u128 * amount_y as u128)
+}
+
+/// Amount of a reserve an LP must deposit to mint `lp_amount`, rounded up
+pub fn deposit_share(lp_amount: u64, reserve: u64, lp_supply: u64) -> Result<u64, MathError> {
+    if reserve == 0 || lp_supply == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    to_u64(mul_div(
+        lp_amount as u128,
+        reserve as u128,
+        lp_supply as u128,
+        Rounding::Up,
+    )?)
+}
+
+/// Amount of a reserve paid out for burning `lp_amount`, rounded down
+pub fn withdraw_share(lp_amount: u64, reserve: u64, lp_supply: u64) -> Result<u64, MathError> {
+    if lp_amount > lp_supply {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    to_u64(mul_div(
+        lp_amount as u128,
+        reserve as u128,
+        lp_supply as u128,
+        Rounding::Down,
+    )?)
+}
+
+/// Fee owed on top of a flash loan of `amount`, `fee` basis points of it, rounded up
+pub fn flash_loan_fee(amount: u64, fee: u16) -> Result<u64, MathError> {
+    check_fee(fee)?;
+
+    to_u64(mul_div(
+        amount as u128,
+        fee as u128,
+        FEE_DENOMINATOR as u128,
+        Rounding::Up,
+    )?)
+}
+
+/// Part of a swap's `amount_in` the fee keeps in the pool, `fee` basis points
+/// of it, rounded up. The rest is what every curve prices the swap on
+pub fn swap_fee(amount_in: u64, fee: u16) -> Result<u64, MathError> {
+    check_fee(fee)?;
+
+    to_u64(mul_div(
+        amount_in as u128,
+        fee as u128,
+        FEE_DENOMINATOR as u128,
+        Rounding::Up,
+    )?)
+}
+
+/// Marginal price of the input token in units of the output token,
+/// as a Q64.64 fixed-point number, rounded down
+pub fn spot_price(reserve_in: u64, reserve_out: u64) -> Result<u128, MathError> {
+    if reserve_in == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    Ok(((reserve_out as u128) << 64) / reserve_in as u128)
+}
+
+/// How far the execution price of a trade is below the spot price, in basis
+/// points, rounded up
+pub fn price_impact(
+    amount_in: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64, MathError> {
+    if amount_in == 0 {
+        return Err(MathError::ZeroAmount);
+    }
+
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    // 1 - (out / in) / (reserve_out / reserve_in)
+    let spot = amount_in as u128 * reserve_out as u128;
+    let execution = amount_out as u128 * reserve_in as u128;
+    let shortfall = spot.saturating_sub(execution);
+
+    to_u64(mul_div(
+        shortfall,
+        FEE_DENOMINATOR as u128,
+        spot,
+        Rounding::Up,
+    )?)
+}
+
+/// Integer square root, rounded down
+pub fn sqrt(value: u128) -> u64 {
+    if value < 2 {
+        return value as u64;
+    }
+
+    let mut root = value;
+    let mut next = value / 2;
+    while next < root {
+        root = next;
+        next = (value / next + next) / 2;
+    }
+
+    root as u64
+}
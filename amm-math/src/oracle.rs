@@ -0,0 +1,118 @@
+//! Time-weighted average prices from cumulative price observations.
+//!
+//! An observation holds, for both directions, the running sum of the Q64.64
+//! spot price multiplied by the seconds it was in effect. The average price
+//! over a window is the difference of the sums at its ends divided by its
+//! length. Sums wrap on overflow, which leaves that difference correct as long
+//! as the window's own sum fits in 128 bits.
+
+use crate::MathError;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Observation {
+    pub timestamp: i64,
+    /// Sum of the price of x in y times the seconds it held
+    pub price_x_cumulative: u128,
+    /// Sum of the price of y in x times the seconds it held
+    pub price_y_cumulative: u128,
+}
+
+impl Observation {
+    /// Carries the sums forward to `timestamp` at the Q64.64 spot prices in
+    /// effect since `self`, as given by [`crate::Curve::spot_prices`]
+    pub fn advance(&self, timestamp: i64, price_x: u128, price_y: u128) -> Self {
+        let elapsed = timestamp.saturating_sub(self.timestamp).max(0) as u128;
+
+        Self {
+            timestamp: timestamp.max(self.timestamp),
+            price_x_cumulative: self
+                .price_x_cumulative
+                .wrapping_add(price_x.wrapping_mul(elapsed)),
+            price_y_cumulative: self
+                .price_y_cumulative
+                .wrapping_add(price_y.wrapping_mul(elapsed)),
+        }
+    }
+
+    /// The sums at `timestamp`, between `self` and the later `next`, given the
+    /// price held constant from one to the other
+    fn interpolate(&self, next: &Self, timestamp: i64) -> Self {
+        let span = next.timestamp.saturating_sub(self.timestamp) as u128;
+        if span == 0 {
+            return *self;
+        }
+
+        let elapsed = timestamp.saturating_sub(self.timestamp) as u128;
+        let step = |from: u128, to: u128| {
+            from.wrapping_add((to.wrapping_sub(from) / span).wrapping_mul(elapsed))
+        };
+
+        Self {
+            timestamp,
+            price_x_cumulative: step(self.price_x_cumulative, next.price_x_cumulative),
+            price_y_cumulative: step(self.price_y_cumulative, next.price_y_cumulative),
+        }
+    }
+}
+
+/// Average Q64.64 prices of x in y and of y in x from `start` to `end`,
+/// rounded down
+pub fn twap(start: &Observation, end: &Observation) -> Result<(u128, u128), MathError> {
+    let elapsed = end
+        .timestamp
+        .checked_sub(start.timestamp)
+        .filter(|elapsed| *elapsed > 0)
+        .ok_or(MathError::ZeroAmount)? as u128;
+
+    Ok((
+        end.price_x_cumulative
+            .wrapping_sub(start.price_x_cumulative)
+            / elapsed,
+        end.price_y_cumulative
+            .wrapping_sub(start.price_y_cumulative)
+            / elapsed,
+    ))
+}
+
+/// The sums at `target`, read from a ring buffer of `capacity` slots holding
+/// `len` observations with the newest in slot `newest`. `current` is the
+/// newest observation advanced to now.
+///
+/// `None` if `target` is before the oldest observation or after `current`.
+pub fn observe(
+    capacity: usize,
+    len: usize,
+    newest: usize,
+    slot: impl Fn(usize) -> Observation,
+    current: &Observation,
+    target: i64,
+) -> Option<Observation> {
+    if len == 0 || len > capacity || target > current.timestamp {
+        return None;
+    }
+
+    // The i-th oldest observation
+    let nth = |i: usize| slot((newest + capacity + 1 - len + i) % capacity);
+
+    let latest = nth(len - 1);
+    if target >= latest.timestamp {
+        return Some(latest.interpolate(current, target));
+    }
+
+    if target < nth(0).timestamp {
+        return None;
+    }
+
+    // nth(low) is at or before target and nth(high) after it
+    let (mut low, mut high) = (0, len - 1);
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if nth(mid).timestamp <= target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    Some(nth(low).interpolate(&nth(high), target))
+}
@@ -0,0 +1,324 @@
+//! Curve's StableSwap invariant for two tokens.
+//!
+//! `4A(x + y) + D = 4AD + D^3 / 4xy` blends the constant sum `x + y = D`,
+//! which trades pegged tokens one for one, with the constant product, which
+//! keeps both reserves from running dry. The amplification `A` sets how far
+//! from the peg the curve stays flat. `D` and the reserve left after a trade
+//! have no closed form and are found by Newton's method, as in Curve.
+
+use crate::{check_fee, mul_div, to_u64, MathError, Rounding, FEE_DENOMINATOR, U256};
+
+/// Bounds on the amplification coefficient
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+
+/// A ramp may at most multiply or divide the amplification by this factor
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+/// Shortest ramp in seconds, so LPs can react before the curve moves far
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+/// Newton's method converges in a handful of steps for any reserves the
+/// programs can hold; this only bounds the loop
+const MAX_ITERATIONS: usize = 255;
+
+#[inline]
+fn converged(next: U256, previous: U256) -> bool {
+    let difference = if next > previous {
+        next - previous
+    } else {
+        previous - next
+    };
+
+    difference <= U256::one()
+}
+
+/// `D` for reserves `x` and `y`, from `D' = (4A S + 2 Dp) D / ((4A - 1) D + 3 Dp)`
+/// with `Dp = D^3 / 4xy`, starting at `S = x + y`
+fn compute_d(amp: u64, x: u128, y: u128) -> Result<U256, MathError> {
+    if x == 0 || y == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let ann = U256::from(amp) * 4;
+    let sum = U256::from(x) + U256::from(y);
+    let xy4 = U256::from(x) * U256::from(y) * 4;
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // One division keeps Dp exact enough for the iteration to settle
+        let d_p = d * d * d / xy4;
+        let previous = d;
+        d = (ann * sum + d_p * 2) * d / ((ann - 1) * d + d_p * 3);
+
+        if converged(d, previous) {
+            return Ok(d);
+        }
+    }
+
+    Err(MathError::NoConvergence)
+}
+
+/// The other reserve for which reserve `x` keeps the invariant at `d`, from
+/// `y' = (y^2 + c) / (2y + b - D)` with `c = D^3 / 16Ax` and `b = x + D / 4A`
+fn compute_y(amp: u64, x: u128, d: U256) -> Result<U256, MathError> {
+    if x == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let ann = U256::from(amp) * 4;
+    let x = U256::from(x);
+    let c = d * d * d / (x * ann * 4);
+    let b = x + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let previous = y;
+        let denominator = (y * U256::from(2) + b)
+            .checked_sub(d)
+            .filter(|denominator| !denominator.is_zero())
+            .ok_or(MathError::NoConvergence)?;
+        y = (y * y + c) / denominator;
+
+        if converged(y, previous) {
+            return Ok(y);
+        }
+    }
+
+    Err(MathError::NoConvergence)
+}
+
+/// Checks an amplification against `MIN_AMP` and `MAX_AMP`
+#[inline]
+pub fn check_amp(amp: u64) -> Result<(), MathError> {
+    if !(MIN_AMP..=MAX_AMP).contains(&amp) {
+        return Err(MathError::InvalidAmp);
+    }
+
+    Ok(())
+}
+
+/// The invariant `D` of a pool holding `reserve_x` and `reserve_y`
+pub fn invariant(amp: u64, reserve_x: u64, reserve_y: u64) -> Result<u128, MathError> {
+    check_amp(amp)?;
+
+    let d = compute_d(amp, reserve_x as u128, reserve_y as u128)?;
+    if d > U256::from(u128::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    Ok(d.as_u128())
+}
+
+/// Output for an exact `amount_in`, with the fee taken from the input as in
+/// [`crate::swap_exact_in`]. The output reserve is rounded up, so the output
+/// is at least one below the exact curve
+pub fn swap_exact_in(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: u16,
+    amp: u64,
+) -> Result<u64, MathError> {
+    check_fee(fee)?;
+    check_amp(amp)?;
+
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let amount_in_with_fee = mul_div(
+        amount_in as u128,
+        (FEE_DENOMINATOR - fee as u64) as u128,
+        FEE_DENOMINATOR as u128,
+        Rounding::Down,
+    )?;
+
+    let d = compute_d(amp, reserve_in as u128, reserve_out as u128)?;
+    let reserve_out_after = compute_y(amp, reserve_in as u128 + amount_in_with_fee, d)?;
+
+    let amount_out = U256::from(reserve_out)
+        .checked_sub(reserve_out_after + 1)
+        .unwrap_or_default();
+
+    to_u64(amount_out.as_u128())
+}
+
+/// Input required to receive an exact `amount_out`, with the fee taken from
+/// the input as in [`crate::swap_exact_out`]. The input reserve is rounded up
+/// before the fee is added, and the fee rounds up too
+pub fn swap_exact_out(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: u16,
+    amp: u64,
+) -> Result<u64, MathError> {
+    check_fee(fee)?;
+    check_amp(amp)?;
+
+    if amount_out == 0 {
+        return Err(MathError::ZeroAmount);
+    }
+
+    if reserve_in == 0 || amount_out >= reserve_out {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let d = compute_d(amp, reserve_in as u128, reserve_out as u128)?;
+    let reserve_in_after = compute_y(amp, (reserve_out - amount_out) as u128, d)?;
+
+    let amount_in = (reserve_in_after + 1).saturating_sub(U256::from(reserve_in));
+    if amount_in > U256::from(u128::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    to_u64(mul_div(
+        amount_in.as_u128(),
+        FEE_DENOMINATOR as u128,
+        (FEE_DENOMINATOR - fee as u64) as u128,
+        Rounding::Up,
+    )?)
+}
+
+/// LP minted by the first deposit: `D` of the deposited amounts, rounded down
+pub fn initial_liquidity(amp: u64, amount_x: u64, amount_y: u64) -> Result<u64, MathError> {
+    to_u64(invariant(amp, amount_x, amount_y)?)
+}
+
+/// LP minted for depositing `amount_x` and `amount_y` in any ratio, in
+/// proportion to how much they grow `D`, rounded down.
+///
+/// Any part of a deposit away from the pool's ratio is an implicit swap, so as
+/// in Curve it pays half the swap fee before `D` is measured. Without that, a
+/// one-sided deposit followed by a balanced withdrawal would be a free swap
+pub fn deposit_liquidity(
+    amount_x: u64,
+    amount_y: u64,
+    reserve_x: u64,
+    reserve_y: u64,
+    lp_supply: u64,
+    fee: u16,
+    amp: u64,
+) -> Result<u64, MathError> {
+    check_fee(fee)?;
+    check_amp(amp)?;
+
+    if reserve_x == 0 || reserve_y == 0 || lp_supply == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let (old_x, old_y) = (reserve_x as u128, reserve_y as u128);
+    let (new_x, new_y) = (old_x + amount_x as u128, old_y + amount_y as u128);
+
+    let d0 = compute_d(amp, old_x, old_y)?;
+    let d1 = compute_d(amp, new_x, new_y)?;
+    if d1 <= d0 {
+        return Err(MathError::ZeroAmount);
+    }
+
+    // Each reserve is charged on its distance from where a balanced deposit
+    // growing D as much would have left it
+    let charge = |old: u128, new: u128| {
+        let ideal = d1 * U256::from(old) / d0;
+        let new = U256::from(new);
+        let difference = if ideal > new {
+            ideal - new
+        } else {
+            new - ideal
+        };
+
+        // Rounded up, so the pool keeps any rounding
+        let denominator = U256::from(2 * FEE_DENOMINATOR);
+        let imbalance_fee = (difference * U256::from(fee) + denominator - 1) / denominator;
+
+        new.saturating_sub(imbalance_fee).as_u128()
+    };
+
+    let d2 = compute_d(amp, charge(old_x, new_x), charge(old_y, new_y))?;
+    if d2 <= d0 {
+        return Err(MathError::ZeroAmount);
+    }
+
+    let minted = U256::from(lp_supply) * (d2 - d0) / d0;
+    if minted > U256::from(u64::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    Ok(minted.as_u64())
+}
+
+/// Marginal price of the input token in units of the output token, as a
+/// Q64.64 fixed-point number, rounded down.
+///
+/// The price is `-dy/dx` along the invariant, `(16Axy + D^3/x) / (16Axy + D^3/y)`
+/// for the input reserve `x`. It is one at balanced reserves and tends to
+/// `y / x`, the constant-product price, as `A` goes to zero
+pub fn spot_price(reserve_in: u64, reserve_out: u64, amp: u64) -> Result<u128, MathError> {
+    check_amp(amp)?;
+
+    let d = compute_d(amp, reserve_in as u128, reserve_out as u128)?;
+    let d3 = d * d * d;
+    let p: U256 = U256::from(amp) * 16 * U256::from(reserve_in) * U256::from(reserve_out);
+
+    let mut numerator = p + d3 / U256::from(reserve_in);
+    let mut denominator = p + d3 / U256::from(reserve_out);
+
+    // Leave 64 bits of headroom for the fixed-point shift
+    let shift = numerator.bits().max(denominator.bits()).saturating_sub(192);
+    numerator >>= shift;
+    denominator >>= shift;
+
+    let price = (numerator << 64) / denominator;
+    if price > U256::from(u128::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    Ok(price.as_u128())
+}
+
+/// Amplification at `now` while it ramps linearly from `initial_amp` at
+/// `ramp_start` to `target_amp` at `ramp_end`
+pub fn current_amp(
+    initial_amp: u64,
+    target_amp: u64,
+    ramp_start: i64,
+    ramp_end: i64,
+    now: i64,
+) -> u64 {
+    if now >= ramp_end || ramp_end <= ramp_start {
+        return target_amp;
+    }
+
+    if now <= ramp_start {
+        return initial_amp;
+    }
+
+    let elapsed = (now - ramp_start) as u128;
+    let duration = (ramp_end - ramp_start) as u128;
+
+    if target_amp > initial_amp {
+        initial_amp + ((target_amp - initial_amp) as u128 * elapsed / duration) as u64
+    } else {
+        initial_amp - ((initial_amp - target_amp) as u128 * elapsed / duration) as u64
+    }
+}
+
+/// Checks that ramping from `amp` at `now` to `target_amp` at `ramp_end`
+/// stays within the bounds, takes at least `MIN_RAMP_DURATION` and changes
+/// the amplification by at most `MAX_AMP_CHANGE` times
+pub fn check_ramp(amp: u64, target_amp: u64, now: i64, ramp_end: i64) -> Result<(), MathError> {
+    check_amp(target_amp)?;
+
+    if ramp_end.saturating_sub(now) < MIN_RAMP_DURATION {
+        return Err(MathError::InvalidAmp);
+    }
+
+    if target_amp > amp.saturating_mul(MAX_AMP_CHANGE)
+        || amp > target_amp.saturating_mul(MAX_AMP_CHANGE)
+    {
+        return Err(MathError::InvalidAmp);
+    }
+
+    Ok(())
+}
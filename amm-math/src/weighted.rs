@@ -0,0 +1,268 @@
+//! Balancer's weighted-product invariant for two tokens.
+//!
+//! `x^wx * y^wy = k` with `wx + wy = 1` keeps a fixed share of the pool's value
+//! in each token, `wx` in x and `wy` in y, so an 80/20 pool holds four times as
+//! much value in x as in y. At even weights it is the constant product `x * y = k`
+//! and every function here defers to the crate root, so those pools price exactly
+//! as before. Elsewhere the non-integer powers are taken as `exp(w * ln(b))` in
+//! fixed point, with a relative margin that always leans towards the pool.
+
+use crate::{check_fee, mul_div, to_u64, MathError, Rounding, FEE_DENOMINATOR, U256};
+
+/// Weights are expressed in basis points of this denominator
+pub const WEIGHT_DENOMINATOR: u16 = 10_000;
+
+/// The weight of each token in a constant-product pool
+pub const EVEN_WEIGHT: u16 = WEIGHT_DENOMINATOR / 2;
+
+/// Bounds on either token's weight, which keep the exponent of a swap within 99
+pub const MIN_WEIGHT: u16 = 100;
+pub const MAX_WEIGHT: u16 = WEIGHT_DENOMINATOR - MIN_WEIGHT;
+
+/// Fractional bits of the fixed-point numbers the powers are taken in
+const SCALE: usize = 96;
+
+/// `1` in fixed point
+const ONE: U256 = U256([0, 1 << 32, 0, 0]);
+
+/// `ln(2)` in fixed point, rounded down
+const LN2: u128 = 0xB172_17F7_D1CF_79AB_C9E3_B398;
+
+/// Powers from `2^MAX_EXP_SHIFT` up overflow, which is beyond any reserve ratio
+const MAX_EXP_SHIFT: u64 = 64;
+
+/// The fixed-point power is within `2^-84` of the exact one, so a margin of
+/// `2^-76` of the result covers it and still leaves a trade of any size in
+/// a pool of any depth within a unit of the exact price
+const POW_MARGIN_SHIFT: usize = 76;
+
+/// `ln(x)` of a fixed-point `x >= 1`, rounded down
+fn ln(x: U256) -> U256 {
+    // x = m * 2^k with m in [1, 2)
+    let k = x.bits() - SCALE - 1;
+    let m = x >> k;
+
+    // ln(m) = 2 atanh(z) = 2 (z + z^3 / 3 + z^5 / 5 + ...) with z = (m - 1) / (m + 1),
+    // which stays below 1/3 so every term is a ninth of the one before
+    let z = ((m - ONE) << SCALE) / (m + ONE);
+    let z2 = (z * z) >> SCALE;
+
+    let mut sum = U256::zero();
+    let mut term = z;
+    let mut n = 1u64;
+    while !term.is_zero() {
+        sum += term / n;
+        term = (term * z2) >> SCALE;
+        n += 2;
+    }
+
+    U256::from(LN2) * k + (sum << 1)
+}
+
+/// `e^y` of a fixed-point `y >= 0`, rounded down
+fn exp(y: U256) -> Result<U256, MathError> {
+    // e^y = 2^k e^r with r = y - k ln(2) in [0, ln(2))
+    let k = y / U256::from(LN2);
+    if k >= U256::from(MAX_EXP_SHIFT) {
+        return Err(MathError::Overflow);
+    }
+
+    let r = y - k * U256::from(LN2);
+
+    let mut sum = ONE;
+    let mut term = ONE;
+    let mut n = 1u64;
+    while !term.is_zero() {
+        term = ((term * r) >> SCALE) / n;
+        sum += term;
+        n += 1;
+    }
+
+    Ok(sum << k.as_usize())
+}
+
+/// `base^(numerator / denominator)` of a fixed-point `base >= 1`
+fn pow(base: U256, numerator: u16, denominator: u16) -> Result<U256, MathError> {
+    exp(ln(base) * numerator / denominator)
+}
+
+/// [`pow`] less its margin, so never above the exact power
+fn pow_down(base: U256, numerator: u16, denominator: u16) -> Result<U256, MathError> {
+    let power = pow(base, numerator, denominator)?;
+
+    Ok(power.saturating_sub((power >> POW_MARGIN_SHIFT) + 1))
+}
+
+/// [`pow`] plus its margin, so never below the exact power
+fn pow_up(base: U256, numerator: u16, denominator: u16) -> Result<U256, MathError> {
+    let power = pow(base, numerator, denominator)?;
+
+    Ok(power + (power >> POW_MARGIN_SHIFT) + 1)
+}
+
+/// `a / b`, rounded up
+#[inline]
+fn div_up(a: U256, b: U256) -> U256 {
+    let (quotient, remainder) = a.div_mod(b);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
+
+/// Checks a weight against `MIN_WEIGHT` and `MAX_WEIGHT`
+#[inline]
+pub fn check_weight(weight: u16) -> Result<(), MathError> {
+    if !(MIN_WEIGHT..=MAX_WEIGHT).contains(&weight) {
+        return Err(MathError::InvalidWeight);
+    }
+
+    Ok(())
+}
+
+/// Output for an exact `amount_in`, with the fee taken from the input as in
+/// [`crate::swap_exact_in`]. The input token has weight `weight_in` and the
+/// output token the rest.
+///
+/// `out = reserve_out * (1 - (reserve_in / (reserve_in + in))^(w_in / w_out))`,
+/// rounded down
+pub fn swap_exact_in(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: u16,
+    weight_in: u16,
+) -> Result<u64, MathError> {
+    check_weight(weight_in)?;
+
+    if weight_in == EVEN_WEIGHT {
+        return crate::swap_exact_in(amount_in, reserve_in, reserve_out, fee);
+    }
+
+    check_fee(fee)?;
+
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let amount_in_with_fee = mul_div(
+        amount_in as u128,
+        (FEE_DENOMINATOR - fee as u64) as u128,
+        FEE_DENOMINATOR as u128,
+        Rounding::Down,
+    )?;
+
+    // The output reserve left is reserve_out / ((reserve_in + in) / reserve_in)^(w_in / w_out),
+    // rounded up by taking the power and its base low. A power too large to
+    // represent leaves less than one unit of it
+    let base = ((U256::from(reserve_in) + U256::from(amount_in_with_fee)) << SCALE)
+        / U256::from(reserve_in);
+    let reserve_out_after = match pow_down(base, weight_in, WEIGHT_DENOMINATOR - weight_in) {
+        Ok(power) => div_up(U256::from(reserve_out) << SCALE, power),
+        Err(_) => U256::one(),
+    };
+
+    let amount_out = U256::from(reserve_out).saturating_sub(reserve_out_after);
+
+    Ok(amount_out.as_u64())
+}
+
+/// Input required to receive an exact `amount_out`, with the fee taken from the
+/// input as in [`crate::swap_exact_out`]. The input token has weight `weight_in`
+/// and the output token the rest.
+///
+/// `in = reserve_in * ((reserve_out / (reserve_out - out))^(w_out / w_in) - 1) / (1 - fee)`,
+/// rounded up
+pub fn swap_exact_out(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee: u16,
+    weight_in: u16,
+) -> Result<u64, MathError> {
+    check_weight(weight_in)?;
+
+    if weight_in == EVEN_WEIGHT {
+        return crate::swap_exact_out(amount_out, reserve_in, reserve_out, fee);
+    }
+
+    check_fee(fee)?;
+
+    if amount_out == 0 {
+        return Err(MathError::ZeroAmount);
+    }
+
+    if reserve_in == 0 || amount_out >= reserve_out {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let base = div_up(
+        U256::from(reserve_out) << SCALE,
+        U256::from(reserve_out - amount_out),
+    );
+    let power = pow_up(base, WEIGHT_DENOMINATOR - weight_in, weight_in)?;
+
+    let amount_in = div_up(U256::from(reserve_in) * (power - ONE), ONE);
+    if amount_in > U256::from(u128::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    to_u64(mul_div(
+        amount_in.as_u128(),
+        FEE_DENOMINATOR as u128,
+        (FEE_DENOMINATOR - fee as u64) as u128,
+        Rounding::Up,
+    )?)
+}
+
+/// LP minted by the first deposit: the weighted geometric mean
+/// `amount_x^wx * amount_y^wy`, rounded down. At even weights this is
+/// [`crate::initial_liquidity`]
+pub fn initial_liquidity(weight_x: u16, amount_x: u64, amount_y: u64) -> Result<u64, MathError> {
+    check_weight(weight_x)?;
+
+    if weight_x == EVEN_WEIGHT {
+        return Ok(crate::initial_liquidity(amount_x, amount_y));
+    }
+
+    if amount_x == 0 || amount_y == 0 {
+        return Ok(0);
+    }
+
+    // small * (large / small)^w_large, so the power's base is at least one
+    let (small, large, weight_large) = if amount_x < amount_y {
+        (amount_x, amount_y, WEIGHT_DENOMINATOR - weight_x)
+    } else {
+        (amount_y, amount_x, weight_x)
+    };
+
+    let base = (U256::from(large) << SCALE) / U256::from(small);
+    let power = pow_down(base, weight_large, WEIGHT_DENOMINATOR)?;
+
+    // Never above `large`, as the power is never above the exact one
+    Ok(((U256::from(small) * power) >> SCALE).as_u64())
+}
+
+/// Marginal price of the input token in units of the output token, as a
+/// Q64.64 fixed-point number, rounded down.
+///
+/// The price is `(reserve_out / w_out) / (reserve_in / w_in)`: the weights tilt
+/// the constant-product price towards the heavier token
+pub fn spot_price(reserve_in: u64, reserve_out: u64, weight_in: u16) -> Result<u128, MathError> {
+    check_weight(weight_in)?;
+
+    if reserve_in == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let weight_out = WEIGHT_DENOMINATOR - weight_in;
+    let price = ((U256::from(reserve_out) * U256::from(weight_in)) << 64)
+        / (U256::from(reserve_in) * U256::from(weight_out));
+
+    if price > U256::from(u128::MAX) {
+        return Err(MathError::Overflow);
+    }
+
+    Ok(price.as_u128())
+}
@@ -0,0 +1,524 @@
+//! Property tests for the invariants both programs rely on.
+//!
+//! Reserves and amounts are drawn from the full `u64` range. A case is
+//! discarded only when its setup could never happen on-chain, such as a
+//! vault balance that would exceed `u64::MAX`.
+
+use amm_math::oracle::{observe, twap, Observation};
+use amm_math::stable::{self, MAX_AMP, MIN_AMP};
+use amm_math::weighted::{self, EVEN_WEIGHT, MAX_WEIGHT, MIN_WEIGHT, WEIGHT_DENOMINATOR};
+use amm_math::{
+    deposit_share, flash_loan_fee, initial_liquidity, mul_div, price_impact, spot_price, sqrt,
+    swap_exact_in, swap_exact_out, swap_fee, withdraw_share, Curve, MathError, Rounding,
+    FEE_DENOMINATOR, U256,
+};
+use proptest::prelude::*;
+
+fn fee() -> impl Strategy<Value = u16> {
+    0..FEE_DENOMINATOR as u16
+}
+
+fn reserve() -> impl Strategy<Value = u64> {
+    prop_oneof![1..=1_000_000u64, 1..=u64::MAX]
+}
+
+/// Reserves whose prices stay far enough below 2^128 for the oracle's sums
+/// over any tested window to fit in 128 bits
+fn oracle_reserve() -> impl Strategy<Value = u64> {
+    1..=1_000_000_000_000u64
+}
+
+fn observation() -> impl Strategy<Value = Observation> {
+    (0..=1_000_000_000i64, any::<u128>(), any::<u128>()).prop_map(
+        |(timestamp, price_x_cumulative, price_y_cumulative)| Observation {
+            timestamp,
+            price_x_cumulative,
+            price_y_cumulative,
+        },
+    )
+}
+
+fn amp() -> impl Strategy<Value = u64> {
+    prop_oneof![MIN_AMP..=1_000, MIN_AMP..=MAX_AMP]
+}
+
+fn weight() -> impl Strategy<Value = u16> {
+    MIN_WEIGHT..=MAX_WEIGHT
+}
+
+fn k(reserve_in: u64, reserve_out: u64) -> U256 {
+    U256::from(reserve_in) * U256::from(reserve_out)
+}
+
+proptest! {
+    #[test]
+    fn swap_exact_in_never_decreases_k(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount_in in any::<u64>(),
+        fee in fee(),
+    ) {
+        prop_assume!(reserve_in.checked_add(amount_in).is_some());
+
+        let amount_out = swap_exact_in(amount_in, reserve_in, reserve_out, fee).unwrap();
+
+        prop_assert!(amount_out < reserve_out);
+        prop_assert!(
+            k(reserve_in + amount_in, reserve_out - amount_out) >= k(reserve_in, reserve_out)
+        );
+    }
+
+    #[test]
+    fn swap_exact_out_never_decreases_k(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount_out in any::<u64>(),
+        fee in fee(),
+    ) {
+        let amount_in = match swap_exact_out(amount_out, reserve_in, reserve_out, fee) {
+            Ok(amount_in) => amount_in,
+            Err(error) => {
+                match error {
+                    MathError::ZeroAmount => prop_assert_eq!(amount_out, 0),
+                    MathError::InsufficientLiquidity => prop_assert!(amount_out >= reserve_out),
+                    MathError::Overflow => {}
+                    MathError::InvalidFee => prop_assert!(false, "fee {} is valid", fee),
+                    MathError::InvalidAmp | MathError::NoConvergence | MathError::InvalidWeight => {
+                        prop_assert!(false, "constant product returned {:?}", error)
+                    }
+                }
+                return Ok(());
+            }
+        };
+        prop_assume!(reserve_in.checked_add(amount_in).is_some());
+
+        prop_assert!(
+            k(reserve_in + amount_in, reserve_out - amount_out) >= k(reserve_in, reserve_out)
+        );
+    }
+
+    #[test]
+    fn swap_exact_out_pays_at_least_the_exact_in_price(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount_out in 1..=u64::MAX,
+        fee in fee(),
+    ) {
+        let Ok(amount_in) = swap_exact_out(amount_out, reserve_in, reserve_out, fee) else {
+            return Ok(());
+        };
+        prop_assume!(reserve_in.checked_add(amount_in).is_some());
+
+        prop_assert!(swap_exact_in(amount_in, reserve_in, reserve_out, fee).unwrap() >= amount_out);
+    }
+
+    #[test]
+    fn round_trip_swap_never_profits(
+        reserve_x in reserve(),
+        reserve_y in reserve(),
+        amount_in in any::<u64>(),
+        fee in fee(),
+    ) {
+        prop_assume!(reserve_x.checked_add(amount_in).is_some());
+
+        let amount_y = swap_exact_in(amount_in, reserve_x, reserve_y, fee).unwrap();
+        let amount_x = swap_exact_in(
+            amount_y,
+            reserve_y - amount_y,
+            reserve_x + amount_in,
+            fee,
+        ).unwrap();
+
+        prop_assert!(amount_x <= amount_in);
+    }
+
+    #[test]
+    fn deposit_then_withdraw_never_profits(
+        reserve_x in reserve(),
+        reserve_y in reserve(),
+        lp_supply in reserve(),
+        lp_amount in any::<u64>(),
+    ) {
+        let (Ok(amount_x), Ok(amount_y)) = (
+            deposit_share(lp_amount, reserve_x, lp_supply),
+            deposit_share(lp_amount, reserve_y, lp_supply),
+        ) else {
+            return Ok(());
+        };
+        let (Some(reserve_x), Some(reserve_y), Some(lp_supply)) = (
+            reserve_x.checked_add(amount_x),
+            reserve_y.checked_add(amount_y),
+            lp_supply.checked_add(lp_amount),
+        ) else {
+            return Ok(());
+        };
+
+        prop_assert!(withdraw_share(lp_amount, reserve_x, lp_supply).unwrap() <= amount_x);
+        prop_assert!(withdraw_share(lp_amount, reserve_y, lp_supply).unwrap() <= amount_y);
+    }
+
+    #[test]
+    fn withdrawals_never_exceed_the_reserve(
+        reserve in any::<u64>(),
+        lp_supply in reserve(),
+        lp_amount in any::<u64>(),
+    ) {
+        prop_assume!(lp_amount <= lp_supply);
+
+        let amount = withdraw_share(lp_amount, reserve, lp_supply).unwrap();
+
+        prop_assert!(amount <= reserve);
+        prop_assert!(lp_amount < lp_supply || amount == reserve);
+    }
+
+    #[test]
+    fn initial_liquidity_is_the_floor_of_the_geometric_mean(
+        amount_x in any::<u64>(),
+        amount_y in any::<u64>(),
+    ) {
+        let liquidity = initial_liquidity(amount_x, amount_y) as u128;
+        let product = amount_x as u128 * amount_y as u128;
+
+        prop_assert!(liquidity * liquidity <= product);
+        prop_assert!(U256::from(liquidity + 1) * U256::from(liquidity + 1) > U256::from(product));
+    }
+
+    #[test]
+    fn flash_loan_fee_rounds_up(amount in any::<u64>(), fee in fee()) {
+        let owed = flash_loan_fee(amount, fee).unwrap();
+        let exact = amount as u128 * fee as u128;
+
+        prop_assert!(owed as u128 * FEE_DENOMINATOR as u128 >= exact);
+        prop_assert!(owed == 0 || (owed as u128 - 1) * (FEE_DENOMINATOR as u128) < exact);
+    }
+
+    #[test]
+    fn swap_fee_and_the_priced_input_add_up_to_the_input(amount_in in any::<u64>(), fee in fee()) {
+        let priced = mul_div(
+            amount_in as u128,
+            (FEE_DENOMINATOR - fee as u64) as u128,
+            FEE_DENOMINATOR as u128,
+            Rounding::Down,
+        )
+        .unwrap();
+
+        prop_assert_eq!(swap_fee(amount_in, fee).unwrap() as u128 + priced, amount_in as u128);
+    }
+
+    #[test]
+    fn mul_div_rounds_in_the_requested_direction(
+        a in any::<u128>(),
+        b in any::<u128>(),
+        c in 1..=u128::MAX,
+    ) {
+        let (Ok(down), Ok(up)) = (
+            mul_div(a, b, c, Rounding::Down),
+            mul_div(a, b, c, Rounding::Up),
+        ) else {
+            return Ok(());
+        };
+        let product = U256::from(a) * U256::from(b);
+
+        prop_assert!(U256::from(down) * U256::from(c) <= product);
+        prop_assert!(U256::from(up) * U256::from(c) >= product);
+        prop_assert!(up - down <= 1);
+    }
+
+    #[test]
+    fn no_input_panics(
+        a in any::<u64>(),
+        b in any::<u64>(),
+        c in any::<u64>(),
+        d in any::<u64>(),
+        fee in any::<u16>(),
+        wide in any::<u128>(),
+    ) {
+        let _ = swap_exact_in(a, b, c, fee);
+        let _ = swap_exact_out(a, b, c, fee);
+        let _ = deposit_share(a, b, c);
+        let _ = withdraw_share(a, b, c);
+        let _ = spot_price(a, b);
+        let _ = price_impact(a, b, c, d);
+        let _ = flash_loan_fee(a, fee);
+        let _ = swap_fee(a, fee);
+        let _ = initial_liquidity(a, b);
+        let _ = sqrt(wide);
+        let _ = mul_div(wide, wide, a as u128, Rounding::Up);
+        let _ = stable::swap_exact_in(a, b, c, fee, d);
+        let _ = stable::swap_exact_out(a, b, c, fee, d);
+        let _ = stable::deposit_liquidity(a, b, c, d, a, fee, d);
+        let _ = stable::spot_price(a, b, d);
+        let _ = stable::initial_liquidity(d, a, b);
+        let _ = weighted::swap_exact_in(a, b, c, fee, d as u16);
+        let _ = weighted::swap_exact_out(a, b, c, fee, d as u16);
+        let _ = weighted::spot_price(a, b, d as u16);
+        let _ = weighted::initial_liquidity(d as u16, a, b);
+    }
+
+    #[test]
+    fn twap_of_a_constant_price_is_the_spot_price(
+        reserve_x in oracle_reserve(),
+        reserve_y in oracle_reserve(),
+        start in observation(),
+        gaps in prop::collection::vec(1..=1_000i64, 1..20),
+        window in 1..=20_000i64,
+    ) {
+        // A ring buffer of 8 slots, overwritten as it fills
+        let mut slots = [Observation::default(); 8];
+        let (mut newest, mut len) = (0, 1);
+        slots[0] = start;
+        let (price_x, price_y) = Curve::ConstantProduct.spot_prices(reserve_x, reserve_y);
+        for gap in gaps {
+            let next = slots[newest].advance(slots[newest].timestamp + gap, price_x, price_y);
+            newest = (newest + 1) % slots.len();
+            len = (len + 1).min(slots.len());
+            slots[newest] = next;
+        }
+
+        let current = slots[newest].advance(slots[newest].timestamp + 10, price_x, price_y);
+        let Some(past) = observe(slots.len(), len, newest, |i| slots[i], &current, current.timestamp - window) else {
+            let oldest = slots[(newest + 1 + slots.len() - len) % slots.len()];
+            prop_assert!(current.timestamp - window < oldest.timestamp);
+            return Ok(());
+        };
+
+        prop_assert_eq!(
+            twap(&past, &current),
+            Ok((spot_price(reserve_x, reserve_y).unwrap(), spot_price(reserve_y, reserve_x).unwrap()))
+        );
+    }
+
+    #[test]
+    fn twap_weights_each_price_by_how_long_it_held(
+        first in (oracle_reserve(), oracle_reserve(), 1..=100_000i64),
+        second in (oracle_reserve(), oracle_reserve(), 1..=100_000i64),
+    ) {
+        let start = Observation::default();
+        let prices = |reserve_x, reserve_y| Curve::ConstantProduct.spot_prices(reserve_x, reserve_y);
+        let (first_x, first_y) = prices(first.0, first.1);
+        let (second_x, second_y) = prices(second.0, second.1);
+        let middle = start.advance(first.2, first_x, first_y);
+        let end = middle.advance(first.2 + second.2, second_x, second_y);
+
+        let weighted = U256::from(spot_price(first.0, first.1).unwrap()) * U256::from(first.2)
+            + U256::from(spot_price(second.0, second.1).unwrap()) * U256::from(second.2);
+        let expected = weighted / U256::from(first.2 + second.2);
+
+        let (price_x, _) = twap(&start, &end).unwrap();
+        prop_assert_eq!(U256::from(price_x), expected);
+    }
+
+    #[test]
+    fn stable_swap_exact_in_never_decreases_d(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount_in in any::<u64>(),
+        fee in fee(),
+        amp in amp(),
+    ) {
+        prop_assume!(reserve_in.checked_add(amount_in).is_some());
+
+        let amount_out = stable::swap_exact_in(amount_in, reserve_in, reserve_out, fee, amp).unwrap();
+
+        // Both sides are solved to within one unit
+        prop_assert!(amount_out < reserve_out);
+        prop_assert!(
+            stable::invariant(amp, reserve_in + amount_in, reserve_out - amount_out).unwrap() + 1
+                >= stable::invariant(amp, reserve_in, reserve_out).unwrap()
+        );
+    }
+
+    #[test]
+    fn stable_swap_exact_out_pays_at_least_the_exact_in_price(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount_out in any::<u64>(),
+        fee in fee(),
+        amp in amp(),
+    ) {
+        let Ok(amount_in) = stable::swap_exact_out(amount_out, reserve_in, reserve_out, fee, amp) else {
+            return Ok(());
+        };
+        prop_assume!(reserve_in.checked_add(amount_in).is_some());
+
+        prop_assert!(
+            stable::swap_exact_in(amount_in, reserve_in, reserve_out, fee, amp).unwrap() >= amount_out
+        );
+    }
+
+    #[test]
+    fn stable_pool_trades_closer_to_the_peg_than_constant_product(
+        reserve in 1_000_000..=1_000_000_000_000_000u64,
+        share in 1..=100u64,
+        fee in fee(),
+        amp in amp(),
+    ) {
+        let amount_in = reserve / 1_000 * share;
+
+        let stable_out = stable::swap_exact_in(amount_in, reserve, reserve, fee, amp).unwrap();
+        let constant_product_out = swap_exact_in(amount_in, reserve, reserve, fee).unwrap();
+
+        prop_assert!(stable_out <= amount_in);
+        prop_assert!(stable_out + 1 >= constant_product_out);
+        prop_assert_eq!(stable::spot_price(reserve, reserve, amp), Ok(1 << 64));
+    }
+
+    #[test]
+    fn stable_deposit_never_dilutes_existing_lps(
+        reserve_x in 1..=1_000_000_000_000_000u64,
+        reserve_y in 1..=1_000_000_000_000_000u64,
+        lp_supply in 1..=1_000_000_000_000_000u64,
+        amount_x in 0..=1_000_000_000_000_000u64,
+        amount_y in 0..=1_000_000_000_000_000u64,
+        fee in fee(),
+        amp in amp(),
+    ) {
+        let Ok(lp_amount) = stable::deposit_liquidity(
+            amount_x, amount_y, reserve_x, reserve_y, lp_supply, fee, amp,
+        ) else {
+            return Ok(());
+        };
+
+        // D per LP token never falls
+        let before = stable::invariant(amp, reserve_x, reserve_y).unwrap();
+        let after = stable::invariant(amp, reserve_x + amount_x, reserve_y + amount_y).unwrap();
+        prop_assert!(
+            U256::from(after + 1) * U256::from(lp_supply)
+                >= U256::from(before) * U256::from(lp_supply + lp_amount)
+        );
+    }
+
+    #[test]
+    fn amp_ramps_monotonically_between_its_ends(
+        initial_amp in amp(),
+        target_amp in amp(),
+        ramp_start in 0..=1_000_000_000i64,
+        duration in 1..=10_000_000i64,
+        first in 0..=20_000_000i64,
+        second in 0..=20_000_000i64,
+    ) {
+        let ramp_end = ramp_start + duration;
+        let (earlier, later) = (ramp_start - 1 + first.min(second), ramp_start - 1 + first.max(second));
+        let amp_at = |now| stable::current_amp(initial_amp, target_amp, ramp_start, ramp_end, now);
+
+        let (low, high) = (initial_amp.min(target_amp), initial_amp.max(target_amp));
+        prop_assert!((low..=high).contains(&amp_at(earlier)));
+        prop_assert_eq!(amp_at(ramp_start), initial_amp);
+        prop_assert_eq!(amp_at(ramp_end), target_amp);
+        if target_amp >= initial_amp {
+            prop_assert!(amp_at(earlier) <= amp_at(later));
+        } else {
+            prop_assert!(amp_at(earlier) >= amp_at(later));
+        }
+    }
+
+    #[test]
+    fn weighted_round_trip_swap_never_profits(
+        reserve_x in reserve(),
+        reserve_y in reserve(),
+        amount_in in any::<u64>(),
+        fee in fee(),
+        weight_x in weight(),
+    ) {
+        prop_assume!(reserve_x.checked_add(amount_in).is_some());
+
+        let amount_y =
+            weighted::swap_exact_in(amount_in, reserve_x, reserve_y, fee, weight_x).unwrap();
+        prop_assert!(amount_y < reserve_y);
+
+        let amount_x = weighted::swap_exact_in(
+            amount_y,
+            reserve_y - amount_y,
+            reserve_x + amount_in,
+            fee,
+            WEIGHT_DENOMINATOR - weight_x,
+        ).unwrap();
+
+        prop_assert!(amount_x <= amount_in);
+    }
+
+    #[test]
+    fn weighted_swap_exact_out_pays_at_least_the_exact_in_price(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount_out in any::<u64>(),
+        fee in fee(),
+        weight_in in weight(),
+    ) {
+        let Ok(amount_in) =
+            weighted::swap_exact_out(amount_out, reserve_in, reserve_out, fee, weight_in)
+        else {
+            return Ok(());
+        };
+        prop_assume!(reserve_in.checked_add(amount_in).is_some());
+
+        prop_assert!(
+            weighted::swap_exact_in(amount_in, reserve_in, reserve_out, fee, weight_in).unwrap()
+                >= amount_out
+        );
+    }
+
+    #[test]
+    fn weighted_pool_is_constant_product_at_even_weights(
+        reserve_in in reserve(),
+        reserve_out in reserve(),
+        amount in any::<u64>(),
+        fee in fee(),
+        lighter in MIN_WEIGHT..EVEN_WEIGHT,
+        heavier in EVEN_WEIGHT + 1..=MAX_WEIGHT,
+    ) {
+        let even = Curve::Weighted { weight_x: EVEN_WEIGHT };
+        let constant_product = Curve::ConstantProduct;
+        for from_x in [true, false] {
+            prop_assert_eq!(
+                even.swap_exact_in(amount, reserve_in, reserve_out, fee, from_x),
+                constant_product.swap_exact_in(amount, reserve_in, reserve_out, fee, from_x)
+            );
+            prop_assert_eq!(
+                even.swap_exact_out(amount, reserve_in, reserve_out, fee, from_x),
+                constant_product.swap_exact_out(amount, reserve_in, reserve_out, fee, from_x)
+            );
+        }
+        prop_assert_eq!(
+            even.initial_liquidity(reserve_in, reserve_out),
+            constant_product.initial_liquidity(reserve_in, reserve_out)
+        );
+        prop_assert_eq!(
+            even.spot_prices(reserve_in, reserve_out),
+            constant_product.spot_prices(reserve_in, reserve_out)
+        );
+
+        // Weighting the input more buys more of the output, with the constant
+        // product in between
+        prop_assume!(reserve_in.checked_add(amount).is_some());
+        let amount_out = |weight_in| {
+            weighted::swap_exact_in(amount, reserve_in, reserve_out, fee, weight_in).unwrap()
+        };
+        prop_assert!(amount_out(lighter) <= amount_out(EVEN_WEIGHT));
+        prop_assert!(amount_out(EVEN_WEIGHT) <= amount_out(heavier) + 1);
+    }
+
+    #[test]
+    fn weighted_initial_liquidity_is_the_weighted_geometric_mean(
+        amount_x in reserve(),
+        amount_y in reserve(),
+        weight_x in weight(),
+        scale in 1..=1_000_000_000u64,
+    ) {
+        let lp_amount = weighted::initial_liquidity(weight_x, amount_x, amount_y).unwrap();
+
+        // A mean of the two amounts, which is one of them when they are equal
+        prop_assert!(lp_amount + 1 >= amount_x.min(amount_y));
+        prop_assert!(lp_amount <= amount_x.max(amount_y));
+        prop_assert!(
+            weighted::initial_liquidity(weight_x, amount_x, amount_x).unwrap() + 1 >= amount_x
+        );
+
+        // Reserves in proportion to the weights price the tokens at par
+        let weight_y = WEIGHT_DENOMINATOR - weight_x;
+        prop_assert_eq!(
+            weighted::spot_price(weight_x as u64 * scale, weight_y as u64 * scale, weight_x),
+            Ok(1 << 64)
+        );
+    }
+}
@@ -1,4 +1,6 @@
+use amm_math::MathError;
 use anchor_lang::error_code;
+
 #[error_code]
 pub enum AmmError {
     #[msg("Fees should not be more than 100%")]
@@ -13,4 +15,48 @@ pub enum AmmError {
     InsufficientLiquidity,
     #[msg("invalid token amount it cannot be zero")]
     TokenNonZero,
+    #[msg("Signer is not the pool authority")]
+    Unauthorized,
+    #[msg("Config account is not in the legacy layout")]
+    InvalidConfig,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Oracle history does not cover the requested window")]
+    InsufficientHistory,
+    #[msg("Route does not chain its pools")]
+    InvalidRoute,
+    #[msg("Pool curve does not support this instruction")]
+    InvalidCurve,
+    #[msg("Amplification or its ramp is out of bounds")]
+    InvalidAmp,
+    #[msg("Token weight is out of bounds or unsupported by the curve")]
+    InvalidWeight,
+    #[msg("Flash loan receiver may not be the AMM itself")]
+    InvalidReceiver,
+    #[msg("Flash loan was not repaid with its fee")]
+    FlashLoanNotRepaid,
+    #[msg("Pool mints must differ")]
+    IdenticalMints,
+    #[msg("Canonical pool mints must be in sorted order")]
+    UnsortedMints,
+    #[msg("Fee tier is not offered by the factory")]
+    InvalidFeeTier,
+    #[msg("Factory already offers the most fee tiers it can hold")]
+    TooManyFeeTiers,
+    #[msg("Mint has a Token-2022 extension pools do not support")]
+    UnsupportedMintExtension,
+}
+
+impl From<MathError> for AmmError {
+    fn from(e: MathError) -> Self {
+        match e {
+            MathError::Overflow => AmmError::MathOverflow,
+            MathError::InsufficientLiquidity => AmmError::InsufficientLiquidity,
+            MathError::InvalidFee => AmmError::InvalidFee,
+            MathError::ZeroAmount => AmmError::TokenNonZero,
+            MathError::InvalidAmp => AmmError::InvalidAmp,
+            MathError::NoConvergence => AmmError::MathOverflow,
+            MathError::InvalidWeight => AmmError::InvalidWeight,
+        }
+    }
 }
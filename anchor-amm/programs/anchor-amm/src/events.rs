@@ -0,0 +1,82 @@
+//! Events for indexers. `amm_events` declares the same events field for field,
+//! and decodes them from the logs of this program and `native-amm` alike
+use anchor_lang::prelude::*;
+use crate::state::CurveType;
+
+/// A pool was created
+#[event]
+pub struct PoolInitialized {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub authority: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub curve: CurveType,
+    pub weight_x: u16,
+    pub amp: u64,
+}
+
+/// Liquidity was added, with the reserves and LP supply after it
+#[event]
+pub struct Deposit {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_minted: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+/// Liquidity was removed, with the reserves and LP supply after it
+#[event]
+pub struct Withdraw {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub lp_burned: u64,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+/// A trade against one pool, once per pool of a routed swap, with the reserves after it
+#[event]
+pub struct Swap {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub from_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_paid: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+/// The authority changed a pool's settings, all of which it carries as they are now
+#[event]
+pub struct ConfigUpdated {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub authority: Pubkey,
+    /// Always the default key, as authority moves in one step here
+    pub pending_authority: Pubkey,
+    pub fee: u16,
+    pub flash_fee: u16,
+    pub locked: bool,
+    pub target_amp: u64,
+    pub ramp_end: i64,
+}
+
+/// The factory admin started or stopped offering `fee` as a fee tier
+#[event]
+pub struct FeeTierSet {
+    pub factory: Pubkey,
+    pub admin: Pubkey,
+    pub fee: u16,
+    pub enabled: bool,
+}
@@ -0,0 +1,88 @@
+use amm_math::{stable, Curve};
+use anchor_lang::prelude::*;
+use crate::errors::AmmError;
+use crate::events::ConfigUpdated;
+use crate::state::Config;
+
+#[derive(Accounts)]
+pub struct Admin<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        address = config.address()? @ ErrorCode::ConstraintSeeds,
+        has_one = authority @ AmmError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> Admin<'info> {
+    pub fn set_fee(&mut self, fee: u16) -> Result<()> {
+        require!(fee < 10000, AmmError::InvalidFee);
+        self.config.fee = fee;
+
+        self.emit_updated();
+
+        Ok(())
+    }
+
+    pub fn set_flash_fee(&mut self, flash_fee: u16) -> Result<()> {
+        require!(flash_fee < 10000, AmmError::InvalidFee);
+        self.config.flash_fee = flash_fee;
+
+        self.emit_updated();
+
+        Ok(())
+    }
+
+    pub fn set_locked(&mut self, locked: bool) -> Result<()> {
+        self.config.locked = locked;
+
+        self.emit_updated();
+
+        Ok(())
+    }
+
+    pub fn set_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.config.authority = new_authority;
+
+        self.emit_updated();
+
+        Ok(())
+    }
+
+    /// Moves a StableSwap pool's amplification linearly from its current value
+    /// now to `target_amp` at `ramp_end`
+    pub fn ramp_amp(&mut self, target_amp: u64, ramp_end: i64) -> Result<()> {
+        let Curve::Stable { amp } = self.config.curve()? else {
+            return err!(AmmError::InvalidCurve);
+        };
+
+        // A new ramp starts wherever the current one has got to
+        let now = Clock::get()?.unix_timestamp;
+        stable::check_ramp(amp, target_amp, now, ramp_end).map_err(AmmError::from)?;
+
+        self.config.initial_amp = amp;
+        self.config.target_amp = target_amp;
+        self.config.ramp_start = now;
+        self.config.ramp_end = ramp_end;
+
+        self.emit_updated();
+
+        Ok(())
+    }
+
+    /// Logs the pool's settings after a change the authority signed
+    fn emit_updated(&self) {
+        emit!(ConfigUpdated {
+            pool: self.config.key(),
+            user: self.authority.key(),
+            authority: self.config.authority,
+            pending_authority: Pubkey::default(),
+            fee: self.config.fee,
+            flash_fee: self.config.flash_fee,
+            locked: self.config.locked,
+            target_amp: self.config.target_amp,
+            ramp_end: self.config.ramp_end,
+        });
+    }
+}
@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use crate::errors::AmmError;
+use crate::instructions::initialize::init_pool;
+use crate::state::{Config, CurveType, Factory, Oracle};
+
+#[derive(Accounts)]
+#[instruction(fee_tier: u16)]
+pub struct CreatePool<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    /// One order per pair, so a Y/X pool cannot be created beside an X/Y one
+    #[account(
+        constraint = mint_y.key() != mint_x.key() @ AmmError::IdenticalMints,
+        constraint = mint_x.key() < mint_y.key() @ AmmError::UnsortedMints,
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        seeds = [b"lp", config.key().as_ref()],
+        payer = initializer,
+        bump,
+        mint::decimals = 6,
+        mint::authority = config
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref(), fee_tier.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Config::INIT_SPACE
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<Oracle>()
+    )]
+    pub oracle: AccountLoader<'info, Oracle>,
+    #[account(
+        seeds = [b"factory"],
+        bump = factory.bump,
+        constraint = factory.fee_tiers.contains(&fee_tier) @ AmmError::InvalidFeeTier,
+    )]
+    pub factory: Account<'info, Factory>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreatePool<'info> {
+    pub fn create(&mut self, fee_tier: u16, curve: CurveType, weight_x: u16, amp: u64, bumps: &CreatePoolBumps) -> Result<()> {
+        // A pool everyone shares answers to the protocol, not to whoever created it
+        let config = Config {
+            seed: 0,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee: fee_tier,
+            locked: false,
+            config_bump: bumps.config,
+            lp_bump: bumps.mint_lp,
+            authority: self.factory.admin,
+            curve,
+            initial_amp: amp,
+            target_amp: amp,
+            ramp_start: 0,
+            ramp_end: 0,
+            weight_x,
+            flash_fee: 0,
+            fee_tier,
+            factory: true,
+        };
+
+        init_pool(&mut self.config, &self.oracle, [&self.mint_x, &self.mint_y], self.initializer.key(), config, bumps.oracle)
+    }
+}
@@ -3,8 +3,11 @@ use anchor_spl::{
     associated_token::AssociatedToken,
     token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, MintTo, transfer_checked, mint_to},
 };
-use crate::state::Config;
+use amm_math::Curve;
+use crate::state::{Config, MINIMUM_LIQUIDITY};
 use crate::errors::AmmError;
+use crate::events;
+use crate::utils::{amount_after_transfer_fee, amount_before_transfer_fee};
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
@@ -21,37 +24,41 @@ pub struct Deposit<'info> {
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
     pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
     pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
     )]
     pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
-        bump = config.config_bump,
+        address = config.address()? @ ErrorCode::ConstraintSeeds,
         has_one = mint_x,
         has_one = mint_y,
         constraint = !config.locked @ AmmError::PoolLocked,
@@ -67,16 +74,101 @@ impl<'info> Deposit<'info> {
     pub fn deposit(
         &mut self,
         amount: u64,
-        min_x: u64,
-        min_y: u64,
+        max_x: u64,
+        max_y: u64,
     ) -> Result<()> {
         require!(!self.config.locked, AmmError::PoolLocked);
-        require!(min_x == 0, AmmError::TokenNonZero);
-        require!(min_y == 0, AmmError::TokenNonZero);
+        require!(amount != 0, AmmError::TokenNonZero);
+
+        let (amount_x, amount_y, lp_amount) = self.calculate_deposit_amounts(amount, max_x, max_y)?;
+
+        self.deposit_tokens(amount_x, true)?;
+        self.deposit_tokens(amount_y, false)?;
+        self.mint_lp_token(lp_amount)?;
 
-        self.deposit_tokens(amount, true)?;
-        self.deposit_tokens(amount, false)?;
-        self.mint_lp_token(amount)
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
+
+        emit!(events::Deposit {
+            pool: self.config.key(),
+            user: self.user.key(),
+            amount_x,
+            amount_y,
+            lp_minted: lp_amount,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+            lp_supply: self.mint_lp.supply,
+        });
+
+        Ok(())
+    }
+
+    fn calculate_deposit_amounts(
+        &self,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<(u64, u64, u64)> {
+        let supply = self.mint_lp.supply;
+        let curve = self.config.curve()?;
+
+        // Empty pool: the first depositor sets the price with both maxima and receives
+        // the curve's initial liquidity, the weighted geometric mean x^wx * y^wy or StableSwap's D,
+        // minus the permanently locked minimum liquidity
+        if supply == 0 {
+            require!(max_x != 0 && max_y != 0, AmmError::TokenNonZero);
+
+            // LP is priced on what the vaults receive after any transfer fee
+            let received_x = amount_after_transfer_fee(&self.mint_x, max_x)?;
+            let received_y = amount_after_transfer_fee(&self.mint_y, max_y)?;
+
+            let lp_amount = curve
+                .initial_liquidity(received_x, received_y)
+                .map_err(AmmError::from)?
+                .checked_sub(MINIMUM_LIQUIDITY)
+                .filter(|lp_amount| *lp_amount > 0)
+                .ok_or(AmmError::InsufficientLiquidity)?;
+            require!(lp_amount >= amount, AmmError::SlippageExceeded);
+
+            return Ok((max_x, max_y, lp_amount));
+        }
+
+        // StableSwap takes deposits in any ratio: both maxima are deposited and
+        // LP is minted on how much they grow the invariant, at least `amount`
+        let supply = Config::total_liquidity(supply);
+
+        if let Curve::Stable { amp } = curve {
+            let received_x = amount_after_transfer_fee(&self.mint_x, max_x)?;
+            let received_y = amount_after_transfer_fee(&self.mint_y, max_y)?;
+
+            let lp_amount = amm_math::stable::deposit_liquidity(
+                received_x,
+                received_y,
+                self.vault_x.amount,
+                self.vault_y.amount,
+                supply,
+                self.config.fee,
+                amp,
+            )
+            .map_err(AmmError::from)?;
+            require!(lp_amount >= amount, AmmError::SlippageExceeded);
+
+            return Ok((max_x, max_y, lp_amount));
+        }
+
+        // Deposits into a product pool are pro rata, which keeps the value split its weights set.
+        // Required deposits are rounded up so new LP tokens never dilute existing ones
+        let amount_x = amm_math::deposit_share(amount, self.vault_x.amount, supply).map_err(AmmError::from)?;
+        let amount_y = amm_math::deposit_share(amount, self.vault_y.amount, supply).map_err(AmmError::from)?;
+
+        // Gross the deposits up so the vaults receive them net of any transfer fee
+        let amount_x = amount_before_transfer_fee(&self.mint_x, amount_x)?;
+        let amount_y = amount_before_transfer_fee(&self.mint_y, amount_y)?;
+
+        require!(amount_x <= max_x && amount_y <= max_y, AmmError::SlippageExceeded);
+
+        Ok((amount_x, amount_y, amount))
     }
 
     fn deposit_tokens(
@@ -111,15 +203,11 @@ impl<'info> Deposit<'info> {
             authority: self.config.to_account_info()
         };
 
-        let seeds = &[
-            &b"config"[..],
-            &self.config.seed.to_le_bytes(),
-            &[self.config.config_bump]
-        ];
+        let seeds = self.config.signer_seeds();
         let signer_seeds = &[&seeds[..]];
 
         let ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), accounts, signer_seeds);
 
         mint_to(ctx, amount)
     }
-}
\ No newline at end of file
+}
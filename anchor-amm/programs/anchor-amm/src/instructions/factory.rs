@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use crate::errors::AmmError;
+use crate::events::FeeTierSet;
+use crate::program::AnchorAmm;
+use crate::state::Factory;
+
+#[derive(Accounts)]
+pub struct InitializeFactory<'info> {
+    #[account(mut)]
+    pub upgrade_authority: Signer<'info>,
+    #[account(
+        init,
+        payer = upgrade_authority,
+        seeds = [b"factory"],
+        bump,
+        space = 8 + Factory::INIT_SPACE
+    )]
+    pub factory: Account<'info, Factory>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, AnchorAmm>,
+    /// There is only one factory, so only whoever deployed the program creates it
+    #[account(constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key()) @ AmmError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeFactory<'info> {
+    pub fn init(&mut self, admin: Pubkey, bumps: &InitializeFactoryBumps) -> Result<()> {
+        self.factory.set_inner(Factory {
+            admin,
+            fee_tiers: Vec::new(),
+            bump: bumps.factory,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FactoryAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"factory"],
+        bump = factory.bump,
+        has_one = admin @ AmmError::Unauthorized,
+    )]
+    pub factory: Account<'info, Factory>,
+}
+
+impl<'info> FactoryAdmin<'info> {
+    pub fn set_fee_tier(&mut self, fee: u16, enabled: bool) -> Result<()> {
+        self.factory.set_fee_tier(fee, enabled)?;
+
+        emit!(FeeTierSet {
+            factory: self.factory.key(),
+            admin: self.admin.key(),
+            fee,
+            enabled,
+        });
+
+        Ok(())
+    }
+}
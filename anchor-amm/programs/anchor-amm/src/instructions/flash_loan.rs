@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, transfer_checked, TransferChecked};
+use crate::state::Config;
+use crate::errors::AmmError;
+
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = config.address()? @ ErrorCode::ConstraintSeeds,
+        constraint = mint.key() == config.mint_x || mint.key() == config.mint_y @ AmmError::InvalidMint,
+        constraint = !config.locked @ AmmError::PoolLocked,
+    )]
+    pub config: Account<'info, Config>,
+    /// CHECK: any program but this one, which is called with the remaining accounts
+    #[account(executable, constraint = receiver_program.key() != crate::ID @ AmmError::InvalidReceiver)]
+    pub receiver_program: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoan<'info> {
+    pub fn flash_loan(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount: u64,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        require!(amount > 0, AmmError::TokenNonZero);
+
+        let fee = amm_math::flash_loan_fee(amount, self.config.flash_fee).map_err(AmmError::from)?;
+        let owed = self.vault.amount.checked_add(fee).ok_or(AmmError::MathOverflow)?;
+
+        // Written out before any CPI, so the pool reads as locked until the loan is repaid
+        self.config.locked = true;
+        self.config.exit(&crate::ID)?;
+
+        self.lend(amount)?;
+
+        let instruction = Instruction {
+            program_id: self.receiver_program.key(),
+            accounts: remaining_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: account.key(),
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data,
+        };
+
+        let mut account_infos = remaining_accounts.to_vec();
+        account_infos.push(self.receiver_program.to_account_info());
+
+        invoke(&instruction, &account_infos)?;
+
+        // Any transfer fee on the way back is the borrower's to cover
+        self.vault.reload()?;
+        require!(self.vault.amount >= owed, AmmError::FlashLoanNotRepaid);
+
+        self.config.locked = false;
+
+        Ok(())
+    }
+
+    fn lend(&self, amount: u64) -> Result<()> {
+        let seeds = self.config.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = TransferChecked {
+            from: self.vault.to_account_info(),
+            mint: self.mint.to_account_info(),
+            to: self.destination.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.mint.decimals)
+    }
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+use crate::state::{Config, Oracle};
+
+#[derive(Accounts)]
+pub struct InitOracle<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(address = config.address()? @ ErrorCode::ConstraintSeeds)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<Oracle>()
+    )]
+    pub oracle: AccountLoader<'info, Oracle>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitOracle<'info> {
+    /// The history starts now, as there is none from before
+    pub fn init(&mut self, bumps: &InitOracleBumps) -> Result<()> {
+        self.oracle.load_init()?.start(self.config.key(), bumps.oracle, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+}
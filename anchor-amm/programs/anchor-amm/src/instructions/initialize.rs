@@ -4,7 +4,9 @@ use anchor_spl::{
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 use crate::errors::AmmError;
-use crate::state::Config;
+use crate::events::PoolInitialized;
+use crate::state::{Config, CurveType, Oracle};
+use crate::utils::check_mint_extensions;
 
 #[derive(Accounts)]
 #[instruction(seed: u64)]
@@ -12,6 +14,7 @@ pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
     pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(constraint = mint_y.key() != mint_x.key() @ AmmError::IdenticalMints)]
     pub mint_y: InterfaceAccount<'info, Mint>,
     #[account(
         init,
@@ -26,14 +29,16 @@ pub struct Initialize<'info> {
         init,
         payer = initializer,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
     pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
     pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
@@ -44,15 +49,22 @@ pub struct Initialize<'info> {
         space = 8 + Config::INIT_SPACE
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<Oracle>()
+    )]
+    pub oracle: AccountLoader<'info, Oracle>,
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Initialize<'info> {
-    pub fn init(&mut self, seed: u64, fee: u16, bumps: &InitializeBumps) -> Result<()> {
-        require!(fee <= 10000, AmmError::InvalidFee);
-        self.config.set_inner(Config {
+    pub fn init(&mut self, seed: u64, fee: u16, curve: CurveType, weight_x: u16, amp: u64, bumps: &InitializeBumps) -> Result<()> {
+        let config = Config {
             seed,
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
@@ -60,8 +72,66 @@ impl<'info> Initialize<'info> {
             locked: false,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
-        });
-        
-        Ok(())
+            authority: self.initializer.key(),
+            curve,
+            initial_amp: amp,
+            target_amp: amp,
+            ramp_start: 0,
+            ramp_end: 0,
+            weight_x,
+            flash_fee: 0,
+            fee_tier: 0,
+            factory: false,
+        };
+
+        init_pool(&mut self.config, &self.oracle, [&self.mint_x, &self.mint_y], self.initializer.key(), config, bumps.oracle)
+    }
+}
+
+/// Checks a new pool's settings and mints, then writes its config and oracle. Shared with
+/// `CreatePool`, whose pools only differ in how their config is derived
+pub(crate) fn init_pool<'info>(
+    config: &mut Account<'info, Config>,
+    oracle: &AccountLoader<'info, Oracle>,
+    mints: [&InterfaceAccount<'info, Mint>; 2],
+    initializer: Pubkey,
+    config_account: Config,
+    oracle_bump: u8,
+) -> Result<()> {
+    require!(config_account.fee < 10000, AmmError::InvalidFee);
+
+    for mint in mints {
+        check_mint_extensions(mint)?;
+    }
+
+    // Only StableSwap pools have an amplification, and only constant-product
+    // pools can weigh their tokens unevenly
+    match config_account.curve {
+        CurveType::ConstantProduct => {
+            require!(config_account.initial_amp == 0, AmmError::InvalidAmp);
+            amm_math::weighted::check_weight(config_account.weight_x).map_err(AmmError::from)?;
+        }
+        CurveType::Stable => {
+            require!(config_account.weight_x == amm_math::weighted::EVEN_WEIGHT, AmmError::InvalidWeight);
+            amm_math::stable::check_amp(config_account.initial_amp).map_err(AmmError::from)?;
+        }
     }
+
+    emit!(PoolInitialized {
+        pool: config.key(),
+        user: initializer,
+        authority: config_account.authority,
+        mint_x: config_account.mint_x,
+        mint_y: config_account.mint_y,
+        fee: config_account.fee,
+        curve: config_account.curve,
+        weight_x: config_account.weight_x,
+        amp: config_account.initial_amp,
+    });
+
+    config.set_inner(config_account);
+
+    oracle.load_init()?.start(config.key(), oracle_bump, Clock::get()?.unix_timestamp);
+
+    Ok(())
 }
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::Discriminator;
+use crate::errors::AmmError;
+use crate::state::Config;
+
+/// Size of a `Config` account created before the `authority` field existed
+pub const LEGACY_CONFIG_SPACE: usize = 8 + 8 + 32 + 32 + 2 + 1 + 1 + 1;
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub upgrade_authority: Signer<'info>,
+    /// CHECK: legacy accounts no longer deserialize as `Config`, so the owner,
+    /// discriminator and length are checked by hand in `migrate`
+    #[account(mut, owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+    /// Derived from the program id rather than read from the program account, which
+    /// only points at it once the program is deployed through the upgradeable loader
+    #[account(
+        address = bpf_loader_upgradeable::get_program_data_address(&crate::ID),
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key()) @ AmmError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateConfig<'info> {
+    pub fn migrate(&mut self, authority: Pubkey) -> Result<()> {
+        let config = self.config.to_account_info();
+
+        {
+            let data = config.try_borrow_data()?;
+            require!(data.len() == LEGACY_CONFIG_SPACE, AmmError::InvalidConfig);
+            require!(data[..8] == *Config::DISCRIMINATOR, AmmError::InvalidConfig);
+        }
+
+        let new_space = 8 + Config::INIT_SPACE;
+        let rent = Rent::get()?.minimum_balance(new_space);
+        let lamports = rent.saturating_sub(config.lamports());
+
+        if lamports > 0 {
+            let accounts = Transfer {
+                from: self.upgrade_authority.to_account_info(),
+                to: config.clone(),
+            };
+
+            let ctx = CpiContext::new(self.system_program.to_account_info(), accounts);
+
+            transfer(ctx, lamports)?;
+        }
+
+        // New bytes are zeroed
+        config.resize(new_space)?;
+
+        // Fields were only ever appended, so the legacy bytes keep their offsets and
+        // the zeroed tail reads as a constant-product curve, still to be weighted evenly
+        let mut data = config.try_borrow_mut_data()?;
+        let mut config_account = Config::try_deserialize(&mut &data[..])?;
+        config_account.authority = authority;
+        config_account.weight_x = amm_math::weighted::EVEN_WEIGHT;
+        config_account.try_serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,24 @@
 pub mod initialize;
 pub use initialize::*;
+pub mod factory;
+pub use factory::*;
+pub mod create_pool;
+pub use create_pool::*;
 pub mod deposit;
 pub use deposit::*;
+pub mod withdraw;
+pub use withdraw::*;
 pub mod swap;
-pub use swap::*;
\ No newline at end of file
+pub use swap::*;
+pub mod route_swap;
+pub use route_swap::*;
+pub mod observe;
+pub use observe::*;
+pub mod init_oracle;
+pub use init_oracle::*;
+pub mod flash_loan;
+pub use flash_loan::*;
+pub mod admin;
+pub use admin::*;
+pub mod migrate_config;
+pub use migrate_config::*;
\ No newline at end of file
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use crate::state::{Config, Oracle, Twap};
+
+#[derive(Accounts)]
+pub struct Observe<'info> {
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        address = config.address()? @ ErrorCode::ConstraintSeeds,
+        has_one = mint_x,
+        has_one = mint_y,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.load()?.bump,
+    )]
+    pub oracle: AccountLoader<'info, Oracle>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> Observe<'info> {
+    pub fn observe(&self, window: u32) -> Result<Twap> {
+        self.oracle.load()?.twap(
+            Clock::get()?.unix_timestamp,
+            window,
+            &self.config.curve()?,
+            self.vault_x.amount,
+            self.vault_y.amount,
+        )
+    }
+}
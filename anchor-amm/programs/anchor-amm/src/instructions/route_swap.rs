@@ -0,0 +1,212 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked},
+};
+use crate::errors::AmmError;
+use crate::events;
+use crate::state::{Config, Oracle};
+use crate::utils::amount_after_transfer_fee;
+
+/// Remaining accounts per pool in the route: `[config, oracle, mint_x, mint_y, mint_lp, vault_x, vault_y]`
+pub const HOP_ACCOUNTS: usize = 7;
+
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_in: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user_out: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// A pool holds both its tokens under one program, so every pool the
+    /// route chains through shares it too
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Output of one hop, still in its pool's vault until the next hop or the user takes it
+struct Output<'info> {
+    amount: u64,
+    vault: AccountInfo<'info>,
+    mint: InterfaceAccount<'info, Mint>,
+    config: AccountInfo<'info>,
+    config_account: Config,
+    vault_x: &'info AccountInfo<'info>,
+    vault_y: &'info AccountInfo<'info>,
+    /// Logged once the output has left, with the reserves filled in then
+    event: events::Swap,
+}
+
+impl<'info> RouteSwap<'info> {
+    pub fn route_swap(
+        &self,
+        hops: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        let hops = hops.chunks_exact(HOP_ACCOUNTS);
+        if hops.len() == 0 || !hops.remainder().is_empty() {
+            return err!(AmmError::InvalidRoute);
+        }
+
+        // Each hop reads its reserves before the previous hop's output arrives,
+        // so a pool visited twice would be priced on stale reserves
+        for (i, hop) in hops.clone().enumerate() {
+            require!(
+                !hops.clone().skip(i + 1).any(|other| other[0].key == hop[0].key),
+                AmmError::InvalidRoute
+            );
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut mint_in_key = self.user_in.mint;
+        let mut amount = amount_in;
+        let mut previous: Option<Output<'info>> = None;
+
+        for hop in hops {
+            let [config, oracle, mint_x, mint_y, mint_lp, vault_x, vault_y] = hop else {
+                return err!(AmmError::InvalidRoute);
+            };
+
+            let config_account = Account::<Config>::try_from(config)?;
+            require_keys_eq!(config.key(), config_account.address()?, ErrorCode::ConstraintSeeds);
+            require!(!config_account.locked, AmmError::PoolLocked);
+            require_keys_eq!(mint_x.key(), config_account.mint_x, AmmError::InvalidMint);
+            require_keys_eq!(mint_y.key(), config_account.mint_y, AmmError::InvalidMint);
+            require_keys_eq!(
+                mint_lp.key(),
+                Pubkey::create_program_address(&[b"lp", config.key.as_ref(), &[config_account.lp_bump]], &crate::ID)
+                    .map_err(|_| AmmError::InvalidRoute)?,
+                AmmError::InvalidRoute
+            );
+
+            // Vaults are the config's associated token accounts
+            for (vault, mint) in [(vault_x, mint_x), (vault_y, mint_y)] {
+                require_keys_eq!(
+                    vault.key(),
+                    get_associated_token_address_with_program_id(config.key, mint.key, &self.token_program.key()),
+                    AmmError::InvalidRoute
+                );
+            }
+
+            let oracle = AccountLoader::<Oracle>::try_from(oracle)?;
+            require_keys_eq!(oracle.load()?.config, config.key(), AmmError::InvalidRoute);
+
+            let mint_x = InterfaceAccount::<Mint>::try_from(mint_x)?;
+            let mint_y = InterfaceAccount::<Mint>::try_from(mint_y)?;
+            let lp_supply = InterfaceAccount::<Mint>::try_from(mint_lp)?.supply;
+            let reserve_x = InterfaceAccount::<TokenAccount>::try_from(vault_x)?.amount;
+            let reserve_y = InterfaceAccount::<TokenAccount>::try_from(vault_y)?.amount;
+
+            // The hop sells whatever the one before it bought
+            let from_x = if mint_in_key == mint_x.key() {
+                true
+            } else if mint_in_key == mint_y.key() {
+                false
+            } else {
+                return err!(AmmError::InvalidRoute);
+            };
+
+            // The oracle sees the price every swap started from
+            let curve = config_account.curve()?;
+            oracle.load_mut()?.record(now, &curve, reserve_x, reserve_y);
+
+            let (mint_in, mint_out, vault_in, vault_out, reserve_in, reserve_out) = if from_x {
+                (mint_x, mint_y, vault_x, vault_y, reserve_x, reserve_y)
+            } else {
+                (mint_y, mint_x, vault_y, vault_x, reserve_y, reserve_x)
+            };
+
+            let amount_received = amount_after_transfer_fee(&mint_in, amount)?;
+            let amount_out = curve.swap_exact_in(amount_received, reserve_in, reserve_out, config_account.fee, from_x)
+                .map_err(AmmError::from)?;
+            let fee_paid = amm_math::swap_fee(amount_received, config_account.fee).map_err(AmmError::from)?;
+
+            // Intermediate tokens move vault to vault and never pass through the user
+            match previous.take() {
+                None => self.transfer(
+                    self.user_in.to_account_info(),
+                    vault_in.clone(),
+                    &mint_in,
+                    self.user.to_account_info(),
+                    amount,
+                    &[],
+                )?,
+                Some(output) => self.send(output, vault_in.clone())?,
+            }
+
+            mint_in_key = mint_out.key();
+            amount = amount_out;
+            previous = Some(Output {
+                amount: amount_out,
+                vault: vault_out.clone(),
+                mint: mint_out,
+                config: config.clone(),
+                config_account: (*config_account).clone(),
+                vault_x,
+                vault_y,
+                event: events::Swap {
+                    pool: config.key(),
+                    user: self.user.key(),
+                    from_x,
+                    amount_in: amount_received,
+                    amount_out,
+                    fee_paid,
+                    reserve_x: 0,
+                    reserve_y: 0,
+                    lp_supply,
+                },
+            });
+        }
+
+        let output = previous.ok_or(AmmError::InvalidRoute)?;
+
+        // Only the final output is protected, as only it reaches the user
+        require!(
+            amount_after_transfer_fee(&output.mint, output.amount)? >= min_amount_out,
+            AmmError::SlippageExceeded
+        );
+
+        self.send(output, self.user_out.to_account_info())
+    }
+
+    /// Pays out a hop's output from its vault, signed by the pool's config,
+    /// and logs the hop's swap with the reserves it left
+    fn send(&self, mut output: Output<'info>, to: AccountInfo<'info>) -> Result<()> {
+        let seeds = output.config_account.signer_seeds();
+
+        self.transfer(output.vault, to, &output.mint, output.config, output.amount, &[&seeds])?;
+
+        output.event.reserve_x = InterfaceAccount::<TokenAccount>::try_from(output.vault_x)?.amount;
+        output.event.reserve_y = InterfaceAccount::<TokenAccount>::try_from(output.vault_y)?.amount;
+        emit!(output.event);
+
+        Ok(())
+    }
+
+    fn transfer(
+        &self,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        mint: &InterfaceAccount<'info, Mint>,
+        authority: AccountInfo<'info>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from,
+            mint: mint.to_account_info(),
+            to,
+            authority,
+        };
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+}
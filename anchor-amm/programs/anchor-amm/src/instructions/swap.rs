@@ -3,8 +3,10 @@ use anchor_spl::{
     associated_token::AssociatedToken,
     token_interface::{Mint, TokenAccount, TokenInterface, transfer_checked},
 };
-use crate::state::Config;
+use crate::state::{Config, Oracle};
 use crate::errors::AmmError;
+use crate::events;
+use crate::utils::{amount_after_transfer_fee, amount_before_transfer_fee};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
@@ -12,38 +14,52 @@ pub struct Swap<'info> {
     pub user: Signer<'info>,
     pub mint_x: InterfaceAccount<'info, Mint>,
     pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
     pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program
     )]
     pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
     pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
-        bump = config.config_bump,
+        address = config.address()? @ ErrorCode::ConstraintSeeds,
         has_one = mint_x,
         has_one = mint_y,
         constraint = !config.locked @ AmmError::PoolLocked,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"oracle", config.key().as_ref()],
+        bump = oracle.load()?.bump,
+    )]
+    pub oracle: AccountLoader<'info, Oracle>,
 
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -58,22 +74,100 @@ impl<'info> Swap<'info> {
         from_x: bool,
     ) -> Result<()> {
         require!(!self.config.locked, AmmError::PoolLocked);
+
+        self.record_price()?;
         
-        // Calculate the amount out based on constant product formula (x * y = k)
-        let amount_out = self.calculate_amount_out(amount_in, from_x)?;
+        let (mint_in, mint_out) = if from_x {
+            (&self.mint_x, &self.mint_y)
+        } else {
+            (&self.mint_y, &self.mint_x)
+        };
+
+        // Price only what the vault actually receives after any transfer fee
+        let amount_received = amount_after_transfer_fee(mint_in, amount_in)?;
+
+        // Calculate the amount out on the pool's curve
+        let amount_out = self.calculate_amount_out(amount_received, from_x)?;
         
-        // Verify the minimum output amount
-        require!(amount_out >= min_amount_out, AmmError::SlippageExceeded);
+        // Verify the minimum output amount the user receives after any transfer fee
+        require!(amount_after_transfer_fee(mint_out, amount_out)? >= min_amount_out, AmmError::SlippageExceeded);
         
         // Transfer tokens from user to vault
         self.transfer_tokens_from_user(amount_in, from_x)?;
         
         // Transfer tokens from vault to user
         self.transfer_tokens_to_user(amount_out, !from_x)?;
-        
-        Ok(())
+
+        self.emit_swap(amount_received, amount_out, from_x)
     }
     
+    pub fn swap_exact_out(
+        &mut self,
+        amount_out: u64,
+        max_amount_in: u64,
+        from_x: bool,
+    ) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+
+        self.record_price()?;
+
+        let (mint_in, mint_out) = if from_x {
+            (&self.mint_x, &self.mint_y)
+        } else {
+            (&self.mint_y, &self.mint_x)
+        };
+
+        // The vault has to send enough for amount_out to arrive after any transfer fee
+        let amount_sent = amount_before_transfer_fee(mint_out, amount_out)?;
+
+        // Invert the pool's curve for the input required to send amount_sent,
+        // then gross it up so the vault receives it after any transfer fee
+        let amount_in = amount_before_transfer_fee(mint_in, self.calculate_amount_in(amount_sent, from_x)?)?;
+
+        // Verify the maximum input amount
+        require!(amount_in <= max_amount_in, AmmError::SlippageExceeded);
+
+        let amount_received = amount_after_transfer_fee(mint_in, amount_in)?;
+
+        // Transfer tokens from user to vault
+        self.transfer_tokens_from_user(amount_in, from_x)?;
+
+        // Transfer tokens from vault to user
+        self.transfer_tokens_to_user(amount_sent, !from_x)?;
+
+        self.emit_swap(amount_received, amount_sent, from_x)
+    }
+
+    /// Logs a settled swap of `amount_in` received for `amount_out` sent, with the reserves it left
+    /// and the LP supply
+    fn emit_swap(&mut self, amount_in: u64, amount_out: u64, from_x: bool) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+
+        emit!(events::Swap {
+            pool: self.config.key(),
+            user: self.user.key(),
+            from_x,
+            amount_in,
+            amount_out,
+            fee_paid: amm_math::swap_fee(amount_in, self.config.fee).map_err(AmmError::from)?,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+            lp_supply: self.mint_lp.supply,
+        });
+
+        Ok(())
+    }
+
+    /// Records the price every swap starts from in the pool's oracle
+    fn record_price(&self) -> Result<()> {
+        self.oracle
+            .load_mut()?
+            .record(Clock::get()?.unix_timestamp, &self.config.curve()?, self.vault_x.amount, self.vault_y.amount);
+
+        Ok(())
+    }
+
     fn calculate_amount_out(
         &self,
         amount_in: u64,
@@ -92,27 +186,38 @@ impl<'info> Swap<'info> {
             self.vault_x.amount 
         };
         
-        require!(reserve_in > 0 && reserve_out > 0, AmmError::InsufficientLiquidity);
-        
-        // Apply fee
-        let fee_numerator = self.config.fee as u128;
-        let fee_denominator = 10000u128;
-        let amount_in_after_fee = (amount_in as u128) * (fee_denominator - fee_numerator) / fee_denominator;
-        
-        // Calculate amount out using constant product formula: x * y = k
-        // New reserve_out = (reserve_in * reserve_out) / (reserve_in + amount_in_after_fee)
-        // amount_out = Current reserve_out - New reserve_out
-        let amount_out = reserve_out as u128 - 
-            (reserve_in as u128 * reserve_out as u128) / 
-            (reserve_in as u128 + amount_in_after_fee);
-            
-        let amount_out = amount_out as u64;
-        
-        require!(amount_out < reserve_out, AmmError::InsufficientLiquidity);
-        
+        let amount_out = self.config.curve()?
+            .swap_exact_in(amount_in, reserve_in, reserve_out, self.config.fee, is_x_to_y)
+            .map_err(AmmError::from)?;
+
         Ok(amount_out)
     }
     
+    fn calculate_amount_in(
+        &self,
+        amount_out: u64,
+        is_x_to_y: bool,
+    ) -> Result<u64> {
+        let reserve_in = if is_x_to_y {
+            self.vault_x.amount
+        } else {
+            self.vault_y.amount
+        };
+
+        let reserve_out = if is_x_to_y {
+            self.vault_y.amount
+        } else {
+            self.vault_x.amount
+        };
+
+        // Rounds up so the rounding is always paid by the trader
+        let amount_in = self.config.curve()?
+            .swap_exact_out(amount_out, reserve_in, reserve_out, self.config.fee, is_x_to_y)
+            .map_err(AmmError::from)?;
+
+        Ok(amount_in)
+    }
+
     fn transfer_tokens_from_user(
         &self,
         amount: u64,
@@ -170,11 +275,7 @@ impl<'info> Swap<'info> {
             )
         };
         
-        let seeds = &[
-            b"config",
-            &self.config.seed.to_le_bytes()[..],
-            &[self.config.config_bump],
-        ];
+        let seeds = self.config.signer_seeds();
         let signer_seeds = &[&seeds[..]];
         
         let cpi_accounts = anchor_spl::token_interface::TransferChecked {
@@ -0,0 +1,157 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, Burn, transfer_checked, burn},
+};
+use crate::state::Config;
+use crate::errors::AmmError;
+use crate::events;
+use crate::utils::amount_after_transfer_fee;
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        address = config.address()? @ ErrorCode::ConstraintSeeds,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = !config.locked @ AmmError::PoolLocked,
+    )]
+    pub config: Account<'info, Config>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Withdraw<'info> {
+    pub fn withdraw(
+        &mut self,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount != 0, AmmError::TokenNonZero);
+        require!(amount <= self.mint_lp.supply, AmmError::InsufficientLiquidity);
+
+        // Each LP token is a claim on an equal share of both vaults, the locked minimum included
+        let supply = Config::total_liquidity(self.mint_lp.supply);
+        let amount_x = amm_math::withdraw_share(amount, self.vault_x.amount, supply).map_err(AmmError::from)?;
+        let amount_y = amm_math::withdraw_share(amount, self.vault_y.amount, supply).map_err(AmmError::from)?;
+
+        // Slippage applies to what the user receives after any transfer fee
+        require!(
+            amount_after_transfer_fee(&self.mint_x, amount_x)? >= min_x
+                && amount_after_transfer_fee(&self.mint_y, amount_y)? >= min_y,
+            AmmError::SlippageExceeded
+        );
+
+        self.burn_lp_token(amount)?;
+        self.withdraw_tokens(amount_x, true)?;
+        self.withdraw_tokens(amount_y, false)?;
+
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
+
+        emit!(events::Withdraw {
+            pool: self.config.key(),
+            user: self.user.key(),
+            lp_burned: amount,
+            amount_x,
+            amount_y,
+            reserve_x: self.vault_x.amount,
+            reserve_y: self.vault_y.amount,
+            lp_supply: self.mint_lp.supply,
+        });
+
+        Ok(())
+    }
+
+    fn withdraw_tokens(
+        &self,
+        amount: u64,
+        is_x: bool
+    ) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (self.vault_x.to_account_info(), self.user_x.to_account_info(), self.mint_x.to_account_info(), self.mint_x.decimals),
+            false => (self.vault_y.to_account_info(), self.user_y.to_account_info(), self.mint_y.to_account_info(), self.mint_y.decimals)
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info()
+        };
+
+        let seeds = self.config.signer_seeds();
+        let signer_seeds = &[&seeds[..]];
+
+        let ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer_checked(ctx, amount, decimals)
+    }
+
+    fn burn_lp_token(
+        &self,
+        amount: u64,
+    ) -> Result<()> {
+        let accounts = Burn {
+            mint: self.mint_lp.to_account_info(),
+            from: self.user_lp.to_account_info(),
+            authority: self.user.to_account_info()
+        };
+
+        let ctx = CpiContext::new(self.token_program.to_account_info(), accounts);
+
+        burn(ctx, amount)
+    }
+}
@@ -1,23 +1,114 @@
 use anchor_lang::prelude::*;
-mod errors;
+pub mod errors;
+pub mod events;
 mod instructions;
-mod state;
+pub mod state;
+mod utils;
 use instructions::*;
 declare_id!("GpjB8kfUpEifuQxtRBsZYHr5nMchzCYYE3Hj3UJFnaun");
 
-#[program]
-pub mod anchor_amm {
+// The IDL instructions `#[program]` generates in Anchor 0.31 still call
+// `AccountInfo::realloc`, deprecated since solana-account-info 2.3. They land
+// beside the program module rather than in it, so one module wraps both
+#[allow(deprecated)]
+mod amm {
     use super::*;
+
+    #[program]
+    pub mod anchor_amm {
+        use super::*;
     
-    pub fn initialize(ctx: Context<Initialize>, seed: u64, fee: u16) -> Result<()> {
-        ctx.accounts.init(seed, fee, &ctx.bumps)
-    }
+        /// `amp` is the StableSwap amplification, and must be zero for constant product
+        pub fn initialize(ctx: Context<Initialize>, seed: u64, fee: u16, curve: state::CurveType, weight_x: u16, amp: u64) -> Result<()> {
+            ctx.accounts.init(seed, fee, curve, weight_x, amp, &ctx.bumps)
+        }
 
-    pub fn add_liquidity(ctx: Context<Deposit>, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
-        ctx.accounts.deposit(amount, min_x, min_y)
-    }
+        /// Creates the factory with `admin` over its fee tiers. Signed by the upgrade authority
+        pub fn initialize_factory(ctx: Context<InitializeFactory>, admin: Pubkey) -> Result<()> {
+            ctx.accounts.init(admin, &ctx.bumps)
+        }
+
+        /// Offers `fee` as a fee tier, or stops offering it. Pools already created at it keep trading
+        pub fn set_fee_tier(ctx: Context<FactoryAdmin>, fee: u16, enabled: bool) -> Result<()> {
+            ctx.accounts.set_fee_tier(fee, enabled)
+        }
+
+        /// Creates the canonical pool for `mint_x` and `mint_y`, in sorted order, at one of the
+        /// factory's fee tiers. The factory admin is its authority
+        pub fn create_pool(ctx: Context<CreatePool>, fee_tier: u16, curve: state::CurveType, weight_x: u16, amp: u64) -> Result<()> {
+            ctx.accounts.create(fee_tier, curve, weight_x, amp, &ctx.bumps)
+        }
+
+        pub fn add_liquidity(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
+            ctx.accounts.deposit(amount, max_x, max_y)
+        }
+
+        pub fn withdraw(ctx: Context<Withdraw>, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
+            ctx.accounts.withdraw(amount, min_x, min_y)
+        }
+
+        pub fn swap(ctx: Context<Swap>, amount_in: u64, min_amount_out: u64, from_x: bool) -> Result<()> {
+            ctx.accounts.swap(amount_in, min_amount_out, from_x)
+        }
+
+        pub fn swap_exact_out(ctx: Context<Swap>, amount_out: u64, max_amount_in: u64, from_x: bool) -> Result<()> {
+            ctx.accounts.swap_exact_out(amount_out, max_amount_in, from_x)
+        }
+
+        /// Sells exactly `amount_in` through the pools in `remaining_accounts`, in order,
+        /// checking only the final output against `min_amount_out`
+        pub fn route_swap<'info>(
+            ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>,
+            amount_in: u64,
+            min_amount_out: u64,
+        ) -> Result<()> {
+            ctx.accounts.route_swap(ctx.remaining_accounts, amount_in, min_amount_out)
+        }
+
+        /// Creates the oracle of a pool from before pools had one, such as a migrated
+        /// legacy config. Anyone can pay for it
+        pub fn init_oracle(ctx: Context<InitOracle>) -> Result<()> {
+            ctx.accounts.init(&ctx.bumps)
+        }
+
+        /// Q64.64 average prices of x in y and of y in x over the last `window` seconds
+        pub fn observe(ctx: Context<Observe>, window: u32) -> Result<state::Twap> {
+            ctx.accounts.observe(window)
+        }
+
+        /// Lends `amount` of the vault's token to `destination` and calls `receiver_program`
+        /// with `data` and the remaining accounts, which must repay it with the flash fee
+        pub fn flash_loan<'info>(
+            ctx: Context<'_, '_, 'info, 'info, FlashLoan<'info>>,
+            amount: u64,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            ctx.accounts.flash_loan(ctx.remaining_accounts, amount, data)
+        }
+
+        pub fn set_fee(ctx: Context<Admin>, fee: u16) -> Result<()> {
+            ctx.accounts.set_fee(fee)
+        }
+
+        pub fn set_flash_fee(ctx: Context<Admin>, flash_fee: u16) -> Result<()> {
+            ctx.accounts.set_flash_fee(flash_fee)
+        }
+
+        pub fn set_locked(ctx: Context<Admin>, locked: bool) -> Result<()> {
+            ctx.accounts.set_locked(locked)
+        }
+
+        pub fn set_authority(ctx: Context<Admin>, new_authority: Pubkey) -> Result<()> {
+            ctx.accounts.set_authority(new_authority)
+        }
+
+        pub fn ramp_amp(ctx: Context<Admin>, target_amp: u64, ramp_end: i64) -> Result<()> {
+            ctx.accounts.ramp_amp(target_amp, ramp_end)
+        }
 
-    pub fn swap(ctx: Context<Swap>, amount_in: u64, min_amount_out: u64, from_x: bool) -> Result<()> {
-        ctx.accounts.swap(amount_in, min_amount_out, from_x)
+        pub fn migrate_config(ctx: Context<MigrateConfig>, authority: Pubkey) -> Result<()> {
+            ctx.accounts.migrate(authority)
+        }
     }
 }
+pub use amm::*;
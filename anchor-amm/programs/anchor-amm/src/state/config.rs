@@ -1,8 +1,16 @@
+use amm_math::{stable, weighted, Curve};
 use anchor_lang::prelude::*;
 
+/// LP never minted by the first deposit, so the pool can never be fully
+/// drained or have its share price inflated from a single unit
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// A pool created with its own seed is at `["config", seed]`, and a canonical
+/// pool from the factory at `["config", mint_x, mint_y, fee_tier]`
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
+    /// Zero for a canonical pool
     pub seed: u64,                      
     pub mint_x: Pubkey,                 
     pub mint_y: Pubkey,                 
@@ -10,4 +18,77 @@ pub struct Config {
     pub locked: bool,                   
     pub config_bump: u8,                
     pub lp_bump: u8,                    
+    pub authority: Pubkey,
+    pub curve: CurveType,
+    /// StableSwap amplification, ramping linearly from `initial_amp` at
+    /// `ramp_start` to `target_amp` at `ramp_end`. Zero for constant product
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start: i64,
+    pub ramp_end: i64,
+    /// Share of the pool's value held in x, in basis points. Always
+    /// `EVEN_WEIGHT` for StableSwap
+    pub weight_x: u16,
+    /// Fee on flash loans in basis points, zero until the authority sets one
+    pub flash_fee: u16,
+    /// The fee a canonical pool was created at, which stays in its address
+    /// when the authority changes `fee`. Zero otherwise
+    pub fee_tier: u16,
+    pub factory: bool,
+}
+
+/// Pricing curve a pool is created with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    /// Weighted product, which is `x * y = k` at even weights
+    ConstantProduct,
+    Stable,
+}
+
+impl Config {
+    /// Seeds the config signs for the vaults and the LP mint with. The
+    /// integers are stored little-endian, as the seeds spell them
+    pub fn signer_seeds(&self) -> Vec<&[u8]> {
+        let mut seeds: Vec<&[u8]> = if self.factory {
+            vec![b"config", self.mint_x.as_ref(), self.mint_y.as_ref(), bytemuck::bytes_of(&self.fee_tier)]
+        } else {
+            vec![b"config", bytemuck::bytes_of(&self.seed)]
+        };
+        seeds.push(std::slice::from_ref(&self.config_bump));
+        seeds
+    }
+
+    /// The address `signer_seeds` derive, which a config the program created
+    /// is at whether it has its own seed or came from the factory
+    pub fn address(&self) -> Result<Pubkey> {
+        Pubkey::create_program_address(&self.signer_seeds(), &crate::ID).map_err(|_| error!(ErrorCode::ConstraintSeeds))
+    }
+
+    /// LP supply including the minimum liquidity locked by the first deposit,
+    /// which is never minted but still owns its share of the vaults
+    pub fn total_liquidity(lp_supply: u64) -> u64 {
+        lp_supply.saturating_add(MINIMUM_LIQUIDITY)
+    }
+
+    /// The pool's curve, at the amplification in effect now
+    pub fn curve(&self) -> Result<Curve> {
+        Ok(self.curve_at(Clock::get()?.unix_timestamp))
+    }
+
+    /// The pool's curve, at the amplification in effect at `now`
+    pub fn curve_at(&self, now: i64) -> Curve {
+        match self.curve {
+            CurveType::ConstantProduct if self.weight_x == weighted::EVEN_WEIGHT => Curve::ConstantProduct,
+            CurveType::ConstantProduct => Curve::Weighted { weight_x: self.weight_x },
+            CurveType::Stable => Curve::Stable {
+                amp: stable::current_amp(
+                    self.initial_amp,
+                    self.target_amp,
+                    self.ramp_start,
+                    self.ramp_end,
+                    now,
+                ),
+            },
+        }
+    }
 }
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::errors::AmmError;
+
+/// Most fee tiers the factory offers at once
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// Fee tiers canonical pools are created at, at the PDA `["factory"]`
+#[account]
+#[derive(InitSpace)]
+pub struct Factory {
+    /// Manages the fee tiers, and is the authority of every canonical pool
+    pub admin: Pubkey,
+    /// Fees in basis points
+    #[max_len(MAX_FEE_TIERS)]
+    pub fee_tiers: Vec<u16>,
+    pub bump: u8,
+}
+
+impl Factory {
+    /// Offers `fee` as a tier, which is a no-op if it already is, or stops offering it
+    pub fn set_fee_tier(&mut self, fee: u16, enabled: bool) -> Result<()> {
+        require!(fee < 10000, AmmError::InvalidFee);
+
+        let position = self.fee_tiers.iter().position(|tier| *tier == fee);
+
+        match (position, enabled) {
+            (Some(_), true) => {}
+            (None, true) => {
+                require!(self.fee_tiers.len() < MAX_FEE_TIERS, AmmError::TooManyFeeTiers);
+                self.fee_tiers.push(fee);
+            }
+            (Some(index), false) => {
+                self.fee_tiers.swap_remove(index);
+            }
+            (None, false) => return err!(AmmError::InvalidFeeTier),
+        }
+
+        Ok(())
+    }
+}
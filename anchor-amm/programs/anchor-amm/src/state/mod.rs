@@ -1,2 +1,6 @@
 pub mod config;
 pub use config::*;
+pub mod oracle;
+pub use oracle::*;
+pub mod factory;
+pub use factory::*;
@@ -0,0 +1,122 @@
+use amm_math::{oracle, Curve};
+use anchor_lang::prelude::*;
+use crate::errors::AmmError;
+
+/// Slots in a pool's ring buffer
+pub const OBSERVATIONS: usize = 64;
+
+/// Fewest seconds between a slot and the one before it. Until the newest slot
+/// is this far past its predecessor, swaps advance it in place, so however
+/// often the pool trades the buffer reaches back over five hours
+pub const OBSERVATION_INTERVAL: i64 = 300;
+
+/// Cumulative prices at a point in time, see `amm_math::oracle`. The sums are
+/// little-endian u128s, stored as bytes to keep the account 8-byte aligned
+#[zero_copy]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_x_cumulative: [u8; 16],
+    pub price_y_cumulative: [u8; 16],
+}
+
+impl From<Observation> for oracle::Observation {
+    fn from(observation: Observation) -> Self {
+        Self {
+            timestamp: observation.timestamp,
+            price_x_cumulative: u128::from_le_bytes(observation.price_x_cumulative),
+            price_y_cumulative: u128::from_le_bytes(observation.price_y_cumulative),
+        }
+    }
+}
+
+impl From<oracle::Observation> for Observation {
+    fn from(observation: oracle::Observation) -> Self {
+        Self {
+            timestamp: observation.timestamp,
+            price_x_cumulative: observation.price_x_cumulative.to_le_bytes(),
+            price_y_cumulative: observation.price_y_cumulative.to_le_bytes(),
+        }
+    }
+}
+
+/// Price history of one pool
+#[account(zero_copy)]
+pub struct Oracle {
+    pub config: Pubkey,
+    /// Slot of the newest observation
+    pub index: u16,
+    /// Slots written so far, up to OBSERVATIONS
+    pub len: u16,
+    pub bump: u8,
+    pub padding: [u8; 3],
+    pub observations: [Observation; OBSERVATIONS],
+}
+
+/// Q64.64 average prices returned by `observe`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct Twap {
+    pub price_x: u128,
+    pub price_y: u128,
+}
+
+impl Oracle {
+    /// Starts the history of the pool at `config` with one observation at `timestamp`
+    pub fn start(&mut self, config: Pubkey, bump: u8, timestamp: i64) {
+        self.config = config;
+        self.len = 1;
+        self.bump = bump;
+        self.observations[0].timestamp = timestamp;
+    }
+
+    fn newest(&self) -> oracle::Observation {
+        self.observations[self.index as usize].into()
+    }
+
+    /// The observation before the newest, only meaningful once `len` is above one
+    fn previous(&self) -> oracle::Observation {
+        self.observations[(self.index as usize + OBSERVATIONS - 1) % OBSERVATIONS].into()
+    }
+
+    /// Whether the newest slot is `OBSERVATION_INTERVAL` past its predecessor,
+    /// and the next observation takes a slot of its own
+    fn newest_is_closed(&self, newest: &oracle::Observation) -> bool {
+        self.len == 1 || newest.timestamp - self.previous().timestamp >= OBSERVATION_INTERVAL
+    }
+
+    /// Records the prices in effect up to `timestamp`, before a swap moves the reserves.
+    /// Later swaps in the same second leave the buffer untouched
+    pub fn record(&mut self, timestamp: i64, curve: &Curve, reserve_x: u64, reserve_y: u64) {
+        let newest = self.newest();
+        if timestamp <= newest.timestamp {
+            return;
+        }
+
+        if self.newest_is_closed(&newest) {
+            self.index = ((self.index as usize + 1) % OBSERVATIONS) as u16;
+            self.len = (self.len + 1).min(OBSERVATIONS as u16);
+        }
+        let (price_x, price_y) = curve.spot_prices(reserve_x, reserve_y);
+        self.observations[self.index as usize] = newest.advance(timestamp, price_x, price_y).into();
+    }
+
+    /// Average prices over the `window` seconds up to `timestamp`, given the pool's
+    /// curve and current reserves
+    pub fn twap(&self, timestamp: i64, window: u32, curve: &Curve, reserve_x: u64, reserve_y: u64) -> Result<Twap> {
+        let (price_x, price_y) = curve.spot_prices(reserve_x, reserve_y);
+        let current = self.newest().advance(timestamp, price_x, price_y);
+
+        let start = oracle::observe(
+            OBSERVATIONS,
+            self.len as usize,
+            self.index as usize,
+            |slot| self.observations[slot].into(),
+            &current,
+            timestamp - window as i64,
+        )
+        .ok_or(AmmError::InsufficientHistory)?;
+
+        let (price_x, price_y) = oracle::twap(&start, &current).map_err(AmmError::from)?;
+
+        Ok(Twap { price_x, price_y })
+    }
+}
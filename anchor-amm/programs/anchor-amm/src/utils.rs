@@ -0,0 +1,89 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    spl_token_2022::{
+        self,
+        extension::{
+            default_account_state::DefaultAccountState,
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        },
+        state::AccountState,
+    },
+    Mint,
+};
+use crate::errors::AmmError;
+
+/// Token-2022 mint extensions a pool can hold. Anything else could let an
+/// outside authority move, freeze, gate or hide the tokens in its vaults
+const SUPPORTED_MINT_EXTENSIONS: &[ExtensionType] = &[
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::DefaultAccountState,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
+
+/// Checks that a Token-2022 mint only has supported extensions, and that new
+/// accounts for it, the pool's vaults among them, do not start frozen
+pub fn check_mint_extensions(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+
+    require!(
+        mint_state.get_extension_types()?.iter().all(|extension| SUPPORTED_MINT_EXTENSIONS.contains(extension)),
+        AmmError::UnsupportedMintExtension
+    );
+
+    if let Ok(default_account_state) = mint_state.get_extension::<DefaultAccountState>() {
+        require!(default_account_state.state == AccountState::Initialized as u8, AmmError::UnsupportedMintExtension);
+    }
+
+    Ok(())
+}
+
+/// Transfer fee a Token-2022 mint charges in the current epoch, if it has one
+fn epoch_transfer_fee(mint: &InterfaceAccount<Mint>) -> Result<Option<TransferFee>> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+
+    let Ok(transfer_fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(None);
+    };
+
+    Ok(Some(*transfer_fee_config.get_epoch_fee(Clock::get()?.epoch)))
+}
+
+/// Amount that arrives at the destination when `amount` of `mint` is transferred
+pub fn amount_after_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    match epoch_transfer_fee(mint)? {
+        Some(transfer_fee) => transfer_fee
+            .calculate_post_fee_amount(amount)
+            .ok_or(error!(AmmError::MathOverflow)),
+        None => Ok(amount),
+    }
+}
+
+/// Amount of `mint` that must be transferred for `amount` to arrive at the destination
+pub fn amount_before_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    match epoch_transfer_fee(mint)? {
+        Some(transfer_fee) => transfer_fee
+            .calculate_pre_fee_amount(amount)
+            .ok_or(error!(AmmError::MathOverflow)),
+        None => Ok(amount),
+    }
+}
@@ -0,0 +1,803 @@
+//! Runs the program natively inside a `ProgramTest` bank, with the real SPL
+//! Token, Token-2022 and associated token programs handling every CPI.
+
+use amm_math::{stable, weighted};
+use anchor_amm::{
+    errors::AmmError,
+    state::{Config, CurveType, Oracle, Twap, MINIMUM_LIQUIDITY, OBSERVATIONS, OBSERVATION_INTERVAL},
+};
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address_with_program_id, spl_associated_token_account::instruction::create_associated_token_account,
+    },
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            confidential_transfer,
+            default_account_state::instruction::initialize_default_account_state,
+            metadata_pointer,
+            transfer_fee::{instruction::initialize_transfer_fee_config, TransferFee},
+            transfer_hook, ExtensionType, StateWithExtensions,
+        },
+        state::{Account as TokenAccount, AccountState, Mint},
+    },
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+const SEED: u64 = 42;
+const FEE: u16 = 30;
+const DEPOSIT: u64 = 1_000_000;
+/// Basis points a transfer-fee mint withholds from every transfer
+const TRANSFER_FEE: u16 = 100;
+/// Size of a `Config` account created before the `authority` field existed
+const LEGACY_CONFIG_SPACE: usize = 8 + 8 + 32 + 32 + 2 + 1 + 1 + 1;
+
+/// `entry` ties the account slice to the lifetime of the accounts in it, which
+/// `processor!` cannot express
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    anchor_amm::entry(program_id, Box::leak(Box::new(accounts.to_vec())), data)
+}
+
+fn address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &anchor_amm::ID).0
+}
+
+/// A seeded pool, traded by the test's payer through its associated token accounts
+struct Pool {
+    config: Pubkey,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    mint_lp: Pubkey,
+    oracle: Pubkey,
+    vault_x: Pubkey,
+    vault_y: Pubkey,
+    user: Pubkey,
+    user_x: Pubkey,
+    user_y: Pubkey,
+    user_lp: Pubkey,
+    token_program: Pubkey,
+}
+
+impl Pool {
+    fn new(seed: u64, mint_x: Pubkey, mint_y: Pubkey, token_program: Pubkey, user: Pubkey) -> Self {
+        let config = address(&[b"config", seed.to_le_bytes().as_ref()]);
+        let mint_lp = address(&[b"lp", config.as_ref()]);
+        let ata = |owner: &Pubkey, mint: &Pubkey| get_associated_token_address_with_program_id(owner, mint, &token_program);
+
+        Self {
+            config,
+            mint_x,
+            mint_y,
+            mint_lp,
+            oracle: address(&[b"oracle", config.as_ref()]),
+            vault_x: ata(&config, &mint_x),
+            vault_y: ata(&config, &mint_y),
+            user,
+            user_x: ata(&user, &mint_x),
+            user_y: ata(&user, &mint_y),
+            user_lp: ata(&user, &mint_lp),
+            token_program,
+        }
+    }
+
+    fn initialize_ix(&self, seed: u64, curve: CurveType, weight_x: u16, amp: u64) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::Initialize { seed, fee: FEE, curve, weight_x, amp }.data(),
+            anchor_amm::accounts::Initialize {
+                initializer: self.user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                config: self.config,
+                oracle: self.oracle,
+                token_program: self.token_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn deposit_ix(&self, amount: u64, max_x: u64, max_y: u64) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::AddLiquidity { amount, max_x, max_y }.data(),
+            anchor_amm::accounts::Deposit {
+                user: self.user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_x,
+                user_y: self.user_y,
+                user_lp: self.user_lp,
+                config: self.config,
+                token_program: self.token_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn withdraw_ix(&self, amount: u64, min_x: u64, min_y: u64) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::Withdraw { amount, min_x, min_y }.data(),
+            anchor_amm::accounts::Withdraw {
+                user: self.user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp: self.mint_lp,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                user_x: self.user_x,
+                user_y: self.user_y,
+                user_lp: self.user_lp,
+                config: self.config,
+                token_program: self.token_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn swap_ix(&self, amount_in: u64, min_amount_out: u64, from_x: bool) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::Swap { amount_in, min_amount_out, from_x }.data(),
+            self.swap_accounts(),
+        )
+    }
+
+    fn swap_exact_out_ix(&self, amount_out: u64, max_amount_in: u64, from_x: bool) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::SwapExactOut { amount_out, max_amount_in, from_x }.data(),
+            self.swap_accounts(),
+        )
+    }
+
+    fn swap_accounts(&self) -> Vec<AccountMeta> {
+        anchor_amm::accounts::Swap {
+            user: self.user,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_x: self.user_x,
+            user_y: self.user_y,
+            config: self.config,
+            oracle: self.oracle,
+            token_program: self.token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None)
+    }
+
+    fn ramp_amp_ix(&self, target_amp: u64, ramp_end: i64) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::RampAmp { target_amp, ramp_end }.data(),
+            anchor_amm::accounts::Admin { authority: self.user, config: self.config }.to_account_metas(None),
+        )
+    }
+
+    fn init_oracle_ix(&self, payer: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::InitOracle {}.data(),
+            anchor_amm::accounts::InitOracle { payer: *payer, config: self.config, oracle: self.oracle, system_program: system_program::ID }.to_account_metas(None),
+        )
+    }
+
+    fn observe_ix(&self, window: u32) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::Observe { window }.data(),
+            anchor_amm::accounts::Observe {
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                vault_x: self.vault_x,
+                vault_y: self.vault_y,
+                config: self.config,
+                oracle: self.oracle,
+                token_program: self.token_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn set_fee_ix(&self, fee: u16) -> Instruction {
+        Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::SetFee { fee }.data(),
+            anchor_amm::accounts::Admin { authority: self.user, config: self.config }.to_account_metas(None),
+        )
+    }
+}
+
+async fn start() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new("anchor_amm", anchor_amm::ID, processor!(process_instruction));
+    program_test.prefer_bpf(false);
+    program_test.start_with_context().await
+}
+
+async fn process(context: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), TransactionError> {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let transaction = Transaction::new_signed_with_payer(instructions, Some(&context.payer.pubkey()), &all_signers, blockhash);
+
+    context.banks_client.process_transaction(transaction).await.map_err(|error| error.unwrap())
+}
+
+fn anchor_error<T: std::fmt::Debug>(result: Result<T, TransactionError>) -> u32 {
+    match result.unwrap_err() {
+        TransactionError::InstructionError(0, InstructionError::Custom(code)) => code,
+        error => panic!("expected a custom error, got {error:?}"),
+    }
+}
+
+fn assert_amm_error<T: std::fmt::Debug>(result: Result<T, TransactionError>, expected: AmmError) {
+    assert_eq!(anchor_error(result), anchor_lang::error::ERROR_CODE_OFFSET + expected as u32);
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+
+    process(
+        context,
+        &[
+            system_instruction::create_account(&payer, &mint.pubkey(), Rent::default().minimum_balance(spl_token::state::Mint::LEN), spl_token::state::Mint::LEN as u64, &spl_token::ID),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer, None, 6).unwrap(),
+        ],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    mint.pubkey()
+}
+
+/// Creates a Token-2022 mint with `extensions`, which the instructions from
+/// `initialize_extensions` set up before the mint itself is initialized
+async fn create_extension_mint(context: &mut ProgramTestContext, extensions: &[ExtensionType], initialize_extensions: impl Fn(&Pubkey) -> Vec<Instruction>) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    let space = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+
+    let mut instructions = vec![system_instruction::create_account(&payer, &mint.pubkey(), Rent::default().minimum_balance(space), space as u64, &spl_token_2022::ID)];
+    instructions.extend(initialize_extensions(&mint.pubkey()));
+    instructions.push(spl_token_2022::instruction::initialize_mint2(&spl_token_2022::ID, &mint.pubkey(), &payer, Some(&payer), 6).unwrap());
+
+    process(context, &instructions, &[&mint]).await.unwrap();
+
+    mint.pubkey()
+}
+
+/// Gives the payer an associated token account for `mint` holding `amount`,
+/// under whichever token program owns the mint
+async fn fund(context: &mut ProgramTestContext, mint: &Pubkey, amount: u64) {
+    let payer = context.payer.pubkey();
+    let token_program = context.banks_client.get_account(*mint).await.unwrap().unwrap().owner;
+    let account = get_associated_token_address_with_program_id(&payer, mint, &token_program);
+
+    process(
+        context,
+        &[
+            create_associated_token_account(&payer, &payer, mint, &token_program),
+            spl_token_2022::instruction::mint_to(&token_program, mint, &account, &payer, &[], amount).unwrap(),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = context.banks_client.get_account(*account).await.unwrap().unwrap();
+
+    StateWithExtensions::<TokenAccount>::unpack(&account.data).unwrap().base.amount
+}
+
+async fn lp_supply(context: &mut ProgramTestContext, mint: &Pubkey) -> u64 {
+    let account = context.banks_client.get_account(*mint).await.unwrap().unwrap();
+
+    StateWithExtensions::<Mint>::unpack(&account.data).unwrap().base.supply
+}
+
+/// Gives the program a program data account naming `upgrade_authority`, which
+/// a program loaded with `processor!` does not have
+fn set_upgrade_authority(context: &mut ProgramTestContext, upgrade_authority: &Pubkey) {
+    let data = bincode::serialize(&UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(*upgrade_authority) }).unwrap();
+
+    context.set_account(
+        &bpf_loader_upgradeable::get_program_data_address(&anchor_amm::ID),
+        &Account { lamports: 1_000_000_000, data, owner: bpf_loader_upgradeable::ID, executable: false, rent_epoch: 0 }.into(),
+    );
+}
+
+/// A constant-product pool at `SEED` holding `DEPOSIT` of each token, with the
+/// payer funded with ten times that
+async fn setup_pool(context: &mut ProgramTestContext) -> Pool {
+    setup_curve_pool(context, CurveType::ConstantProduct, weighted::EVEN_WEIGHT, 0, DEPOSIT, DEPOSIT).await
+}
+
+/// A pool at `SEED` on `curve`, first deposited into with `amount_x` and
+/// `amount_y`, with the payer funded with ten times `DEPOSIT` of each token
+async fn setup_curve_pool(context: &mut ProgramTestContext, curve: CurveType, weight_x: u16, amp: u64, amount_x: u64, amount_y: u64) -> Pool {
+    let mint_x = create_mint(context).await;
+    let mint_y = create_mint(context).await;
+    fund(context, &mint_x, 10 * DEPOSIT).await;
+    fund(context, &mint_y, 10 * DEPOSIT).await;
+
+    let pool = Pool::new(SEED, mint_x, mint_y, spl_token::ID, context.payer.pubkey());
+    process(context, &[pool.initialize_ix(SEED, curve, weight_x, amp), pool.deposit_ix(1, amount_x, amount_y)], &[]).await.unwrap();
+
+    pool
+}
+
+async fn load_oracle(context: &mut ProgramTestContext, pool: &Pool) -> Oracle {
+    let oracle_data = context.banks_client.get_account(pool.oracle).await.unwrap().unwrap().data;
+
+    *bytemuck::from_bytes(&oracle_data[8..])
+}
+
+/// Simulates `observe_ix`, which writes nothing, and decodes its return data
+async fn observe(context: &mut ProgramTestContext, pool: &Pool, window: u32) -> Result<Twap, TransactionError> {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(&[pool.observe_ix(window)], Some(&context.payer.pubkey()), &[&context.payer], blockhash);
+
+    let result = context.banks_client.simulate_transaction(transaction).await.unwrap();
+    result.result.unwrap()?;
+
+    Ok(Twap::try_from_slice(&result.simulation_details.unwrap().return_data.unwrap().data).unwrap())
+}
+
+async fn now(context: &mut ProgramTestContext) -> i64 {
+    context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+}
+
+/// Moves the bank's clock forward by `seconds`
+async fn warp(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+#[tokio::test]
+async fn migrated_legacy_pool_swaps_once_it_has_an_oracle() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let pool = setup_pool(&mut context).await;
+
+    // Fields were only ever appended, so a legacy config is the leading bytes of
+    // a current one. Pools that old had no oracle either
+    let mut config_account = context.banks_client.get_account(pool.config).await.unwrap().unwrap();
+    config_account.data.truncate(LEGACY_CONFIG_SPACE);
+    config_account.lamports = Rent::default().minimum_balance(LEGACY_CONFIG_SPACE);
+    context.set_account(&pool.config, &config_account.into());
+    context.set_account(&pool.oracle, &Account::default().into());
+
+    set_upgrade_authority(&mut context, &payer);
+    let authority = Pubkey::new_unique();
+    process(
+        &mut context,
+        &[Instruction::new_with_bytes(
+            anchor_amm::ID,
+            &anchor_amm::instruction::MigrateConfig { authority }.data(),
+            anchor_amm::accounts::MigrateConfig {
+                upgrade_authority: payer,
+                config: pool.config,
+                program_data: bpf_loader_upgradeable::get_program_data_address(&anchor_amm::ID),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config_data = context.banks_client.get_account(pool.config).await.unwrap().unwrap().data;
+    let migrated = Config::try_deserialize(&mut &config_data[..]).unwrap();
+    assert_eq!(migrated.authority, authority);
+    assert_eq!(migrated.seed, SEED);
+    assert_eq!(migrated.fee, FEE);
+
+    // Swaps load the oracle, so the pool cannot trade until someone creates it
+    assert_eq!(
+        anchor_error(process(&mut context, &[pool.swap_ix(10_000, 0, true)], &[]).await),
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram as u32,
+    );
+
+    let anyone = Keypair::new();
+    process(&mut context, &[system_instruction::transfer(&payer, &anyone.pubkey(), 1_000_000_000)], &[]).await.unwrap();
+    process(&mut context, &[pool.init_oracle_ix(&anyone.pubkey())], &[&anyone]).await.unwrap();
+
+    let oracle_data = context.banks_client.get_account(pool.oracle).await.unwrap().unwrap().data;
+    let oracle_account: &Oracle = bytemuck::from_bytes(&oracle_data[8..]);
+    assert_eq!(oracle_account.config, pool.config);
+    assert_eq!(oracle_account.len, 1);
+
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap();
+    process(&mut context, &[pool.swap_ix(amount_in, 0, true)], &[]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, DEPOSIT + amount_in);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, DEPOSIT - amount_out);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, DEPOSIT - MINIMUM_LIQUIDITY);
+}
+
+#[tokio::test]
+async fn config_is_only_accepted_at_its_own_address() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+
+    // A byte-for-byte copy the program owns, at an address its seeds do not derive
+    let copy = Pubkey::new_unique();
+    let config_account = context.banks_client.get_account(pool.config).await.unwrap().unwrap();
+    context.set_account(&copy, &config_account.into());
+    let forged = Pool { config: copy, oracle: address(&[b"oracle", copy.as_ref()]), ..pool };
+
+    for instruction in [forged.init_oracle_ix(&forged.user), forged.set_fee_ix(FEE + 1)] {
+        assert_eq!(
+            anchor_error(process(&mut context, &[instruction], &[]).await),
+            anchor_lang::error::ErrorCode::ConstraintSeeds as u32,
+        );
+    }
+}
+
+#[tokio::test]
+async fn oracle_keeps_hours_of_history_however_often_the_pool_trades() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let (mut reserve_x, mut reserve_y) = (DEPOSIT, DEPOSIT);
+
+    // Sizes differ so that no two swaps are the same transaction
+    let mut swap = async |context: &mut ProgramTestContext, amount_in: u64, from_x: bool| {
+        let (reserve_in, reserve_out) = if from_x { (reserve_x, reserve_y) } else { (reserve_y, reserve_x) };
+        let amount_out = amm_math::swap_exact_in(amount_in, reserve_in, reserve_out, FEE).unwrap();
+        process(context, &[pool.swap_ix(amount_in, amount_out, from_x)], &[]).await.unwrap();
+        (reserve_x, reserve_y) = if from_x { (reserve_x + amount_in, reserve_y - amount_out) } else { (reserve_x - amount_out, reserve_y + amount_in) };
+        (amm_math::spot_price(reserve_x, reserve_y).unwrap(), amm_math::spot_price(reserve_y, reserve_x).unwrap())
+    };
+
+    // Swapping every second only ever advances the newest slot
+    for i in 0..20 {
+        warp(&mut context, 1).await;
+        swap(&mut context, 1_000 + i, i % 2 == 0).await;
+    }
+    assert_eq!(load_oracle(&mut context, &pool).await.len, 2);
+
+    // Swapping every interval takes a slot each and wraps the buffer, and the
+    // last OBSERVATIONS swaps each set the price for one interval of the window
+    let mut prices = Vec::new();
+    for i in 0..OBSERVATIONS as u64 + 8 {
+        warp(&mut context, OBSERVATION_INTERVAL).await;
+        prices.push(swap(&mut context, 2_000 + i, i % 2 == 0).await);
+    }
+    warp(&mut context, OBSERVATION_INTERVAL).await;
+
+    let oracle = load_oracle(&mut context, &pool).await;
+    let slot = |age: usize| oracle.observations[(oracle.index as usize + OBSERVATIONS - age) % OBSERVATIONS];
+    assert_eq!(oracle.len as usize, OBSERVATIONS);
+    for age in 1..OBSERVATIONS {
+        assert_eq!(slot(age - 1).timestamp - slot(age).timestamp, OBSERVATION_INTERVAL);
+    }
+
+    let held = &prices[prices.len() - OBSERVATIONS..];
+    let full_window = (OBSERVATIONS as i64 * OBSERVATION_INTERVAL) as u32;
+    let twap = observe(&mut context, &pool, full_window).await.unwrap();
+    assert_eq!(twap.price_x, held.iter().map(|price| price.0).sum::<u128>() / OBSERVATIONS as u128);
+    assert_eq!(twap.price_y, held.iter().map(|price| price.1).sum::<u128>() / OBSERVATIONS as u128);
+
+    // The oldest slot is as far back as the history goes
+    assert_amm_error(observe(&mut context, &pool, full_window + 1).await, AmmError::InsufficientHistory);
+}
+
+#[tokio::test]
+async fn stable_pool_deposits_swaps_and_withdraws_during_an_amp_ramp() {
+    let mut context = start().await;
+    let amp = 100;
+    let pool = setup_curve_pool(&mut context, CurveType::Stable, weighted::EVEN_WEIGHT, amp, DEPOSIT, DEPOSIT).await;
+
+    let initial_lp = stable::initial_liquidity(amp, DEPOSIT, DEPOSIT).unwrap() - MINIMUM_LIQUIDITY;
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, initial_lp);
+
+    // Ramps must be gradual
+    let start = now(&mut context).await;
+    let ramp_end = start + 2 * stable::MIN_RAMP_DURATION;
+    for (target_amp, ramp_end) in [(1_000, start + 3_600), (amp * (stable::MAX_AMP_CHANGE + 1), ramp_end)] {
+        assert_amm_error(process(&mut context, &[pool.ramp_amp_ix(target_amp, ramp_end)], &[]).await, AmmError::InvalidAmp);
+    }
+    process(&mut context, &[pool.ramp_amp_ix(1_000, ramp_end)], &[]).await.unwrap();
+
+    // Halfway through, everything prices at the halfway amplification
+    warp(&mut context, stable::MIN_RAMP_DURATION).await;
+    let halfway = stable::current_amp(amp, 1_000, start, ramp_end, start + stable::MIN_RAMP_DURATION);
+    assert_eq!(halfway, 550);
+
+    // A trade well off the peg shows the amplification it was priced at
+    let amount_in = DEPOSIT / 2;
+    let amount_out = stable::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE, halfway).unwrap();
+    assert_ne!(amount_out, stable::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE, amp).unwrap());
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    assert_amm_error(process(&mut context, &[pool.swap_ix(amount_in, amount_out + 1, false)], &[]).await, AmmError::SlippageExceeded);
+    process(&mut context, &[pool.swap_ix(amount_in, amount_out, false)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_x).await - user_x_before, amount_out);
+
+    // One-sided deposits mint on the growth of the invariant at the same amplification
+    let (reserve_x, reserve_y) = (DEPOSIT - amount_out, DEPOSIT + amount_in);
+    let supply = initial_lp + MINIMUM_LIQUIDITY;
+    let minted = stable::deposit_liquidity(amount_out, 0, reserve_x, reserve_y, supply, FEE, halfway).unwrap();
+    assert_amm_error(process(&mut context, &[pool.deposit_ix(minted + 1, amount_out, 0)], &[]).await, AmmError::SlippageExceeded);
+    process(&mut context, &[pool.deposit_ix(minted, amount_out, 0)], &[]).await.unwrap();
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, initial_lp + minted);
+
+    // Withdrawals stay pro rata whatever the curve
+    let (reserve_x, supply) = (reserve_x + amount_out, supply + minted);
+    let burned = initial_lp / 2;
+    let amount_x = amm_math::withdraw_share(burned, reserve_x, supply).unwrap();
+    let amount_y = amm_math::withdraw_share(burned, reserve_y, supply).unwrap();
+    let (user_x_before, user_y_before) = (token_balance(&mut context, &pool.user_x).await, token_balance(&mut context, &pool.user_y).await);
+    process(&mut context, &[pool.withdraw_ix(burned, amount_x, amount_y)], &[]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &pool.user_x).await - user_x_before, amount_x);
+    assert_eq!(token_balance(&mut context, &pool.user_y).await - user_y_before, amount_y);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x - amount_x);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, initial_lp + minted - burned);
+}
+
+#[tokio::test]
+async fn weighted_pool_deposits_swaps_and_withdraws_by_its_weights() {
+    let mut context = start().await;
+    let weight_x = 8_000;
+    let weight_y = weighted::WEIGHT_DENOMINATOR - weight_x;
+
+    // 80/20 by value, with x and y at par
+    let (mut reserve_x, mut reserve_y) = (DEPOSIT * 4, DEPOSIT);
+    let pool = setup_curve_pool(&mut context, CurveType::ConstantProduct, weight_x, 0, reserve_x, reserve_y).await;
+
+    let mut supply = weighted::initial_liquidity(weight_x, reserve_x, reserve_y).unwrap();
+    assert!(supply > amm_math::initial_liquidity(reserve_x, reserve_y));
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, supply - MINIMUM_LIQUIDITY);
+
+    // Each direction is priced with the weight of the token sold
+    let amount_in = 10_000;
+    let amount_out = weighted::swap_exact_in(amount_in, reserve_y, reserve_x, FEE, weight_y).unwrap();
+    assert!(amount_out < amm_math::swap_exact_in(amount_in, reserve_y, reserve_x, FEE).unwrap());
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(&mut context, &[pool.swap_ix(amount_in, amount_out, false)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_x).await - user_x_before, amount_out);
+    (reserve_x, reserve_y) = (reserve_x - amount_out, reserve_y + amount_in);
+
+    let amount_out = weighted::swap_exact_in(amount_in, reserve_x, reserve_y, FEE, weight_x).unwrap();
+    assert_amm_error(process(&mut context, &[pool.swap_ix(amount_in, amount_out + 1, true)], &[]).await, AmmError::SlippageExceeded);
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(&mut context, &[pool.swap_ix(amount_in, amount_out, true)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_y).await - user_y_before, amount_out);
+    (reserve_x, reserve_y) = (reserve_x + amount_in, reserve_y - amount_out);
+
+    // Buying an exact output inverts the same weighted curve
+    let amount_out = 5_000;
+    let amount_in = weighted::swap_exact_out(amount_out, reserve_y, reserve_x, FEE, weight_y).unwrap();
+    assert_amm_error(process(&mut context, &[pool.swap_exact_out_ix(amount_out, amount_in - 1, false)], &[]).await, AmmError::SlippageExceeded);
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(&mut context, &[pool.swap_exact_out_ix(amount_out, amount_in, false)], &[]).await.unwrap();
+    assert_eq!(user_y_before - token_balance(&mut context, &pool.user_y).await, amount_in);
+    (reserve_x, reserve_y) = (reserve_x - amount_out, reserve_y + amount_in);
+
+    // Later deposits are pro rata, which keeps the 80/20 split
+    let minted = supply / 10;
+    let amount_x = amm_math::deposit_share(minted, reserve_x, supply).unwrap();
+    let amount_y = amm_math::deposit_share(minted, reserve_y, supply).unwrap();
+    assert_amm_error(process(&mut context, &[pool.deposit_ix(minted, amount_x - 1, amount_y)], &[]).await, AmmError::SlippageExceeded);
+    process(&mut context, &[pool.deposit_ix(minted, amount_x, amount_y)], &[]).await.unwrap();
+    (reserve_x, reserve_y, supply) = (reserve_x + amount_x, reserve_y + amount_y, supply + minted);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+
+    let burned = supply / 4;
+    let amount_x = amm_math::withdraw_share(burned, reserve_x, supply).unwrap();
+    let amount_y = amm_math::withdraw_share(burned, reserve_y, supply).unwrap();
+    process(&mut context, &[pool.withdraw_ix(burned, amount_x, amount_y)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x - amount_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y - amount_y);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, supply - burned - MINIMUM_LIQUIDITY);
+}
+
+#[tokio::test]
+async fn transfer_fee_mint_trades_on_what_arrives() {
+    let mut context = start().await;
+    let transfer_fee = TransferFee { epoch: 0.into(), maximum_fee: u64::MAX.into(), transfer_fee_basis_points: TRANSFER_FEE.into() };
+    let post_fee = |amount: u64| transfer_fee.calculate_post_fee_amount(amount).unwrap();
+    let pre_fee = |amount: u64| transfer_fee.calculate_pre_fee_amount(amount).unwrap();
+
+    // Both mints live under Token-2022, and only x withholds a fee
+    let mint_x = create_extension_mint(&mut context, &[ExtensionType::TransferFeeConfig], |mint| {
+        vec![initialize_transfer_fee_config(&spl_token_2022::ID, mint, None, None, TRANSFER_FEE, u64::MAX).unwrap()]
+    })
+    .await;
+    let mint_y = create_extension_mint(&mut context, &[], |_| vec![]).await;
+    fund(&mut context, &mint_x, 10 * DEPOSIT).await;
+    fund(&mut context, &mint_y, 10 * DEPOSIT).await;
+
+    let pool = Pool::new(SEED, mint_x, mint_y, spl_token_2022::ID, context.payer.pubkey());
+    process(&mut context, &[pool.initialize_ix(SEED, CurveType::ConstantProduct, weighted::EVEN_WEIGHT, 0), pool.deposit_ix(1, DEPOSIT, DEPOSIT)], &[]).await.unwrap();
+
+    // The first deposit is priced on what reached the vaults
+    let (mut reserve_x, mut reserve_y) = (post_fee(DEPOSIT), DEPOSIT);
+    let mut supply = amm_math::initial_liquidity(reserve_x, reserve_y);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, supply - MINIMUM_LIQUIDITY);
+
+    // Selling x prices only what arrives in the vault
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(post_fee(amount_in), reserve_x, reserve_y, FEE).unwrap();
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(&mut context, &[pool.swap_ix(amount_in, amount_out, true)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_y).await - user_y_before, amount_out);
+    (reserve_x, reserve_y) = (reserve_x + post_fee(amount_in), reserve_y - amount_out);
+
+    // Buying x holds the minimum against what the user receives
+    let amount_out = amm_math::swap_exact_in(amount_in, reserve_y, reserve_x, FEE).unwrap();
+    assert_amm_error(process(&mut context, &[pool.swap_ix(amount_in, amount_out, false)], &[]).await, AmmError::SlippageExceeded);
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(&mut context, &[pool.swap_ix(amount_in, post_fee(amount_out), false)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_x).await - user_x_before, post_fee(amount_out));
+    (reserve_x, reserve_y) = (reserve_x - amount_out, reserve_y + amount_in);
+
+    // An exact x output is grossed up so that amount arrives
+    let amount_out = 5_000;
+    let amount_sent = pre_fee(amount_out);
+    let amount_in = amm_math::swap_exact_out(amount_sent, reserve_y, reserve_x, FEE).unwrap();
+    assert_amm_error(process(&mut context, &[pool.swap_exact_out_ix(amount_out, amount_in - 1, false)], &[]).await, AmmError::SlippageExceeded);
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(&mut context, &[pool.swap_exact_out_ix(amount_out, amount_in, false)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_x).await - user_x_before, amount_out);
+    (reserve_x, reserve_y) = (reserve_x - amount_sent, reserve_y + amount_in);
+
+    // Paying in x for an exact y output is grossed up so the vault receives the curve's input
+    let amount_in_received = amm_math::swap_exact_out(amount_out, reserve_x, reserve_y, FEE).unwrap();
+    let amount_in = pre_fee(amount_in_received);
+    assert_amm_error(process(&mut context, &[pool.swap_exact_out_ix(amount_out, amount_in - 1, true)], &[]).await, AmmError::SlippageExceeded);
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(&mut context, &[pool.swap_exact_out_ix(amount_out, amount_in, true)], &[]).await.unwrap();
+    assert_eq!(user_x_before - token_balance(&mut context, &pool.user_x).await, amount_in);
+    (reserve_x, reserve_y) = (reserve_x + post_fee(amount_in), reserve_y - amount_out);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+
+    // A later deposit sends enough x for its share to arrive
+    let minted = supply / 10;
+    let amount_x = amm_math::deposit_share(minted, reserve_x, supply).unwrap();
+    let amount_y = amm_math::deposit_share(minted, reserve_y, supply).unwrap();
+    assert_amm_error(process(&mut context, &[pool.deposit_ix(minted, amount_x, amount_y)], &[]).await, AmmError::SlippageExceeded);
+    process(&mut context, &[pool.deposit_ix(minted, pre_fee(amount_x), amount_y)], &[]).await.unwrap();
+    (reserve_x, reserve_y, supply) = (reserve_x + post_fee(pre_fee(amount_x)), reserve_y + amount_y, supply + minted);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+
+    // Withdrawing holds the minimum against the x that arrives
+    let burned = supply / 4;
+    let amount_x = amm_math::withdraw_share(burned, reserve_x, supply).unwrap();
+    let amount_y = amm_math::withdraw_share(burned, reserve_y, supply).unwrap();
+    assert_amm_error(process(&mut context, &[pool.withdraw_ix(burned, amount_x, amount_y)], &[]).await, AmmError::SlippageExceeded);
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(&mut context, &[pool.withdraw_ix(burned, post_fee(amount_x), amount_y)], &[]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &pool.user_x).await - user_x_before, post_fee(amount_x));
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x - amount_x);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, supply - burned - MINIMUM_LIQUIDITY);
+}
+
+/// Initializes a pool whose x mint is a Token-2022 mint with `extensions`
+async fn initialize_extension_pool(context: &mut ProgramTestContext, extensions: &[ExtensionType], initialize_extensions: impl Fn(&Pubkey) -> Vec<Instruction>) -> Result<(), TransactionError> {
+    let mint_x = create_extension_mint(context, extensions, initialize_extensions).await;
+    let mint_y = create_extension_mint(context, &[], |_| vec![]).await;
+
+    let pool = Pool::new(SEED, mint_x, mint_y, spl_token_2022::ID, context.payer.pubkey());
+    process(context, &[pool.initialize_ix(SEED, CurveType::ConstantProduct, weighted::EVEN_WEIGHT, 0)], &[]).await
+}
+
+#[tokio::test]
+async fn initialize_accepts_metadata_and_unfrozen_accounts() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    initialize_extension_pool(&mut context, &[ExtensionType::MetadataPointer, ExtensionType::DefaultAccountState], |mint| {
+        vec![
+            metadata_pointer::instruction::initialize(&spl_token_2022::ID, mint, Some(payer), Some(*mint)).unwrap(),
+            initialize_default_account_state(&spl_token_2022::ID, mint, &AccountState::Initialized).unwrap(),
+        ]
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_permanent_delegate_mint() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    let result = initialize_extension_pool(&mut context, &[ExtensionType::PermanentDelegate], |mint| {
+        vec![spl_token_2022::instruction::initialize_permanent_delegate(&spl_token_2022::ID, mint, &payer).unwrap()]
+    })
+    .await;
+    assert_amm_error(result, AmmError::UnsupportedMintExtension);
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_transfer_hook_mint() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    let result = initialize_extension_pool(&mut context, &[ExtensionType::TransferHook], |mint| {
+        vec![transfer_hook::instruction::initialize(&spl_token_2022::ID, mint, Some(payer), Some(Pubkey::new_unique())).unwrap()]
+    })
+    .await;
+    assert_amm_error(result, AmmError::UnsupportedMintExtension);
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_non_transferable_mint() {
+    let mut context = start().await;
+
+    let result = initialize_extension_pool(&mut context, &[ExtensionType::NonTransferable], |mint| {
+        vec![spl_token_2022::instruction::initialize_non_transferable_mint(&spl_token_2022::ID, mint).unwrap()]
+    })
+    .await;
+    assert_amm_error(result, AmmError::UnsupportedMintExtension);
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_mint_whose_accounts_start_frozen() {
+    let mut context = start().await;
+
+    let result = initialize_extension_pool(&mut context, &[ExtensionType::DefaultAccountState], |mint| {
+        vec![initialize_default_account_state(&spl_token_2022::ID, mint, &AccountState::Frozen).unwrap()]
+    })
+    .await;
+    assert_amm_error(result, AmmError::UnsupportedMintExtension);
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_confidential_transfer_mint() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    let result = initialize_extension_pool(&mut context, &[ExtensionType::ConfidentialTransferMint], |mint| {
+        vec![confidential_transfer::instruction::initialize_mint(&spl_token_2022::ID, mint, Some(payer), true, None).unwrap()]
+    })
+    .await;
+    assert_amm_error(result, AmmError::UnsupportedMintExtension);
+}
@@ -16,6 +16,8 @@ import {
   createAssociatedTokenAccount,
   mintTo,
   getAssociatedTokenAddress,
+  getAssociatedTokenAddressSync,
+  createTransferCheckedInstruction,
 } from "@solana/spl-token";
 
 describe("anchor-amm", () => {
@@ -34,9 +36,11 @@ describe("anchor-amm", () => {
   let vaultX: PublicKey;
   let vaultY: PublicKey;
   let config: PublicKey;
+  let oracle: PublicKey;
   
   const seed = new BN(Math.floor(Math.random() * 1000000));
   const fee = 30;
+  const MINIMUM_LIQUIDITY = 1_000;
   
   const deriveLPMint = async (config: PublicKey) => {
     return PublicKey.findProgramAddressSync(
@@ -52,11 +56,19 @@ describe("anchor-amm", () => {
     );
   };
 
+  const deriveOracle = async (config: PublicKey) => {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("oracle"), config.toBuffer()],
+      program.programId
+    );
+  };
+
   before(async function() {
     this.timeout(60000);
     
     [config] = await deriveConfig(seed);
     [mintLP] = await deriveLPMint(config);
+    [oracle] = await deriveOracle(config);
     
     mintX = await createMint(
       provider.connection,
@@ -121,7 +133,7 @@ describe("anchor-amm", () => {
 
   it("Initialize AMM pool", async () => {
     const tx = await program.methods
-      .initialize(seed, fee)
+      .initialize(seed, fee, { constantProduct: {} }, 5000, new BN(0))
       .accountsStrict({
         initializer: wallet.publicKey,
         mintX,
@@ -130,6 +142,7 @@ describe("anchor-amm", () => {
         vaultX,
         vaultY,
         config,
+        oracle,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -142,6 +155,7 @@ describe("anchor-amm", () => {
     assert.equal(configAccount.mintY.toString(), mintY.toString());
     assert.equal(configAccount.fee, fee);
     assert.equal(configAccount.locked, false);
+    assert.equal(configAccount.authority.toString(), wallet.publicKey.toString());
     
     const vaultXInfo = await provider.connection.getTokenAccountBalance(vaultX);
     const vaultYInfo = await provider.connection.getTokenAccountBalance(vaultY);
@@ -157,9 +171,35 @@ describe("anchor-amm", () => {
       wallet.publicKey
     );
     
+    // The first deposit mints sqrt(x * y) less the locked minimum
     const amount = new BN(50_000_000);
-    const minX = new BN(0);
-    const minY = new BN(0);
+    const maxX = new BN(50_000_000);
+    const maxY = new BN(50_000_000);
+    const lpAmount = amount.subn(MINIMUM_LIQUIDITY);
+
+    try {
+      await program.methods
+        .addLiquidity(lpAmount.addn(1), maxX, maxY)
+        .accountsStrict({
+          user: wallet.publicKey,
+          mintX,
+          mintY,
+          mintLp: mintLP,
+          vaultX,
+          vaultY,
+          userX,
+          userY,
+          userLp: userLP,
+          config,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("The locked minimum should not be minted");
+    } catch (err) {
+      assert.include(err.toString(), "SlippageExceeded");
+    }
     
     const userXBalanceBefore = await provider.connection.getTokenAccountBalance(userX);
     const userYBalanceBefore = await provider.connection.getTokenAccountBalance(userY);
@@ -167,7 +207,7 @@ describe("anchor-amm", () => {
     const vaultYBalanceBefore = await provider.connection.getTokenAccountBalance(vaultY);
     
     const tx = await program.methods
-      .addLiquidity(amount, minX, minY)
+      .addLiquidity(lpAmount, maxX, maxY)
       .accountsStrict({
         user: wallet.publicKey,
         mintX,
@@ -208,25 +248,58 @@ describe("anchor-amm", () => {
       new BN(vaultYBalanceAfter.value.amount).sub(new BN(vaultYBalanceBefore.value.amount)).toString(),
       amount.toString()
     );
-    
-    if (new BN(vaultXBalanceBefore.value.amount).eq(new BN(0)) &&
-        new BN(vaultYBalanceBefore.value.amount).eq(new BN(0))) {
-      const expectedLpAmount = Math.floor(Math.sqrt(
-        Number(amount.toString()) * Number(amount.toString())
-      ));
-      
-      assert.approximately(
-        parseInt(userLpBalanceAfter.value.amount), 
-        expectedLpAmount, 
-        10,
-        "LP token amount should match sqrt(amountX * amountY)"
-      );
-    } else {
-      assert.isAbove(
-        parseInt(userLpBalanceAfter.value.amount),
-        0,
-        "User should have received LP tokens"
-      );
+    assert.equal(userLpBalanceAfter.value.amount, lpAmount.toString());
+
+    // Later deposits are priced pro rata against the locked minimum as well,
+    // so minting `amount` LP costs exactly `amount` of each token at 1:1
+    await program.methods
+      .addLiquidity(amount, amount, amount)
+      .accountsStrict({
+        user: wallet.publicKey,
+        mintX,
+        mintY,
+        mintLp: mintLP,
+        vaultX,
+        vaultY,
+        userX,
+        userY,
+        userLp: userLP,
+        config,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const vaultXBalance = await provider.connection.getTokenAccountBalance(vaultX);
+    const userLpBalance = await provider.connection.getTokenAccountBalance(userLP);
+    assert.equal(vaultXBalance.value.amount, amount.muln(2).toString());
+    assert.equal(userLpBalance.value.amount, lpAmount.add(amount).toString());
+  });
+
+  it("Rejects a deposit that exceeds the maximum amounts", async () => {
+    try {
+      await program.methods
+        .addLiquidity(new BN(10_000_000), new BN(1), new BN(1))
+        .accountsStrict({
+          user: wallet.publicKey,
+          mintX,
+          mintY,
+          mintLp: mintLP,
+          vaultX,
+          vaultY,
+          userX,
+          userY,
+          userLp: userLP,
+          config,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Deposit should have exceeded the maximum amounts");
+    } catch (err) {
+      assert.include(err.toString(), "SlippageExceeded");
     }
   });
 
@@ -258,16 +331,18 @@ describe("anchor-amm", () => {
         user: wallet.publicKey,
         mintX,
         mintY,
+        mintLp: mintLP,
         vaultX,
         vaultY,
         userX,
         userY,
         config,
+        oracle,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
-      .rpc();
+      .rpc({ commitment: "confirmed" });
     
     const userXBalanceAfter = await provider.connection.getTokenAccountBalance(userX);
     const userYBalanceAfter = await provider.connection.getTokenAccountBalance(userY);
@@ -302,6 +377,21 @@ describe("anchor-amm", () => {
     const productAfter = new BN(vaultXBalanceAfter.value.amount).mul(new BN(vaultYBalanceAfter.value.amount));
     
     assert.isAtLeast(parseInt(productAfter.toString()), parseInt(productBefore.toString()), "Constant product invariant should hold");
+
+    // Indexers read the swap, the reserves it left and the LP supply from the logs alone
+    const swapTx = await provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const events = [...new anchor.EventParser(program.programId, program.coder).parseLogs(swapTx.meta.logMessages)];
+    assert.equal(events.length, 1);
+    assert.equal(events[0].name, "swap");
+    assert.isTrue(events[0].data.fromX);
+    assert.equal(events[0].data.amountIn.toString(), amountIn.toString());
+    assert.equal(events[0].data.amountOut.toString(), actualAmountOut.toString());
+    assert.equal(events[0].data.reserveX.toString(), vaultXBalanceAfter.value.amount);
+    assert.equal(events[0].data.reserveY.toString(), vaultYBalanceAfter.value.amount);
+    assert.equal(events[0].data.lpSupply.toString(), (await provider.connection.getTokenSupply(mintLP)).value.amount);
     
     const reverseAmountIn = new BN(5_000_000);
     
@@ -311,15 +401,468 @@ describe("anchor-amm", () => {
         user: wallet.publicKey,
         mintX,
         mintY,
+        mintLp: mintLP,
+        vaultX,
+        vaultY,
+        userX,
+        userY,
+        config,
+        oracle,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("Swap tokens for an exact output amount", async () => {
+    const amountOut = new BN(1_000_000);
+    const maxAmountIn = new BN(2_000_000);
+
+    const userXBalanceBefore = await provider.connection.getTokenAccountBalance(userX);
+    const userYBalanceBefore = await provider.connection.getTokenAccountBalance(userY);
+    const vaultXBalanceBefore = await provider.connection.getTokenAccountBalance(vaultX);
+    const vaultYBalanceBefore = await provider.connection.getTokenAccountBalance(vaultY);
+
+    const reserveIn = new BN(vaultXBalanceBefore.value.amount);
+    const reserveOut = new BN(vaultYBalanceBefore.value.amount);
+    const ceilDiv = (a: BN, b: BN) => a.add(b).sub(new BN(1)).div(b);
+    const amountInAfterFee = ceilDiv(reserveIn.mul(amountOut), reserveOut.sub(amountOut));
+    const expectedAmountIn = ceilDiv(amountInAfterFee.mul(new BN(10000)), new BN(10000 - fee));
+
+    const tx = await program.methods
+      .swapExactOut(amountOut, maxAmountIn, true)
+      .accountsStrict({
+        user: wallet.publicKey,
+        mintX,
+        mintY,
+        mintLp: mintLP,
+        vaultX,
+        vaultY,
+        userX,
+        userY,
+        config,
+        oracle,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const userXBalanceAfter = await provider.connection.getTokenAccountBalance(userX);
+    const userYBalanceAfter = await provider.connection.getTokenAccountBalance(userY);
+
+    assert.equal(
+      new BN(userYBalanceAfter.value.amount).sub(new BN(userYBalanceBefore.value.amount)).toString(),
+      amountOut.toString()
+    );
+    assert.equal(
+      new BN(userXBalanceBefore.value.amount).sub(new BN(userXBalanceAfter.value.amount)).toString(),
+      expectedAmountIn.toString()
+    );
+  });
+
+  it("Observe the TWAP recorded by swaps", async () => {
+    const oracleAccount = await program.account.oracle.fetch(oracle);
+    assert.equal(oracleAccount.config.toString(), config.toString());
+    assert.isAtLeast(oracleAccount.len, 1);
+
+    // Wait out the current second so the window ends after the latest swap
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+
+    const twap = await program.methods
+      .observe(1)
+      .accountsStrict({
+        mintX,
+        mintY,
+        vaultX,
+        vaultY,
+        config,
+        oracle,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .view();
+
+    // Over the last second the price is the current spot price in Q64.64
+    const reserveX = new BN((await provider.connection.getTokenAccountBalance(vaultX)).value.amount);
+    const reserveY = new BN((await provider.connection.getTokenAccountBalance(vaultY)).value.amount);
+    assert.equal(twap.priceX.toString(), reserveY.shln(64).div(reserveX).toString());
+    assert.equal(twap.priceY.toString(), reserveX.shln(64).div(reserveY).toString());
+  });
+
+  it("Route a swap through two pools", async () => {
+    // A second pool pairing x with a new token z
+    const seedZ = seed.addn(1);
+    const [configZ] = await deriveConfig(seedZ);
+    const [mintLPZ] = await deriveLPMint(configZ);
+    const [oracleZ] = await deriveOracle(configZ);
+
+    const mintZ = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    const userZ = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      mintZ,
+      wallet.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      mintZ,
+      userZ,
+      wallet.publicKey,
+      1_000_000_000
+    );
+
+    const vaultXZ = await getAssociatedTokenAddress(mintX, configZ, true);
+    const vaultZ = await getAssociatedTokenAddress(mintZ, configZ, true);
+
+    await program.methods
+      .initialize(seedZ, fee, { constantProduct: {} }, 5000, new BN(0))
+      .accountsStrict({
+        initializer: wallet.publicKey,
+        mintX,
+        mintY: mintZ,
+        mintLp: mintLPZ,
+        vaultX: vaultXZ,
+        vaultY: vaultZ,
+        config: configZ,
+        oracle: oracleZ,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const userLPZ = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet.payer,
+      mintLPZ,
+      wallet.publicKey
+    );
+
+    await program.methods
+      .addLiquidity(new BN(50_000_000 - MINIMUM_LIQUIDITY), new BN(50_000_000), new BN(50_000_000))
+      .accountsStrict({
+        user: wallet.publicKey,
+        mintX,
+        mintY: mintZ,
+        mintLp: mintLPZ,
+        vaultX: vaultXZ,
+        vaultY: vaultZ,
+        userX,
+        userY: userZ,
+        userLp: userLPZ,
+        config: configZ,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const hop = (config: PublicKey, oracle: PublicKey, mintX: PublicKey, mintY: PublicKey, mintLp: PublicKey, vaultX: PublicKey, vaultY: PublicKey) =>
+      [config, oracle, mintX, mintY, mintLp, vaultX, vaultY].map((pubkey, i) => ({
+        pubkey,
+        isSigner: false,
+        isWritable: i == 1 || i >= 5,
+      }));
+
+    const userXBalanceBefore = await provider.connection.getTokenAccountBalance(userX);
+    const userYBalanceBefore = await provider.connection.getTokenAccountBalance(userY);
+    const userZBalanceBefore = await provider.connection.getTokenAccountBalance(userZ);
+
+    // y -> x in the first pool, then x -> z in the second
+    const amountIn = new BN(1_000_000);
+    await program.methods
+      .routeSwap(amountIn, new BN(1))
+      .accountsStrict({
+        user: wallet.publicKey,
+        userIn: userY,
+        userOut: userZ,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        ...hop(config, oracle, mintX, mintY, mintLP, vaultX, vaultY),
+        ...hop(configZ, oracleZ, mintX, mintZ, mintLPZ, vaultXZ, vaultZ),
+      ])
+      .rpc();
+
+    const userXBalanceAfter = await provider.connection.getTokenAccountBalance(userX);
+    const userYBalanceAfter = await provider.connection.getTokenAccountBalance(userY);
+    const userZBalanceAfter = await provider.connection.getTokenAccountBalance(userZ);
+
+    assert.equal(
+      new BN(userYBalanceBefore.value.amount).sub(new BN(userYBalanceAfter.value.amount)).toString(),
+      amountIn.toString()
+    );
+    // The middle token moves between vaults and never reaches the user
+    assert.equal(userXBalanceAfter.value.amount, userXBalanceBefore.value.amount);
+    assert.isTrue(
+      new BN(userZBalanceAfter.value.amount).gt(new BN(userZBalanceBefore.value.amount))
+    );
+  });
+
+  it("Withdraw from the AMM pool", async () => {
+    const userLpBalanceBefore = await provider.connection.getTokenAccountBalance(userLP);
+    const vaultXBalanceBefore = await provider.connection.getTokenAccountBalance(vaultX);
+    const vaultYBalanceBefore = await provider.connection.getTokenAccountBalance(vaultY);
+    const userXBalanceBefore = await provider.connection.getTokenAccountBalance(userX);
+    const userYBalanceBefore = await provider.connection.getTokenAccountBalance(userY);
+    // The locked minimum owns its share of the vaults too
+    const lpSupply = new BN((await provider.connection.getTokenSupply(mintLP)).value.amount)
+      .addn(MINIMUM_LIQUIDITY);
+
+    const amount = new BN(userLpBalanceBefore.value.amount).div(new BN(2));
+    const expectedX = new BN(vaultXBalanceBefore.value.amount).mul(amount).div(lpSupply);
+    const expectedY = new BN(vaultYBalanceBefore.value.amount).mul(amount).div(lpSupply);
+
+    const tx = await program.methods
+      .withdraw(amount, expectedX, expectedY)
+      .accountsStrict({
+        user: wallet.publicKey,
+        mintX,
+        mintY,
+        mintLp: mintLP,
         vaultX,
         vaultY,
         userX,
         userY,
+        userLp: userLP,
         config,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
+
+    const userLpBalanceAfter = await provider.connection.getTokenAccountBalance(userLP);
+    const userXBalanceAfter = await provider.connection.getTokenAccountBalance(userX);
+    const userYBalanceAfter = await provider.connection.getTokenAccountBalance(userY);
+
+    assert.equal(
+      new BN(userLpBalanceBefore.value.amount).sub(new BN(userLpBalanceAfter.value.amount)).toString(),
+      amount.toString()
+    );
+    assert.equal(
+      new BN(userXBalanceAfter.value.amount).sub(new BN(userXBalanceBefore.value.amount)).toString(),
+      expectedX.toString()
+    );
+    assert.equal(
+      new BN(userYBalanceAfter.value.amount).sub(new BN(userYBalanceBefore.value.amount)).toString(),
+      expectedY.toString()
+    );
+  });
+
+  it("Flash loan is repaid with its fee", async () => {
+    await program.methods
+      .setFlashFee(100)
+      .accountsStrict({ authority: wallet.publicKey, config })
+      .rpc();
+
+    const amount = new BN(100_000);
+    const owed = amount.add(amount.muln(100).addn(9_999).divn(10_000));
+
+    // SPL Token is the receiver, and the transfer back to the vault its instruction
+    const repay = (repayAmount: BN) =>
+      createTransferCheckedInstruction(userX, mintX, vaultX, wallet.publicKey, BigInt(repayAmount.toString()), 6);
+
+    const flashLoan = (repayAmount: BN) => {
+      const instruction = repay(repayAmount);
+      return program.methods
+        .flashLoan(amount, instruction.data)
+        .accountsStrict({
+          mint: mintX,
+          vault: vaultX,
+          destination: userX,
+          config,
+          receiverProgram: TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(instruction.keys)
+        .rpc();
+    };
+
+    try {
+      await flashLoan(owed.subn(1));
+      assert.fail("A flash loan short of its fee should fail");
+    } catch (err) {
+      assert.include(err.toString(), "FlashLoanNotRepaid");
+    }
+
+    const vaultXBalanceBefore = await provider.connection.getTokenAccountBalance(vaultX);
+    await flashLoan(owed);
+    const vaultXBalanceAfter = await provider.connection.getTokenAccountBalance(vaultX);
+
+    assert.equal(
+      new BN(vaultXBalanceAfter.value.amount).sub(new BN(vaultXBalanceBefore.value.amount)).toString(),
+      owed.sub(amount).toString()
+    );
+
+    const configAccount = await program.account.config.fetch(config);
+    assert.equal(configAccount.flashFee, 100);
+    assert.equal(configAccount.locked, false);
+  });
+
+  it("Authority updates the fee and locks the pool", async () => {
+    await program.methods
+      .setFee(50)
+      .accountsStrict({ authority: wallet.publicKey, config })
+      .rpc();
+
+    await program.methods
+      .setLocked(true)
+      .accountsStrict({ authority: wallet.publicKey, config })
+      .rpc();
+
+    let configAccount = await program.account.config.fetch(config);
+    assert.equal(configAccount.fee, 50);
+    assert.equal(configAccount.locked, true);
+
+    try {
+      await program.methods
+        .swap(new BN(1_000_000), new BN(1), true)
+        .accountsStrict({
+          user: wallet.publicKey,
+          mintX,
+          mintY,
+          mintLp: mintLP,
+          vaultX,
+          vaultY,
+          userX,
+          userY,
+          config,
+          oracle,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Swap should fail while the pool is locked");
+    } catch (err) {
+      assert.include(err.toString(), "PoolLocked");
+    }
+
+    await program.methods
+      .setLocked(false)
+      .accountsStrict({ authority: wallet.publicKey, config })
+      .rpc();
+
+    configAccount = await program.account.config.fetch(config);
+    assert.equal(configAccount.locked, false);
+  });
+
+  it("Rejects admin instructions from a non-authority signer", async () => {
+    const stranger = Keypair.generate();
+
+    try {
+      await program.methods
+        .setFee(0)
+        .accountsStrict({ authority: stranger.publicKey, config })
+        .signers([stranger])
+        .rpc();
+      assert.fail("Only the authority should be able to set the fee");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+  });
+
+  it("Factory creates the canonical pool for a sorted pair and fee tier", async () => {
+    const [factory] = PublicKey.findProgramAddressSync(
+      [Buffer.from("factory")],
+      program.programId
+    );
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    await program.methods
+      .initializeFactory(wallet.publicKey)
+      .accountsStrict({
+        upgradeAuthority: wallet.publicKey,
+        factory,
+        program: program.programId,
+        programData,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const setFeeTierTx = await program.methods
+      .setFeeTier(fee, true)
+      .accountsStrict({ admin: wallet.publicKey, factory })
+      .rpc({ commitment: "confirmed" });
+
+    const feeTierLogs = (await provider.connection.getTransaction(setFeeTierTx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    })).meta.logMessages;
+    const [feeTierSet] = [...new anchor.EventParser(program.programId, program.coder).parseLogs(feeTierLogs)];
+    assert.equal(feeTierSet.name, "feeTierSet");
+    assert.equal(feeTierSet.data.factory.toString(), factory.toString());
+    assert.equal(feeTierSet.data.fee, fee);
+    assert.isTrue(feeTierSet.data.enabled);
+
+    const [mintA, mintB] = [mintX, mintY].sort((a, b) => a.toBuffer().compare(b.toBuffer()));
+    const [canonical] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), mintA.toBuffer(), mintB.toBuffer(), new BN(fee).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+    const accounts = (mintX: PublicKey, mintY: PublicKey, config: PublicKey) => ({
+      initializer: wallet.publicKey,
+      mintX,
+      mintY,
+      mintLp: PublicKey.findProgramAddressSync([Buffer.from("lp"), config.toBuffer()], program.programId)[0],
+      vaultX: getAssociatedTokenAddressSync(mintX, config, true),
+      vaultY: getAssociatedTokenAddressSync(mintY, config, true),
+      config,
+      oracle: PublicKey.findProgramAddressSync([Buffer.from("oracle"), config.toBuffer()], program.programId)[0],
+      factory,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    try {
+      const [reversed] = PublicKey.findProgramAddressSync(
+        [Buffer.from("config"), mintB.toBuffer(), mintA.toBuffer(), new BN(fee).toArrayLike(Buffer, "le", 2)],
+        program.programId
+      );
+      await program.methods
+        .createPool(fee, { constantProduct: {} }, 5000, new BN(0))
+        .accountsStrict(accounts(mintB, mintA, reversed))
+        .rpc();
+      assert.fail("Mints out of order should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "UnsortedMints");
+    }
+
+    await program.methods
+      .createPool(fee, { constantProduct: {} }, 5000, new BN(0))
+      .accountsStrict(accounts(mintA, mintB, canonical))
+      .rpc();
+
+    const configAccount = await program.account.config.fetch(canonical);
+    assert.equal(configAccount.factory, true);
+    assert.equal(configAccount.feeTier, fee);
+    assert.equal(configAccount.authority.toString(), wallet.publicKey.toString());
+
+    try {
+      await program.methods
+        .createPool(fee + 1, { constantProduct: {} }, 5000, new BN(0))
+        .accountsStrict(accounts(mintA, mintB, PublicKey.findProgramAddressSync(
+          [Buffer.from("config"), mintA.toBuffer(), mintB.toBuffer(), new BN(fee + 1).toArrayLike(Buffer, "le", 2)],
+          program.programId
+        )[0]))
+        .rpc();
+      assert.fail("Only the factory's fee tiers should be offered");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidFeeTier");
+    }
   });
 });
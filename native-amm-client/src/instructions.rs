@@ -0,0 +1,519 @@
+use bytemuck::{bytes_of, Pod};
+use solana_program::{
+    bpf_loader_upgradeable,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+use amm_math::weighted::EVEN_WEIGHT;
+use native_amm::instruction::{
+    AmmInstructions, CreatePool, Deposit, FlashLoan, Initialize, InitializeFactory, Observe,
+    ProposeAuthority, RampAmp, RouteSwap, SetFee, SetFeeTier, SetFlashFee, SetLocked, Swap,
+    SwapExactOut, Withdraw,
+};
+use native_amm::state::{Curve, CurveType};
+use native_amm::ID;
+
+use crate::{factory_address, Pool};
+
+fn data<T: Pod>(instruction: AmmInstructions, payload: &T) -> Vec<u8> {
+    [&[instruction as u8], bytes_of(payload)].concat()
+}
+
+/// Accounts shared by `deposit_ix` and `withdraw_ix`
+fn liquidity_accounts(
+    pool: &Pool,
+    user: &Pubkey,
+    user_x: &Pubkey,
+    user_y: &Pubkey,
+    user_lp: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*user, true),
+        AccountMeta::new_readonly(pool.mint_x, false),
+        AccountMeta::new_readonly(pool.mint_y, false),
+        AccountMeta::new(pool.mint_lp, false),
+        AccountMeta::new(*user_x, false),
+        AccountMeta::new(*user_y, false),
+        AccountMeta::new(*user_lp, false),
+        AccountMeta::new(pool.vault_x, false),
+        AccountMeta::new(pool.vault_y, false),
+        AccountMeta::new_readonly(pool.config, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(pool.token_program_x, false),
+        AccountMeta::new_readonly(pool.token_program_y, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]
+}
+
+/// Accounts shared by `swap_ix` and `swap_exact_out_ix`
+fn swap_accounts(pool: &Pool, user: &Pubkey, user_x: &Pubkey, user_y: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*user, true),
+        AccountMeta::new_readonly(pool.mint_x, false),
+        AccountMeta::new_readonly(pool.mint_y, false),
+        AccountMeta::new_readonly(pool.mint_lp, false),
+        AccountMeta::new(pool.vault_x, false),
+        AccountMeta::new(pool.vault_y, false),
+        AccountMeta::new(*user_x, false),
+        AccountMeta::new(*user_y, false),
+        AccountMeta::new_readonly(pool.config, false),
+        AccountMeta::new(pool.oracle, false),
+        AccountMeta::new_readonly(pool.token_program_x, false),
+        AccountMeta::new_readonly(pool.token_program_y, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]
+}
+
+/// Accounts shared by `initialize_ix` and `create_pool_ix`
+fn initialize_accounts(pool: &Pool, initializer: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new_readonly(pool.mint_x, false),
+        AccountMeta::new_readonly(pool.mint_y, false),
+        AccountMeta::new(pool.mint_lp, false),
+        AccountMeta::new(pool.vault_x, false),
+        AccountMeta::new(pool.vault_y, false),
+        AccountMeta::new(pool.config, false),
+        AccountMeta::new(pool.oracle, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(pool.token_program_x, false),
+        AccountMeta::new_readonly(pool.token_program_y, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]
+}
+
+/// The `CurveType`, weight of x and amplification the program stores for `curve`
+fn curve_params(curve: Curve) -> (CurveType, u16, u64) {
+    match curve {
+        Curve::ConstantProduct => (CurveType::ConstantProduct, EVEN_WEIGHT, 0),
+        Curve::Stable { amp } => (CurveType::Stable, EVEN_WEIGHT, amp),
+        Curve::Weighted { weight_x } => (CurveType::ConstantProduct, weight_x, 0),
+    }
+}
+
+/// Creates the pool's config, oracle, vaults and LP mint, paid for by `initializer`
+pub fn initialize_ix(
+    pool: &Pool,
+    initializer: &Pubkey,
+    fee: u16,
+    authority: &Pubkey,
+    curve: Curve,
+) -> Instruction {
+    let (curve, weight_x, amp) = curve_params(curve);
+
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::Initialize,
+            &Initialize {
+                seed: pool.seed,
+                fee,
+                authority: *authority,
+                curve: curve as u8,
+                padding: [0; 3],
+                weight_x,
+                amp,
+            },
+        ),
+        initialize_accounts(pool, initializer),
+    )
+}
+
+/// Creates the factory's canonical pool `pool`, from [`Pool::canonical`], at
+/// `fee_tier`, paid for by `initializer`. The factory admin becomes its authority
+pub fn create_pool_ix(
+    pool: &Pool,
+    initializer: &Pubkey,
+    fee_tier: u16,
+    curve: Curve,
+) -> Instruction {
+    let (curve, weight_x, amp) = curve_params(curve);
+
+    let mut accounts = initialize_accounts(pool, initializer);
+    accounts.push(AccountMeta::new_readonly(factory_address().0, false));
+
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::CreatePool,
+            &CreatePool {
+                fee_tier,
+                weight_x,
+                curve: curve as u8,
+                padding: [0; 3],
+                amp,
+            },
+        ),
+        accounts,
+    )
+}
+
+/// Mints `amount` LP for at most `max_x` and `max_y`. On an empty pool both maxima
+/// are deposited and `amount` is the minimum LP to receive
+pub fn deposit_ix(
+    pool: &Pool,
+    user: &Pubkey,
+    user_x: &Pubkey,
+    user_y: &Pubkey,
+    user_lp: &Pubkey,
+    amount: u64,
+    max_x: u64,
+    max_y: u64,
+    expiration: i64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::Deposit,
+            &Deposit {
+                amount,
+                max_x,
+                max_y,
+                expiration,
+            },
+        ),
+        liquidity_accounts(pool, user, user_x, user_y, user_lp),
+    )
+}
+
+/// Burns `amount` LP for at least `min_x` and `min_y`
+pub fn withdraw_ix(
+    pool: &Pool,
+    user: &Pubkey,
+    user_x: &Pubkey,
+    user_y: &Pubkey,
+    user_lp: &Pubkey,
+    amount: u64,
+    min_x: u64,
+    min_y: u64,
+    expiration: i64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::Withdraw,
+            &Withdraw {
+                amount,
+                min_x,
+                min_y,
+                expiration,
+            },
+        ),
+        liquidity_accounts(pool, user, user_x, user_y, user_lp),
+    )
+}
+
+/// Sells exactly `amount` of x (or y when `from_x` is false) for at least `min`
+pub fn swap_ix(
+    pool: &Pool,
+    user: &Pubkey,
+    user_x: &Pubkey,
+    user_y: &Pubkey,
+    amount: u64,
+    min: u64,
+    from_x: bool,
+    expiration: i64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::Swap,
+            &Swap {
+                amount,
+                min,
+                expiration,
+                from_x: from_x as u8,
+                padding: [0; 7],
+            },
+        ),
+        swap_accounts(pool, user, user_x, user_y),
+    )
+}
+
+/// Buys exactly `amount_out` of y (or x when `from_x` is false) for at most `max_in`
+pub fn swap_exact_out_ix(
+    pool: &Pool,
+    user: &Pubkey,
+    user_x: &Pubkey,
+    user_y: &Pubkey,
+    amount_out: u64,
+    max_in: u64,
+    from_x: bool,
+    expiration: i64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::SwapExactOut,
+            &SwapExactOut {
+                amount_out,
+                max_in,
+                expiration,
+                from_x: from_x as u8,
+                padding: [0; 7],
+            },
+        ),
+        swap_accounts(pool, user, user_x, user_y),
+    )
+}
+
+/// Sells exactly `amount` of `user_in`'s token through `pools` in order, for at
+/// least `min` of the last pool's other token in `user_out`
+pub fn route_swap_ix(
+    pools: &[Pool],
+    user: &Pubkey,
+    user_in: &Pubkey,
+    user_out: &Pubkey,
+    amount: u64,
+    min: u64,
+    expiration: i64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*user, true),
+        AccountMeta::new(*user_in, false),
+        AccountMeta::new(*user_out, false),
+    ];
+    for pool in pools {
+        accounts.extend([
+            AccountMeta::new_readonly(pool.config, false),
+            AccountMeta::new(pool.oracle, false),
+            AccountMeta::new_readonly(pool.mint_x, false),
+            AccountMeta::new_readonly(pool.mint_y, false),
+            AccountMeta::new_readonly(pool.mint_lp, false),
+            AccountMeta::new(pool.vault_x, false),
+            AccountMeta::new(pool.vault_y, false),
+            AccountMeta::new_readonly(pool.token_program_x, false),
+            AccountMeta::new_readonly(pool.token_program_y, false),
+        ]);
+    }
+
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::RouteSwap,
+            &RouteSwap {
+                amount,
+                min,
+                expiration,
+            },
+        ),
+        accounts,
+    )
+}
+
+/// Reads the TWAPs over the last `window` seconds as return data, see `decode_twap`
+pub fn observe_ix(pool: &Pool, window: u32) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(AmmInstructions::Observe, &Observe { window }),
+        vec![
+            AccountMeta::new_readonly(pool.config, false),
+            AccountMeta::new_readonly(pool.oracle, false),
+            AccountMeta::new_readonly(pool.vault_x, false),
+            AccountMeta::new_readonly(pool.vault_y, false),
+        ],
+    )
+}
+
+/// Creates the oracle of a pool initialized before pools had one, paid for by
+/// `payer`. Swaps on the pool fail until it exists
+pub fn init_oracle_ix(pool: &Pool, payer: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &[AmmInstructions::InitOracle as u8],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(pool.config, false),
+            AccountMeta::new(pool.oracle, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Rewrites the config of a pool from the program's first release in the
+/// current layout, paid for by `payer`. Its oracle still has to be created
+/// with `init_oracle_ix` before it can swap
+pub fn migrate_config_ix(config: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &[AmmInstructions::MigrateConfig as u8],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*config, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Lends `amount` of the pool's x or y into `destination`, then calls
+/// `receiver_program` with `receiver_accounts` and `receiver_data`. The receiver
+/// must pay back the amount and the flash fee to the vault before it returns
+pub fn flash_loan_ix(
+    pool: &Pool,
+    from_x: bool,
+    destination: &Pubkey,
+    receiver_program: &Pubkey,
+    receiver_accounts: &[AccountMeta],
+    receiver_data: &[u8],
+    amount: u64,
+) -> Instruction {
+    let (mint, vault, token_program) = if from_x {
+        (pool.mint_x, pool.vault_x, pool.token_program_x)
+    } else {
+        (pool.mint_y, pool.vault_y, pool.token_program_y)
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new(pool.config, false),
+        AccountMeta::new_readonly(mint, false),
+        AccountMeta::new(vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(token_program, false),
+        AccountMeta::new_readonly(*receiver_program, false),
+    ];
+    accounts.extend_from_slice(receiver_accounts);
+
+    Instruction::new_with_bytes(
+        ID,
+        &[
+            data(AmmInstructions::FlashLoan, &FlashLoan { amount }).as_slice(),
+            receiver_data,
+        ]
+        .concat(),
+        accounts,
+    )
+}
+
+pub fn set_fee_ix(config: &Pubkey, authority: &Pubkey, fee: u16) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(AmmInstructions::SetFee, &SetFee { fee }),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+pub fn set_flash_fee_ix(config: &Pubkey, authority: &Pubkey, flash_fee: u16) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(AmmInstructions::SetFlashFee, &SetFlashFee { flash_fee }),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+/// Ramps a StableSwap pool's amplification from its current value to
+/// `target_amp` at the unix timestamp `ramp_end`
+pub fn ramp_amp_ix(
+    config: &Pubkey,
+    authority: &Pubkey,
+    target_amp: u64,
+    ramp_end: i64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::RampAmp,
+            &RampAmp {
+                target_amp,
+                ramp_end,
+            },
+        ),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+pub fn set_locked_ix(config: &Pubkey, authority: &Pubkey, locked: bool) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::SetLocked,
+            &SetLocked {
+                locked: locked as u8,
+            },
+        ),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+/// First step of an authority transfer, signed by the current authority
+pub fn propose_authority_ix(
+    config: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::ProposeAuthority,
+            &ProposeAuthority {
+                authority: *new_authority,
+            },
+        ),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+/// Second step of an authority transfer, signed by the proposed authority
+pub fn accept_authority_ix(config: &Pubkey, pending_authority: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &[AmmInstructions::AcceptAuthority as u8],
+        vec![
+            AccountMeta::new_readonly(*pending_authority, true),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+/// Creates the factory with `admin` over its fee tiers, signed and paid for by
+/// the program's upgrade authority
+pub fn initialize_factory_ix(upgrade_authority: &Pubkey, admin: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::InitializeFactory,
+            &InitializeFactory { admin: *admin },
+        ),
+        vec![
+            AccountMeta::new(*upgrade_authority, true),
+            AccountMeta::new(factory_address().0, false),
+            AccountMeta::new_readonly(bpf_loader_upgradeable::get_program_data_address(&ID), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Offers `fee` as a fee tier, or stops offering it, signed by the factory admin
+pub fn set_fee_tier_ix(admin: &Pubkey, fee: u16, enabled: bool) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &data(
+            AmmInstructions::SetFeeTier,
+            &SetFeeTier {
+                fee,
+                enabled: enabled as u8,
+                padding: [0; 1],
+            },
+        ),
+        vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(factory_address().0, false),
+        ],
+    )
+}
@@ -0,0 +1,157 @@
+//! Off-chain helpers for `native-amm`: PDA derivation, instruction builders and
+//! account decoders. The instruction payloads and `Config` layout are the program's
+//! own types, so the client cannot drift from what the program parses.
+#![allow(clippy::too_many_arguments)]
+
+mod instructions;
+mod pda;
+
+pub use instructions::*;
+pub use native_amm::{
+    error::AmmError,
+    factory::Factory,
+    instruction,
+    oracle::Oracle,
+    state::{Config, Curve, CurveType},
+    ID,
+};
+pub use pda::*;
+
+use bytemuck::Pod;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+/// Every address an instruction on one pool needs, besides the user's own accounts
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pool {
+    pub seed: u64,
+    pub config: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub mint_lp: Pubkey,
+    pub oracle: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+    pub token_program_x: Pubkey,
+    pub token_program_y: Pubkey,
+}
+
+impl Pool {
+    /// Derives the pool addresses for `seed`. The token programs are the owners of
+    /// `mint_x` and `mint_y`, either SPL Token or Token-2022
+    pub fn new(
+        seed: u64,
+        mint_x: Pubkey,
+        mint_y: Pubkey,
+        token_program_x: Pubkey,
+        token_program_y: Pubkey,
+    ) -> Self {
+        let (config, _) = config_address(seed);
+
+        Self::with_config(
+            config,
+            seed,
+            mint_x,
+            mint_y,
+            token_program_x,
+            token_program_y,
+        )
+    }
+
+    /// Derives the addresses of the factory's canonical pool for the pair at
+    /// `fee_tier`. The mints may come in either order, and each token program
+    /// goes with its mint
+    pub fn canonical(
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        fee_tier: u16,
+        token_program_a: Pubkey,
+        token_program_b: Pubkey,
+    ) -> Self {
+        let ((mint_x, token_program_x), (mint_y, token_program_y)) = if mint_a <= mint_b {
+            ((mint_a, token_program_a), (mint_b, token_program_b))
+        } else {
+            ((mint_b, token_program_b), (mint_a, token_program_a))
+        };
+        let (config, _) = canonical_config_address(&mint_x, &mint_y, fee_tier);
+
+        Self::with_config(config, 0, mint_x, mint_y, token_program_x, token_program_y)
+    }
+
+    fn with_config(
+        config: Pubkey,
+        seed: u64,
+        mint_x: Pubkey,
+        mint_y: Pubkey,
+        token_program_x: Pubkey,
+        token_program_y: Pubkey,
+    ) -> Self {
+        Self {
+            seed,
+            config,
+            mint_x,
+            mint_y,
+            mint_lp: lp_mint_address(&config).0,
+            oracle: oracle_address(&config).0,
+            vault_x: vault_address(&mint_x, &config).0,
+            vault_y: vault_address(&mint_y, &config).0,
+            token_program_x,
+            token_program_y,
+        }
+    }
+
+    /// Rebuilds the pool addresses from a decoded `Config`
+    pub fn from_config(config: &Config, token_program_x: Pubkey, token_program_y: Pubkey) -> Self {
+        if config.factory == 1 {
+            return Self::canonical(
+                config.mint_x,
+                config.mint_y,
+                config.fee_tier,
+                token_program_x,
+                token_program_y,
+            );
+        }
+
+        Self::new(
+            config.seed,
+            config.mint_x,
+            config.mint_y,
+            token_program_x,
+            token_program_y,
+        )
+    }
+}
+
+/// Copies the leading `size_of::<T>()` bytes of `data` into a `T`. Unlike
+/// viewing them in place, as the program does, this accepts data at any
+/// alignment, such as a slice into a larger RPC buffer
+fn decode<T: Pod>(data: &[u8]) -> Result<T, ProgramError> {
+    data.get(..size_of::<T>())
+        .and_then(|data| bytemuck::try_pod_read_unaligned(data).ok())
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Decodes the data of a pool's config account
+pub fn decode_config(data: &[u8]) -> Result<Config, ProgramError> {
+    decode(data)
+}
+
+/// Decodes the data of the factory account
+pub fn decode_factory(data: &[u8]) -> Result<Factory, ProgramError> {
+    decode(data)
+}
+
+/// Decodes the data of a pool's oracle account
+pub fn decode_oracle(data: &[u8]) -> Result<Oracle, ProgramError> {
+    decode(data)
+}
+
+/// Decodes the return data of `observe_ix` into the Q64.64 TWAPs of x in y
+/// and of y in x
+pub fn decode_twap(return_data: &[u8]) -> Result<(u128, u128), ProgramError> {
+    let (price_x, price_y) = return_data
+        .split_first_chunk::<16>()
+        .and_then(|(price_x, rest)| Some((price_x, rest.first_chunk::<16>()?)))
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    Ok((u128::from_le_bytes(*price_x), u128::from_le_bytes(*price_y)))
+}
@@ -0,0 +1,42 @@
+use solana_program::pubkey::Pubkey;
+
+use native_amm::ID;
+
+/// Pool config, seeded by the pool's `seed`
+pub fn config_address(seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config", seed.to_le_bytes().as_ref()], &ID)
+}
+
+/// Config of the factory's canonical pool for a mint pair at `fee_tier`, with
+/// `mint_x` the lower of the two
+pub fn canonical_config_address(mint_x: &Pubkey, mint_y: &Pubkey, fee_tier: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"config",
+            mint_x.as_ref(),
+            mint_y.as_ref(),
+            fee_tier.to_le_bytes().as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// The factory holding the fee tiers canonical pools are created at
+pub fn factory_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"factory"], &ID)
+}
+
+/// Vault holding `mint` for the pool at `config`
+pub fn vault_address(mint: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), config.as_ref()], &ID)
+}
+
+/// Price oracle of the pool at `config`
+pub fn oracle_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"oracle", config.as_ref()], &ID)
+}
+
+/// LP mint of the pool at `config`
+pub fn lp_mint_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[config.as_ref()], &ID)
+}
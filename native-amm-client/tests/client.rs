@@ -0,0 +1,782 @@
+//! Drives the program through the client builders only, so a wrong account order,
+//! tag or PDA seed in the client fails here.
+
+use amm_math::weighted::EVEN_WEIGHT;
+use bytemuck::bytes_of;
+use native_amm_client::{
+    accept_authority_ix, canonical_config_address, config_address, create_pool_ix, decode_config,
+    decode_factory, decode_oracle, decode_twap, deposit_ix, factory_address, flash_loan_ix,
+    init_oracle_ix, initialize_factory_ix, initialize_ix, lp_mint_address, migrate_config_ix,
+    observe_ix, oracle_address, propose_authority_ix, ramp_amp_ix, route_swap_ix, set_fee_ix,
+    set_fee_tier_ix, set_flash_fee_ix, set_locked_ix, swap_exact_out_ix, swap_ix, vault_address,
+    withdraw_ix, AmmError, Config, Curve, CurveType, Oracle, Pool, ID,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    bpf_loader_upgradeable,
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+
+const SEED: u64 = 7;
+const FEE: u16 = 30;
+const DEPOSIT: u64 = 1_000_000;
+
+struct User {
+    x: Pubkey,
+    y: Pubkey,
+    lp: Pubkey,
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.banks_client.get_latest_blockhash().await?;
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::ID,
+                &mint.pubkey(),
+                &payer,
+                None,
+                6,
+            )
+            .unwrap(),
+        ],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    mint.pubkey()
+}
+
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &account.pubkey(),
+                rent.minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_account3(
+                &spl_token::ID,
+                &account.pubkey(),
+                mint,
+                &payer,
+            )
+            .unwrap(),
+        ],
+        &[&account],
+    )
+    .await
+    .unwrap();
+
+    if amount > 0 {
+        process(
+            context,
+            &[spl_token::instruction::mint_to(
+                &spl_token::ID,
+                mint,
+                &account.pubkey(),
+                &payer,
+                &[],
+                amount,
+            )
+            .unwrap()],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    account.pubkey()
+}
+
+async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+async fn fetch_config(context: &mut ProgramTestContext, pool: &Pool) -> Config {
+    let account = context
+        .banks_client
+        .get_account(pool.config)
+        .await
+        .unwrap()
+        .unwrap();
+
+    decode_config(&account.data).unwrap()
+}
+
+/// Starts a bank with an initialized, seeded pool owned by the payer
+async fn setup() -> (ProgramTestContext, Pool, User) {
+    setup_curve(Curve::ConstantProduct).await
+}
+
+async fn setup_curve(curve: Curve) -> (ProgramTestContext, Pool, User) {
+    let mut program_test = ProgramTest::new(
+        "native_amm",
+        ID,
+        processor!(native_amm::process_instruction),
+    );
+    program_test.prefer_bpf(false);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let mint_x = create_mint(&mut context).await;
+    let mint_y = create_mint(&mut context).await;
+    let pool = Pool::new(SEED, mint_x, mint_y, spl_token::ID, spl_token::ID);
+
+    process(
+        &mut context,
+        &[initialize_ix(&pool, &payer, FEE, &payer, curve)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let user = User {
+        x: create_token_account(&mut context, &mint_x, 10 * DEPOSIT).await,
+        y: create_token_account(&mut context, &mint_y, 10 * DEPOSIT).await,
+        lp: create_token_account(&mut context, &pool.mint_lp, 0).await,
+    };
+
+    process(
+        &mut context,
+        &[deposit_ix(
+            &pool,
+            &payer,
+            &user.x,
+            &user.y,
+            &user.lp,
+            0,
+            DEPOSIT,
+            DEPOSIT,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    (context, pool, user)
+}
+
+#[test]
+fn pool_addresses_match_the_program_seeds() {
+    let mint_x = Pubkey::new_unique();
+    let mint_y = Pubkey::new_unique();
+    let pool = Pool::new(SEED, mint_x, mint_y, spl_token::ID, spl_token::ID);
+
+    let (config, _) = Pubkey::find_program_address(&[b"config", SEED.to_le_bytes().as_ref()], &ID);
+    assert_eq!(config_address(SEED).0, config);
+    assert_eq!(pool.config, config);
+    assert_eq!(pool.vault_x, vault_address(&mint_x, &config).0);
+    assert_eq!(pool.vault_y, vault_address(&mint_y, &config).0);
+    assert_eq!(pool.mint_lp, lp_mint_address(&config).0);
+    assert_eq!(pool.oracle, oracle_address(&config).0);
+}
+
+#[tokio::test]
+async fn initialize_and_decode_config() {
+    let (mut context, pool, _) = setup().await;
+    let config = fetch_config(&mut context, &pool).await;
+
+    assert_eq!(config.seed, SEED);
+    assert_eq!(config.authority, context.payer.pubkey());
+    assert_eq!(config.mint_x, pool.mint_x);
+    assert_eq!(config.mint_y, pool.mint_y);
+    assert_eq!(config.fee, FEE);
+    assert_eq!(config.curve, CurveType::ConstantProduct as u8);
+    assert_eq!(config.weight_x, EVEN_WEIGHT);
+    assert_eq!(
+        Pool::from_config(&config, spl_token::ID, spl_token::ID),
+        pool
+    );
+}
+
+#[tokio::test]
+async fn decoders_accept_unaligned_data() {
+    let (context, pool, _) = setup().await;
+
+    let config = context
+        .banks_client
+        .get_account(pool.config)
+        .await
+        .unwrap()
+        .unwrap();
+    let oracle = context
+        .banks_client
+        .get_account(pool.oracle)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Account data at an odd offset into a larger buffer, as RPC responses
+    // can hand it out
+    let config_buffer = [&[0], config.data.as_slice()].concat();
+    let oracle_buffer = [&[0], oracle.data.as_slice()].concat();
+
+    assert_eq!(
+        bytes_of(&decode_config(&config_buffer[1..]).unwrap()),
+        &config.data[..Config::LEN]
+    );
+    assert_eq!(
+        bytes_of(&decode_oracle(&oracle_buffer[1..]).unwrap()),
+        &oracle.data[..Oracle::LEN]
+    );
+}
+
+#[tokio::test]
+async fn stable_pool_ramps_its_amplification() {
+    let (mut context, pool, _) = setup_curve(Curve::Stable { amp: 100 }).await;
+    let payer = context.payer.pubkey();
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+
+    let config = fetch_config(&mut context, &pool).await;
+    assert_eq!(config.curve, CurveType::Stable as u8);
+    assert_eq!(config.curve_at(now).unwrap(), Curve::Stable { amp: 100 });
+
+    let ramp_end = now + 7 * 86_400;
+    process(
+        &mut context,
+        &[ramp_amp_ix(&pool.config, &payer, 800, ramp_end)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config = fetch_config(&mut context, &pool).await;
+    assert_eq!(config.curve_at(now).unwrap(), Curve::Stable { amp: 100 });
+    assert_eq!(
+        config.curve_at(now + 7 * 86_400 / 2).unwrap(),
+        Curve::Stable { amp: 450 }
+    );
+    assert_eq!(
+        config.curve_at(ramp_end).unwrap(),
+        Curve::Stable { amp: 800 }
+    );
+}
+
+#[tokio::test]
+async fn weighted_pool_decodes_its_weights() {
+    let (mut context, pool, _) = setup_curve(Curve::Weighted { weight_x: 8_000 }).await;
+
+    let config = fetch_config(&mut context, &pool).await;
+    assert_eq!(config.curve, CurveType::ConstantProduct as u8);
+    assert_eq!(config.weight_x, 8_000);
+    assert_eq!(
+        config.curve_at(0).unwrap(),
+        Curve::Weighted { weight_x: 8_000 }
+    );
+}
+
+#[tokio::test]
+async fn swap_both_ways_and_withdraw() {
+    let (mut context, pool, user) = setup().await;
+    let payer = context.payer.pubkey();
+
+    process(
+        &mut context,
+        &[swap_ix(
+            &pool,
+            &payer,
+            &user.x,
+            &user.y,
+            10_000,
+            1,
+            true,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        DEPOSIT + 10_000
+    );
+
+    let vault_x = token_balance(&mut context, &pool.vault_x).await;
+    process(
+        &mut context,
+        &[swap_exact_out_ix(
+            &pool,
+            &payer,
+            &user.x,
+            &user.y,
+            5_000,
+            u64::MAX,
+            false,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        vault_x - 5_000
+    );
+
+    let lp = token_balance(&mut context, &user.lp).await;
+    process(
+        &mut context,
+        &[withdraw_ix(
+            &pool,
+            &payer,
+            &user.x,
+            &user.y,
+            &user.lp,
+            lp,
+            0,
+            0,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(token_balance(&mut context, &user.lp).await, 0);
+}
+
+#[tokio::test]
+async fn route_swap_through_two_pools() {
+    let (mut context, pool, user) = setup().await;
+    let payer = context.payer.pubkey();
+
+    let mint_z = create_mint(&mut context).await;
+    let next = Pool::new(SEED + 1, pool.mint_y, mint_z, spl_token::ID, spl_token::ID);
+    let user_z = create_token_account(&mut context, &mint_z, 10 * DEPOSIT).await;
+
+    process(
+        &mut context,
+        &[initialize_ix(
+            &next,
+            &payer,
+            FEE,
+            &payer,
+            Curve::ConstantProduct,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let user_lp = create_token_account(&mut context, &next.mint_lp, 0).await;
+    process(
+        &mut context,
+        &[deposit_ix(
+            &next,
+            &payer,
+            &user.y,
+            &user_z,
+            &user_lp,
+            0,
+            DEPOSIT,
+            DEPOSIT,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    process(
+        &mut context,
+        &[route_swap_ix(
+            &[pool, next],
+            &payer,
+            &user.x,
+            &user_z,
+            10_000,
+            1,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert!(token_balance(&mut context, &user_z).await > 9 * DEPOSIT);
+    assert_eq!(
+        token_balance(&mut context, &next.vault_y).await,
+        10 * DEPOSIT - token_balance(&mut context, &user_z).await
+    );
+}
+
+#[tokio::test]
+async fn observe_and_decode_oracle() {
+    let (context, pool, _) = setup().await;
+
+    let account = context
+        .banks_client
+        .get_account(pool.oracle)
+        .await
+        .unwrap()
+        .unwrap();
+    let oracle = decode_oracle(&account.data).unwrap();
+    assert_eq!(oracle.config, pool.config);
+    assert_eq!(oracle.len, 1);
+
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += 10;
+    context.set_sysvar(&clock);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[observe_ix(&pool, 5)],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap().unwrap();
+
+    // The seeded pool has sat at 1:1 since its first deposit
+    let return_data = result.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(decode_twap(&return_data.data).unwrap(), (1 << 64, 1 << 64));
+}
+
+#[tokio::test]
+async fn migrate_config_and_init_oracle_for_a_legacy_pool() {
+    let (mut context, pool, _) = setup().await;
+    let payer = context.payer.pubkey();
+
+    // A pool from the first release, whose Borsh-serialized config ended at
+    // the bumps and which had no oracle
+    let account = context
+        .banks_client
+        .get_account(pool.config)
+        .await
+        .unwrap()
+        .unwrap();
+    let current = decode_config(&account.data).unwrap();
+    let mut data = [
+        &current.seed.to_le_bytes()[..],
+        current.authority.as_ref(),
+        current.mint_x.as_ref(),
+        current.mint_y.as_ref(),
+        &current.fee.to_le_bytes(),
+        &[
+            current.locked,
+            current.config_bump,
+            current.lp_bump,
+            current.x_bump,
+            current.y_bump,
+            0,
+        ],
+    ]
+    .concat();
+    data.resize(144, 0);
+    context.set_account(&pool.config, &Account { data, ..account }.into());
+    context.set_account(&pool.oracle, &Account::default().into());
+
+    process(
+        &mut context,
+        &[
+            migrate_config_ix(&pool.config, &payer),
+            init_oracle_ix(&pool, &payer),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(pool.config)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        bytemuck::bytes_of(&decode_config(&account.data).unwrap()),
+        bytemuck::bytes_of(&current),
+    );
+
+    let account = context
+        .banks_client
+        .get_account(pool.oracle)
+        .await
+        .unwrap()
+        .unwrap();
+    let oracle = decode_oracle(&account.data).unwrap();
+    assert_eq!(oracle.config, pool.config);
+    assert_eq!(oracle.len, 1);
+}
+
+#[tokio::test]
+async fn flash_loan_repaid_by_a_token_transfer() {
+    let (mut context, pool, user) = setup().await;
+    let payer = context.payer.pubkey();
+
+    process(
+        &mut context,
+        &[set_flash_fee_ix(&pool.config, &payer, 50)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(fetch_config(&mut context, &pool).await.flash_fee, 50);
+
+    // SPL Token itself can be the receiver, with a transfer back to the vault
+    // as its instruction
+    let amount = 200_000;
+    let owed = amount + amm_math::flash_loan_fee(amount, 50).unwrap();
+    let repay = spl_token::instruction::transfer_checked(
+        &spl_token::ID,
+        &user.x,
+        &pool.mint_x,
+        &pool.vault_x,
+        &payer,
+        &[],
+        owed,
+        6,
+    )
+    .unwrap();
+
+    let vault_before = token_balance(&mut context, &pool.vault_x).await;
+    process(
+        &mut context,
+        &[flash_loan_ix(
+            &pool,
+            true,
+            &user.x,
+            &spl_token::ID,
+            &repay.accounts,
+            &repay.data,
+            amount,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        vault_before + owed - amount
+    );
+}
+
+#[tokio::test]
+async fn admin_instructions() {
+    let (mut context, pool, user) = setup().await;
+    let payer = context.payer.pubkey();
+    let new_authority = Keypair::new();
+
+    process(
+        &mut context,
+        &[
+            set_fee_ix(&pool.config, &payer, 100),
+            set_locked_ix(&pool.config, &payer, true),
+            propose_authority_ix(&pool.config, &payer, &new_authority.pubkey()),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config = fetch_config(&mut context, &pool).await;
+    assert_eq!(config.fee, 100);
+    assert_eq!(config.locked, 1);
+    assert_eq!(config.pending_authority, new_authority.pubkey());
+
+    let result = process(
+        &mut context,
+        &[swap_ix(
+            &pool,
+            &payer,
+            &user.x,
+            &user.y,
+            10_000,
+            1,
+            true,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(AmmError::PoolLocked as u32)
+        ),
+    );
+
+    process(
+        &mut context,
+        &[accept_authority_ix(&pool.config, &new_authority.pubkey())],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+
+    let config = fetch_config(&mut context, &pool).await;
+    assert_eq!(config.authority, new_authority.pubkey());
+    assert_eq!(config.pending_authority, Pubkey::default());
+}
+
+#[tokio::test]
+async fn canonical_pool_from_the_factory() {
+    let mut program_test = ProgramTest::new(
+        "native_amm",
+        ID,
+        processor!(native_amm::process_instruction),
+    );
+    program_test.prefer_bpf(false);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    // A program loaded with `processor!` has no program data, so give it one
+    // naming the payer as its upgrade authority
+    let mut program_data = 3u32.to_le_bytes().to_vec();
+    program_data.extend_from_slice(&0u64.to_le_bytes());
+    program_data.push(1);
+    program_data.extend_from_slice(payer.as_ref());
+    context.set_account(
+        &bpf_loader_upgradeable::get_program_data_address(&ID),
+        &Account {
+            lamports: 1_000_000_000,
+            data: program_data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+
+    process(
+        &mut context,
+        &[
+            initialize_factory_ix(&payer, &payer),
+            set_fee_tier_ix(&payer, FEE, true),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let factory = context
+        .banks_client
+        .get_account(factory_address().0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(decode_factory(&factory.data).unwrap().fee_tiers(), [FEE]);
+
+    // Either order of the mints finds the same pool
+    let mint_a = create_mint(&mut context).await;
+    let mint_b = create_mint(&mut context).await;
+    let pool = Pool::canonical(mint_a, mint_b, FEE, spl_token::ID, spl_token::ID);
+    assert_eq!(
+        Pool::canonical(mint_b, mint_a, FEE, spl_token::ID, spl_token::ID),
+        pool
+    );
+    assert!(pool.mint_x < pool.mint_y);
+    assert_eq!(
+        pool.config,
+        canonical_config_address(&pool.mint_x, &pool.mint_y, FEE).0
+    );
+
+    process(
+        &mut context,
+        &[create_pool_ix(&pool, &payer, FEE, Curve::ConstantProduct)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config = fetch_config(&mut context, &pool).await;
+    assert_eq!(config.factory, 1);
+    assert_eq!(config.fee_tier, FEE);
+    assert_eq!(config.authority, payer);
+    assert_eq!(
+        Pool::from_config(&config, spl_token::ID, spl_token::ID),
+        pool
+    );
+
+    let user_x = create_token_account(&mut context, &pool.mint_x, DEPOSIT).await;
+    let user_y = create_token_account(&mut context, &pool.mint_y, DEPOSIT).await;
+    let user_lp = create_token_account(&mut context, &pool.mint_lp, 0).await;
+    process(
+        &mut context,
+        &[deposit_ix(
+            &pool,
+            &payer,
+            &user_x,
+            &user_y,
+            &user_lp,
+            0,
+            DEPOSIT,
+            DEPOSIT,
+            i64::MAX,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, DEPOSIT);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, DEPOSIT);
+}
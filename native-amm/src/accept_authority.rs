@@ -0,0 +1,38 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::AmmError,
+    state::Config,
+    utils::{check_config_owner, check_signer},
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [pending_authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config_data = config.data.borrow_mut();
+    let config_account = Config::load_mut(&mut config_data)?;
+
+    check_config_owner(config)?;
+
+    check_signer(pending_authority)?;
+
+    if config_account.pending_authority == Pubkey::default() {
+        return Err(AmmError::NoPendingAuthority.into());
+    }
+
+    if pending_authority.key != &config_account.pending_authority {
+        return Err(AmmError::Unauthorized.into());
+    }
+
+    config_account.authority = config_account.pending_authority;
+    config_account.pending_authority = Pubkey::default();
+
+    config_account.emit_updated(config.key, pending_authority.key);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{
+    error::AmmError, factory::Factory, initialize::initialize_pool, instruction::CreatePool,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let create_pool = CreatePool::try_from(data)?;
+    let fee_tier = create_pool.fee_tier;
+    let curve = create_pool.curve;
+    let weight_x = create_pool.weight_x;
+    let amp = create_pool.amp;
+
+    let [pool_accounts @ .., factory] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let [_initializer, mint_x, mint_y, ..] = pool_accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // One order per pair, so a Y/X pool cannot be created beside an X/Y one.
+    // Identical mints are left to `Config::initialize`, which rejects them
+    if mint_x.key > mint_y.key {
+        return Err(AmmError::UnsortedMints.into());
+    }
+
+    let admin = {
+        let factory_data = factory.data.borrow();
+        let factory_account = Factory::load(&factory_data)?;
+
+        factory_account.check(factory)?;
+
+        if !factory_account.fee_tiers().contains(&fee_tier) {
+            return Err(AmmError::InvalidFeeTier.into());
+        }
+
+        factory_account.admin
+    };
+
+    // A pool everyone shares answers to the protocol, not to whoever created it
+    initialize_pool(
+        pool_accounts,
+        0,
+        true,
+        admin,
+        fee_tier,
+        curve,
+        weight_x,
+        amp,
+    )
+}
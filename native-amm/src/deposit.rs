@@ -3,7 +3,14 @@ use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
 };
 
-use crate::{instruction::Deposit, state::Config};
+use crate::{
+    instruction::Deposit,
+    state::Config,
+    utils::{
+        check_mint_lp, check_mint_token_program, check_signer, check_token_program,
+        perform_basic_checks,
+    },
+};
 
 pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let deposit = Deposit::try_from(data)?;
@@ -12,24 +19,33 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let max_y = deposit.max_y;
     let expiration = deposit.expiration;
 
-    let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program, _system_program] =
+    let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program, token_program_x, token_program_y, _system_program] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    assert!(user.is_signer);
+    check_signer(user)?;
+
+    check_token_program(token_program.key)?;
+    check_mint_token_program(token_program_x.key, mint_x)?;
+    check_mint_token_program(token_program_y.key, mint_y)?;
+
+    let config_data = config.data.borrow();
+    let config_account = Config::load(&config_data)?;
 
-    assert_eq!(token_program.key, &spl_token::ID);
+    perform_basic_checks(config_account, expiration, config, vault_x, vault_y)?;
 
-    let config_account = Config::try_from(config.data.borrow().as_ref())?;
+    check_mint_lp(config_account, config, mint_lp)?;
 
     Config::add_liquidity(
         amount,
         max_x,
         max_y,
-        &config_account,
+        config_account,
         token_program.key,
+        token_program_x.key,
+        token_program_y.key,
         user_x,
         user_y,
         user_lp,
@@ -0,0 +1,82 @@
+use amm_math::MathError;
+use num_derive::FromPrimitive;
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+/// Errors returned by the AMM as `ProgramError::Custom(code)`.
+/// Codes are part of the client interface and must never be reordered.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum AmmError {
+    #[error("Fee must be below 10000 basis points")]
+    InvalidFee = 0,
+    #[error("Slippage tolerance exceeded")]
+    SlippageExceeded = 1,
+    #[error("Pool is locked")]
+    PoolLocked = 2,
+    #[error("Transaction expired")]
+    Expired = 3,
+    #[error("Insufficient liquidity in the pool")]
+    InsufficientLiquidity = 4,
+    #[error("Invalid token amount")]
+    InvalidAmount = 5,
+    #[error("Math overflow")]
+    MathOverflow = 6,
+    #[error("Required signature is missing")]
+    MissingSigner = 7,
+    #[error("Signer is not the pool authority")]
+    Unauthorized = 8,
+    #[error("No authority transfer is pending")]
+    NoPendingAuthority = 9,
+    #[error("Invalid token program")]
+    InvalidTokenProgram = 10,
+    #[error("Config account is not owned by the program")]
+    InvalidConfigOwner = 11,
+    #[error("Account does not match the expected program address")]
+    InvalidPda = 12,
+    #[error("Oracle account does not belong to the pool")]
+    InvalidOracle = 13,
+    #[error("Oracle history does not cover the requested window")]
+    InsufficientHistory = 14,
+    #[error("Route does not chain its pools")]
+    InvalidRoute = 15,
+    #[error("Pool curve is unknown or does not support this instruction")]
+    InvalidCurve = 16,
+    #[error("Amplification or its ramp is out of bounds")]
+    InvalidAmp = 17,
+    #[error("Token weight is out of bounds or unsupported by the curve")]
+    InvalidWeight = 18,
+    #[error("Flash loan receiver may not be the AMM itself")]
+    InvalidReceiver = 19,
+    #[error("Flash loan was not repaid with its fee")]
+    FlashLoanNotRepaid = 20,
+    #[error("Pool mints must differ")]
+    IdenticalMints = 21,
+    #[error("Canonical pool mints must be in sorted order")]
+    UnsortedMints = 22,
+    #[error("Fee tier is not offered by the factory")]
+    InvalidFeeTier = 23,
+    #[error("Factory already offers the most fee tiers it can hold")]
+    TooManyFeeTiers = 24,
+    #[error("Mint has a Token-2022 extension pools do not support")]
+    UnsupportedMintExtension = 25,
+}
+
+impl From<AmmError> for ProgramError {
+    fn from(e: AmmError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl From<MathError> for AmmError {
+    fn from(e: MathError) -> Self {
+        match e {
+            MathError::Overflow => AmmError::MathOverflow,
+            MathError::InsufficientLiquidity => AmmError::InsufficientLiquidity,
+            MathError::InvalidFee => AmmError::InvalidFee,
+            MathError::ZeroAmount => AmmError::InvalidAmount,
+            MathError::InvalidAmp => AmmError::InvalidAmp,
+            MathError::NoConvergence => AmmError::MathOverflow,
+            MathError::InvalidWeight => AmmError::InvalidWeight,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::invoke_signed;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction::create_account;
+use solana_program::sysvar::Sysvar;
+
+use crate::error::AmmError;
+use crate::utils::{check_pda_and_get_bump, check_pda_with_bump, check_signer};
+
+/// Most fee tiers the factory offers at once
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// Fee tiers canonical pools are created at, at the PDA `["factory"]`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Factory {
+    /// Manages the fee tiers, and is the authority of every canonical pool
+    pub admin: Pubkey,
+    /// Fees in basis points, of which the first `fee_tier_count` are offered
+    pub fee_tiers: [u16; MAX_FEE_TIERS],
+    pub fee_tier_count: u8,
+    pub bump: u8,
+    pub padding: [u8; 6],
+}
+
+impl Factory {
+    pub const LEN: usize = std::mem::size_of::<Factory>();
+
+    #[inline]
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        bytemuck::try_from_bytes(
+            data.get(..Self::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    #[inline]
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        bytemuck::try_from_bytes_mut(
+            data.get_mut(..Self::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Other accounts this program owns could load as a `Factory` too, so
+    /// the address is checked against the PDA as well as the owner
+    #[inline]
+    pub fn check(&self, factory: &AccountInfo) -> ProgramResult {
+        if factory.owner != &crate::ID {
+            return Err(AmmError::InvalidPda.into());
+        }
+
+        check_pda_with_bump(&[b"factory", &[self.bump]], &crate::ID, factory.key)
+    }
+
+    #[inline]
+    pub fn check_admin(&self, factory: &AccountInfo, admin: &AccountInfo) -> ProgramResult {
+        self.check(factory)?;
+
+        check_signer(admin)?;
+
+        if admin.key != &self.admin {
+            return Err(AmmError::Unauthorized.into());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn fee_tiers(&self) -> &[u16] {
+        &self.fee_tiers[..self.fee_tier_count as usize]
+    }
+
+    pub fn initialize<'a>(
+        admin: Pubkey,
+        payer: &AccountInfo<'a>,
+        factory: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let bump = check_pda_and_get_bump(&[b"factory"], &crate::ID, factory.key)?;
+
+        invoke_signed(
+            &create_account(
+                payer.key,
+                factory.key,
+                Rent::get()?.minimum_balance(Factory::LEN),
+                Factory::LEN as u64,
+                &crate::ID,
+            ),
+            &[payer.clone(), factory.clone()],
+            &[&[b"factory", &[bump]]],
+        )?;
+
+        let mut factory_data = factory.data.borrow_mut();
+        let factory_account = Factory::load_mut(&mut factory_data)?;
+        factory_account.admin = admin;
+        factory_account.bump = bump;
+
+        Ok(())
+    }
+
+    /// Offers `fee` as a tier, which is a no-op if it already is, or stops offering it
+    pub fn set_fee_tier(&mut self, fee: u16, enabled: bool) -> ProgramResult {
+        if fee >= 10_000 {
+            return Err(AmmError::InvalidFee.into());
+        }
+
+        let position = self.fee_tiers().iter().position(|tier| *tier == fee);
+        let count = self.fee_tier_count as usize;
+
+        match (position, enabled) {
+            (Some(_), true) => {}
+            (None, true) if count == MAX_FEE_TIERS => return Err(AmmError::TooManyFeeTiers.into()),
+            (None, true) => {
+                self.fee_tiers[count] = fee;
+                self.fee_tier_count += 1;
+            }
+            (Some(index), false) => {
+                // Order does not matter, so the last tier fills the gap
+                self.fee_tiers[index] = self.fee_tiers[count - 1];
+                self.fee_tiers[count - 1] = 0;
+                self.fee_tier_count -= 1;
+            }
+            (None, false) => return Err(AmmError::InvalidFeeTier.into()),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use amm_math::flash_loan_fee;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    program_error::ProgramError,
+};
+
+use crate::{
+    error::AmmError,
+    instruction::FlashLoan,
+    state::Config,
+    utils::{
+        check_config_owner, check_mint_token_program, check_pda_with_bump, unpack_mint,
+        unpack_token_account, withdraw,
+    },
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let flash_loan = FlashLoan::try_from(
+        data.get(..FlashLoan::LEN)
+            .ok_or(ProgramError::InvalidInstructionData)?,
+    )?;
+    let amount = flash_loan.amount;
+    let receiver_data = &data[FlashLoan::LEN..];
+
+    let [config, mint, vault, destination, token_program, receiver_program, receiver_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if amount == 0 {
+        return Err(AmmError::InvalidAmount.into());
+    }
+
+    // The runtime only lets a program re-enter itself directly, so refusing the
+    // AMM as the receiver keeps anything from trading against the lent reserves
+    if receiver_program.key == &crate::ID {
+        return Err(AmmError::InvalidReceiver.into());
+    }
+
+    check_config_owner(config)?;
+    check_mint_token_program(token_program.key, mint)?;
+
+    let (config_account, fee) = {
+        let mut config_data = config.data.borrow_mut();
+        let config_account = Config::load_mut(&mut config_data)?;
+
+        if config_account.locked == 1 {
+            return Err(AmmError::PoolLocked.into());
+        }
+
+        let vault_bump = if mint.key == &config_account.mint_x {
+            config_account.x_bump
+        } else if mint.key == &config_account.mint_y {
+            config_account.y_bump
+        } else {
+            return Err(AmmError::InvalidPda.into());
+        };
+
+        check_pda_with_bump(
+            &[mint.key.as_ref(), config.key.as_ref(), &[vault_bump]],
+            &crate::ID,
+            vault.key,
+        )?;
+
+        // Held until the loan is repaid, as a guard against reentry
+        config_account.locked = 1;
+
+        (
+            *config_account,
+            flash_loan_fee(amount, config_account.flash_fee).map_err(AmmError::from)?,
+        )
+    };
+
+    let owed = unpack_token_account(vault)?
+        .amount
+        .checked_add(fee)
+        .ok_or(AmmError::MathOverflow)?;
+
+    withdraw(
+        token_program.key,
+        vault,
+        mint,
+        destination,
+        config,
+        amount,
+        unpack_mint(mint)?.decimals,
+        &config_account.signer_seeds(),
+    )?;
+
+    let mut account_infos = receiver_accounts.to_vec();
+    account_infos.push(receiver_program.clone());
+
+    invoke(
+        &Instruction {
+            program_id: *receiver_program.key,
+            accounts: receiver_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: *account.key,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: receiver_data.to_vec(),
+        },
+        &account_infos,
+    )?;
+
+    // Any transfer fee on the way back is the borrower's to cover
+    if unpack_token_account(vault)?.amount < owed {
+        return Err(AmmError::FlashLoanNotRepaid.into());
+    }
+
+    Config::load_mut(&mut config.data.borrow_mut())?.locked = 0;
+
+    Ok(())
+}
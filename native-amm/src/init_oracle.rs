@@ -0,0 +1,25 @@
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+use crate::{
+    oracle::Oracle,
+    state::Config,
+    utils::{check_config_owner, check_signer},
+};
+
+/// Creates the oracle of a pool initialized before pools had one, which swaps
+/// require. Anyone can pay for it, and its history starts now
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [payer, config, oracle, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(payer)?;
+    check_config_owner(config)?;
+
+    Config::load(&config.data.borrow())?;
+
+    Oracle::initialize(Clock::get()?.unix_timestamp, payer, oracle, config)
+}
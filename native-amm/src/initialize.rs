@@ -1,13 +1,19 @@
 #![allow(unused_variables)]
 
+use amm_events::PoolInitialized;
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, pubkey::Pubkey, sysvar::Sysvar,
 };
 
 use crate::{
     instruction::Initialize,
+    oracle::Oracle,
     state::Config,
-    utils::{check_pda_and_get_bump, create_mint, create_token_account},
+    utils::{
+        check_mint_extensions, check_mint_token_program, check_pda_and_get_bump,
+        check_token_program, create_mint, create_token_account, emit,
+    },
 };
 
 pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
@@ -15,9 +21,27 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let seed = initialize.seed;
     let fee = initialize.fee;
     let authority = initialize.authority;
+    let curve = initialize.curve;
     let padding = initialize.padding;
+    let weight_x = initialize.weight_x;
+    let amp = initialize.amp;
 
-    let [initializer, mint_x, mint_y, mint_lp, vault_x, vault_y, config, token_program, _system_program] =
+    initialize_pool(accounts, seed, false, authority, fee, curve, weight_x, amp)
+}
+
+/// Creates a pool's config, oracle, vaults and LP mint from `Initialize`'s
+/// accounts, for a pool with its own `seed` or a canonical one from the factory
+pub(crate) fn initialize_pool(
+    accounts: &[AccountInfo<'_>],
+    seed: u64,
+    factory: bool,
+    authority: Pubkey,
+    fee: u16,
+    curve: u8,
+    weight_x: u16,
+    amp: u64,
+) -> ProgramResult {
+    let [initializer, mint_x, mint_y, mint_lp, vault_x, vault_y, config, oracle, token_program, token_program_x, token_program_y, _system_program] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -39,8 +63,12 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
 
     Config::initialize(
         seed,
+        factory,
         authority,
         fee,
+        curve,
+        weight_x,
+        amp,
         lp_bump,
         x_bump,
         y_bump,
@@ -50,12 +78,18 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
         config,
     )?;
 
-    assert_eq!(spl_token::ID, *token_program.key);
+    Oracle::initialize(Clock::get()?.unix_timestamp, initializer, oracle, config)?;
+
+    check_token_program(token_program.key)?;
+    check_mint_token_program(token_program_x.key, mint_x)?;
+    check_mint_token_program(token_program_y.key, mint_y)?;
+    check_mint_extensions(mint_x)?;
+    check_mint_extensions(mint_y)?;
 
     // Create the x_vault
     create_token_account(
         &[mint_x.key.as_ref(), config.key.as_ref(), &[x_bump]],
-        token_program.key,
+        token_program_x.key,
         initializer,
         vault_x,
         mint_x,
@@ -65,7 +99,7 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     // Create the y_vault
     create_token_account(
         &[mint_y.key.as_ref(), config.key.as_ref(), &[y_bump]],
-        token_program.key,
+        token_program_y.key,
         initializer,
         vault_y,
         mint_y,
@@ -79,5 +113,19 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
         initializer,
         mint_lp,
         config,
-    )
+    )?;
+
+    emit(&PoolInitialized {
+        pool: *config.key,
+        user: *initializer.key,
+        authority,
+        mint_x: *mint_x.key,
+        mint_y: *mint_y.key,
+        fee,
+        curve,
+        weight_x,
+        amp,
+    });
+
+    Ok(())
 }
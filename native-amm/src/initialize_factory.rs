@@ -0,0 +1,19 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{factory::Factory, instruction::InitializeFactory, utils::check_upgrade_authority};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let initialize_factory = InitializeFactory::try_from(data)?;
+    let admin = initialize_factory.admin;
+
+    let [upgrade_authority, factory, program_data, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // There is only one factory, so only whoever deployed the program creates it
+    check_upgrade_authority(program_data, upgrade_authority)?;
+
+    Factory::initialize(admin, upgrade_authority, factory)
+}
@@ -1,11 +1,28 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use amm_macros::TryFromBytes;
+use bytemuck::{Pod, Zeroable};
 use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
 #[derive(Clone, Copy)]
 pub enum AmmInstructions {
-    Initialize,
-    Deposit,
-    Swap,
+    Initialize = 0,
+    Deposit = 1,
+    Withdraw = 2,
+    Swap = 3,
+    SwapExactOut = 4,
+    SetFee = 5,
+    SetLocked = 6,
+    ProposeAuthority = 7,
+    AcceptAuthority = 8,
+    Observe = 9,
+    RouteSwap = 10,
+    RampAmp = 11,
+    FlashLoan = 12,
+    SetFlashFee = 13,
+    InitializeFactory = 14,
+    SetFeeTier = 15,
+    CreatePool = 16,
+    InitOracle = 17,
+    MigrateConfig = 18,
 }
 
 impl TryFrom<&u8> for AmmInstructions {
@@ -15,27 +32,45 @@ impl TryFrom<&u8> for AmmInstructions {
         match value {
             0 => Ok(Self::Initialize),
             1 => Ok(Self::Deposit),
+            2 => Ok(Self::Withdraw),
             3 => Ok(Self::Swap),
+            4 => Ok(Self::SwapExactOut),
+            5 => Ok(Self::SetFee),
+            6 => Ok(Self::SetLocked),
+            7 => Ok(Self::ProposeAuthority),
+            8 => Ok(Self::AcceptAuthority),
+            9 => Ok(Self::Observe),
+            10 => Ok(Self::RouteSwap),
+            11 => Ok(Self::RampAmp),
+            12 => Ok(Self::FlashLoan),
+            13 => Ok(Self::SetFlashFee),
+            14 => Ok(Self::InitializeFactory),
+            15 => Ok(Self::SetFeeTier),
+            16 => Ok(Self::CreatePool),
+            17 => Ok(Self::InitOracle),
+            18 => Ok(Self::MigrateConfig),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
 }
 
-#[derive(Clone, BorshSerialize, BorshDeserialize)]
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
 pub struct Initialize {
     pub seed: u64,
     pub fee: u16,
     pub authority: Pubkey,
-    pub padding: [u8; 6],
+    /// A `CurveType`
+    pub curve: u8,
+    pub padding: [u8; 3],
+    /// Weight of x in basis points, `EVEN_WEIGHT` unless constant product
+    pub weight_x: u16,
+    /// StableSwap amplification, zero for constant product
+    pub amp: u64,
 }
 
-impl Initialize {
-    pub fn try_from(data: &[u8]) -> Result<Self, ProgramError> {
-        Self::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)
-    }
-}
-
-#[derive(Clone, BorshSerialize, BorshDeserialize)]
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
 pub struct Deposit {
     pub amount: u64,
     pub max_x: u64,
@@ -43,21 +78,131 @@ pub struct Deposit {
     pub expiration: i64,
 }
 
-impl Deposit {
-    pub fn try_from(data: &[u8]) -> Result<Self, ProgramError> {
-        Self::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)
-    }
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct Withdraw {
+    pub amount: u64,
+    pub min_x: u64,
+    pub min_y: u64,
+    pub expiration: i64,
 }
 
-#[derive(Clone, BorshSerialize, BorshDeserialize)]
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
 pub struct Swap {
     pub amount: u64,
     pub min: u64,
     pub expiration: i64,
+    pub from_x: u8,
+    pub padding: [u8; 7],
 }
 
-impl Swap {
-    pub fn try_from(data: &[u8]) -> Result<Self, ProgramError> {
-        Self::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)
-    }
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct SwapExactOut {
+    pub amount_out: u64,
+    pub max_in: u64,
+    pub expiration: i64,
+    pub from_x: u8,
+    pub padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct SetFee {
+    pub fee: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct SetLocked {
+    pub locked: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct ProposeAuthority {
+    pub authority: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct Observe {
+    /// Seconds to average over, ending now
+    pub window: u32,
+}
+
+/// Sells exactly `amount` through every pool in the route, in order. The
+/// accounts after `[user, user_in, user_out]` are `HOP_ACCOUNTS` per pool:
+/// `[config, oracle, mint_x, mint_y, mint_lp, vault_x, vault_y, token_program_x, token_program_y]`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct RouteSwap {
+    pub amount: u64,
+    pub min: u64,
+    pub expiration: i64,
+}
+
+impl RouteSwap {
+    pub const HOP_ACCOUNTS: usize = 9;
+}
+
+/// Moves a StableSwap pool's amplification linearly from its current value
+/// now to `target_amp` at `ramp_end`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct RampAmp {
+    pub target_amp: u64,
+    pub ramp_end: i64,
+}
+
+/// Lends `amount` of one of the pool's tokens for the rest of the instruction.
+/// The bytes after it are the instruction data for the receiver program
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct FlashLoan {
+    pub amount: u64,
+}
+
+impl FlashLoan {
+    pub const LEN: usize = std::mem::size_of::<FlashLoan>();
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct SetFlashFee {
+    pub flash_fee: u16,
+}
+
+/// Creates the factory, signed by the program's upgrade authority
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct InitializeFactory {
+    pub admin: Pubkey,
+}
+
+/// Offers `fee` as a fee tier, or stops offering it when `enabled` is zero.
+/// Pools already created at a tier keep trading after it is withdrawn
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct SetFeeTier {
+    pub fee: u16,
+    pub enabled: u8,
+    pub padding: [u8; 1],
+}
+
+/// Creates the canonical pool for a mint pair at one of the factory's fee
+/// tiers, with the factory admin as its authority. Takes `Initialize`'s
+/// accounts followed by the factory
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct CreatePool {
+    pub fee_tier: u16,
+    /// Weight of x in basis points, `EVEN_WEIGHT` unless constant product
+    pub weight_x: u16,
+    /// A `CurveType`
+    pub curve: u8,
+    pub padding: [u8; 3],
+    /// StableSwap amplification, zero for constant product
+    pub amp: u64,
 }
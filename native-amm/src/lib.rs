@@ -1,19 +1,43 @@
+#![allow(clippy::too_many_arguments)]
+
+use error::AmmError;
 use instruction::AmmInstructions;
+use num_traits::FromPrimitive;
 use solana_program::program_error::ProgramError;
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
-use solana_program::{entrypoint, pubkey};
+use solana_program::{msg, pubkey};
 
+mod accept_authority;
+mod create_pool;
 mod deposit;
+pub mod error;
+pub mod factory;
+mod flash_loan;
+mod init_oracle;
 mod initialize;
-mod instruction;
-mod state;
+mod initialize_factory;
+pub mod instruction;
+mod migrate_config;
+mod observe;
+pub mod oracle;
+mod propose_authority;
+mod ramp_amp;
+mod route_swap;
+mod set_fee;
+mod set_fee_tier;
+mod set_flash_fee;
+mod set_locked;
+pub mod state;
 mod swap;
+mod swap_exact_out;
 mod utils;
+mod withdraw;
 
 /// Devnet
-const ID: Pubkey = pubkey!("3wb7xEi8i3PKone8UvF1LEwrxcVnj3k9BCLMre8nMcwV");
+pub const ID: Pubkey = pubkey!("3wb7xEi8i3PKone8UvF1LEwrxcVnj3k9BCLMre8nMcwV");
 
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -28,9 +52,33 @@ pub fn process_instruction(
         .split_first()
         .ok_or(ProgramError::InvalidInstructionData)?;
 
-    match AmmInstructions::try_from(tag)? {
+    let result = match AmmInstructions::try_from(tag)? {
         AmmInstructions::Initialize => initialize::process(accounts, rest),
         AmmInstructions::Deposit => deposit::process(accounts, rest),
+        AmmInstructions::Withdraw => withdraw::process(accounts, rest),
         AmmInstructions::Swap => swap::process(accounts, rest),
-    }
+        AmmInstructions::SwapExactOut => swap_exact_out::process(accounts, rest),
+        AmmInstructions::SetFee => set_fee::process(accounts, rest),
+        AmmInstructions::SetLocked => set_locked::process(accounts, rest),
+        AmmInstructions::ProposeAuthority => propose_authority::process(accounts, rest),
+        AmmInstructions::AcceptAuthority => accept_authority::process(accounts, rest),
+        AmmInstructions::Observe => observe::process(accounts, rest),
+        AmmInstructions::RouteSwap => route_swap::process(accounts, rest),
+        AmmInstructions::RampAmp => ramp_amp::process(accounts, rest),
+        AmmInstructions::FlashLoan => flash_loan::process(accounts, rest),
+        AmmInstructions::SetFlashFee => set_flash_fee::process(accounts, rest),
+        AmmInstructions::InitializeFactory => initialize_factory::process(accounts, rest),
+        AmmInstructions::SetFeeTier => set_fee_tier::process(accounts, rest),
+        AmmInstructions::CreatePool => create_pool::process(accounts, rest),
+        AmmInstructions::InitOracle => init_oracle::process(accounts, rest),
+        AmmInstructions::MigrateConfig => migrate_config::process(accounts, rest),
+    };
+
+    result.inspect_err(|error| match error {
+        ProgramError::Custom(code) => match AmmError::from_u32(*code) {
+            Some(error) => msg!("Error: {}", error),
+            None => msg!("Error: Unknown"),
+        },
+        error => msg!("Error: {}", error),
+    })
 }
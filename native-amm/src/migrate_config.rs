@@ -0,0 +1,107 @@
+use amm_math::weighted;
+use bytemuck::{Pod, Zeroable};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction::transfer,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    state::{Config, CurveType},
+    utils::{check_config_owner, check_pda_with_bump, check_signer},
+};
+
+/// The config layout the first pools were initialized with, which was
+/// serialized with Borsh and so has no padding of its own
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LegacyConfig {
+    seed: u64,
+    authority: Pubkey,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    fee: u16,
+    locked: u8,
+    config_bump: u8,
+    lp_bump: u8,
+    x_bump: u8,
+    y_bump: u8,
+    padding: [u8; 1],
+}
+
+/// Size of a legacy config account, which was allocated 32 bytes more than it used
+const LEGACY_CONFIG_SPACE: usize = std::mem::size_of::<LegacyConfig>() + 32;
+
+/// Rewrites the config of a pool from before the layout gained a pending
+/// authority, curves, weights and factory pools, which every other instruction
+/// requires. The pool keeps its authority, fee and lock and trades as an evenly
+/// weighted constant-product pool. Anyone can pay for the larger account
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [payer, config, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(payer)?;
+    check_config_owner(config)?;
+
+    let legacy: LegacyConfig = {
+        let data = config.try_borrow_data()?;
+        if data.len() != LEGACY_CONFIG_SPACE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        bytemuck::pod_read_unaligned(&data[..std::mem::size_of::<LegacyConfig>()])
+    };
+
+    // Legacy pools could only be created at their seed's address
+    check_pda_with_bump(
+        &[
+            b"config",
+            legacy.seed.to_le_bytes().as_ref(),
+            &[legacy.config_bump],
+        ],
+        &crate::ID,
+        config.key,
+    )?;
+
+    let lamports = Rent::get()?
+        .minimum_balance(Config::LEN)
+        .saturating_sub(config.lamports());
+    if lamports > 0 {
+        invoke(
+            &transfer(payer.key, config.key, lamports),
+            &[payer.clone(), config.clone()],
+        )?;
+    }
+
+    // Crates built against a newer `solana-account-info` see `realloc` as
+    // deprecated in favour of `resize`, which this one does not have yet
+    #[allow(deprecated)]
+    config.realloc(Config::LEN, true)?;
+
+    *Config::load_mut(&mut config.data.borrow_mut())? = Config {
+        seed: legacy.seed,
+        authority: legacy.authority,
+        pending_authority: Pubkey::default(),
+        mint_x: legacy.mint_x,
+        mint_y: legacy.mint_y,
+        fee: legacy.fee,
+        locked: legacy.locked,
+        config_bump: legacy.config_bump,
+        lp_bump: legacy.lp_bump,
+        x_bump: legacy.x_bump,
+        y_bump: legacy.y_bump,
+        curve: CurveType::ConstantProduct as u8,
+        initial_amp: 0,
+        target_amp: 0,
+        ramp_start: 0,
+        ramp_end: 0,
+        weight_x: weighted::EVEN_WEIGHT,
+        flash_fee: 0,
+        fee_tier: 0,
+        factory: 0,
+        padding: [0; 1],
+    };
+
+    Ok(())
+}
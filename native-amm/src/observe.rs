@@ -0,0 +1,48 @@
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program::set_return_data,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+use crate::{
+    instruction::Observe,
+    oracle::Oracle,
+    state::Config,
+    utils::{check_config_owner, check_vaults, unpack_token_account},
+};
+
+/// Returns the Q64.64 TWAPs of x in y and of y in x, as two little-endian
+/// `u128`s in the return data
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let observe = Observe::try_from(data)?;
+    let window = observe.window;
+
+    let [config, oracle, vault_x, vault_y] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_config_owner(config)?;
+
+    let config_data = config.data.borrow();
+    let config_account = Config::load(&config_data)?;
+
+    check_vaults(config_account, config, vault_x, vault_y)?;
+
+    let oracle_data = oracle.data.borrow();
+    let oracle_account = Oracle::load(&oracle_data)?;
+
+    oracle_account.check(oracle, config)?;
+
+    let (price_x, price_y) = oracle_account
+        .twap(
+            Clock::get()?.unix_timestamp,
+            window,
+            &config_account.curve()?,
+            unpack_token_account(vault_x)?.amount,
+            unpack_token_account(vault_y)?.amount,
+        )
+        .map_err(ProgramError::from)?;
+
+    set_return_data(&[price_x.to_le_bytes(), price_y.to_le_bytes()].concat());
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use amm_math::{oracle, Curve};
+use bytemuck::{Pod, Zeroable};
+use solana_program::account_info::AccountInfo;
+use solana_program::clock::Clock;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::invoke_signed;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction::create_account;
+use solana_program::sysvar::Sysvar;
+
+use crate::error::AmmError;
+use crate::utils::check_pda_and_get_bump;
+
+/// Slots in a pool's ring buffer
+pub const OBSERVATIONS: usize = 64;
+
+/// Fewest seconds between a slot and the one before it. Until the newest slot
+/// is this far past its predecessor, swaps advance it in place, so however
+/// often the pool trades the buffer reaches back over five hours
+pub const OBSERVATION_INTERVAL: i64 = 300;
+
+/// Cumulative prices at a point in time, see [`amm_math::oracle`].
+/// The sums are little-endian `u128`s, stored as bytes to keep the account 8-byte aligned
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_x_cumulative: [u8; 16],
+    pub price_y_cumulative: [u8; 16],
+}
+
+impl From<Observation> for oracle::Observation {
+    fn from(observation: Observation) -> Self {
+        Self {
+            timestamp: observation.timestamp,
+            price_x_cumulative: u128::from_le_bytes(observation.price_x_cumulative),
+            price_y_cumulative: u128::from_le_bytes(observation.price_y_cumulative),
+        }
+    }
+}
+
+impl From<oracle::Observation> for Observation {
+    fn from(observation: oracle::Observation) -> Self {
+        Self {
+            timestamp: observation.timestamp,
+            price_x_cumulative: observation.price_x_cumulative.to_le_bytes(),
+            price_y_cumulative: observation.price_y_cumulative.to_le_bytes(),
+        }
+    }
+}
+
+/// Price history of one pool, at the PDA `["oracle", config]`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Oracle {
+    pub config: Pubkey,
+    /// Slot of the newest observation
+    pub index: u16,
+    /// Slots written so far, up to `OBSERVATIONS`
+    pub len: u16,
+    pub bump: u8,
+    pub padding: [u8; 3],
+    pub observations: [Observation; OBSERVATIONS],
+}
+
+impl Oracle {
+    pub const LEN: usize = std::mem::size_of::<Oracle>();
+
+    #[inline]
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        bytemuck::try_from_bytes(
+            data.get(..Self::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    #[inline]
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        bytemuck::try_from_bytes_mut(
+            data.get_mut(..Self::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Only this program can create accounts it owns, so owner and stored config
+    /// are enough to tie an oracle to its pool without re-deriving the PDA
+    #[inline]
+    pub fn check(&self, oracle: &AccountInfo, config: &AccountInfo) -> ProgramResult {
+        if oracle.owner != &crate::ID || &self.config != config.key {
+            return Err(AmmError::InvalidOracle.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn initialize<'a>(
+        timestamp: i64,
+        initializer: &AccountInfo<'a>,
+        oracle: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let bump =
+            check_pda_and_get_bump(&[b"oracle", config.key.as_ref()], &crate::ID, oracle.key)?;
+
+        invoke_signed(
+            &create_account(
+                initializer.key,
+                oracle.key,
+                Rent::get()?.minimum_balance(Oracle::LEN),
+                Oracle::LEN as u64,
+                &crate::ID,
+            ),
+            &[initializer.clone(), oracle.clone()],
+            &[&[b"oracle", config.key.as_ref(), &[bump]]],
+        )?;
+
+        let mut oracle_data = oracle.data.borrow_mut();
+        let oracle_account = Oracle::load_mut(&mut oracle_data)?;
+        oracle_account.config = *config.key;
+        oracle_account.len = 1;
+        oracle_account.bump = bump;
+        oracle_account.observations[0].timestamp = timestamp;
+
+        Ok(())
+    }
+
+    fn newest(&self) -> oracle::Observation {
+        self.observations[self.index as usize].into()
+    }
+
+    /// The observation before the newest, only meaningful once `len` is above one
+    fn previous(&self) -> oracle::Observation {
+        self.observations[(self.index as usize + OBSERVATIONS - 1) % OBSERVATIONS].into()
+    }
+
+    /// Whether the newest slot is `OBSERVATION_INTERVAL` past its predecessor,
+    /// and the next observation takes a slot of its own
+    fn newest_is_closed(&self, newest: &oracle::Observation) -> bool {
+        self.len == 1 || newest.timestamp - self.previous().timestamp >= OBSERVATION_INTERVAL
+    }
+
+    /// Records the prices in effect until now, before a swap moves the reserves
+    pub fn update(
+        oracle: &AccountInfo,
+        config: &AccountInfo,
+        curve: &Curve,
+        reserve_x: u64,
+        reserve_y: u64,
+    ) -> ProgramResult {
+        let mut oracle_data = oracle.try_borrow_mut_data()?;
+        let oracle_account = Oracle::load_mut(&mut oracle_data)?;
+
+        oracle_account.check(oracle, config)?;
+        let (price_x, price_y) = curve.spot_prices(reserve_x, reserve_y);
+        oracle_account.record(Clock::get()?.unix_timestamp, price_x, price_y);
+
+        Ok(())
+    }
+
+    /// Later swaps in the same second leave the buffer untouched
+    fn record(&mut self, timestamp: i64, price_x: u128, price_y: u128) {
+        let newest = self.newest();
+        if timestamp <= newest.timestamp {
+            return;
+        }
+
+        if self.newest_is_closed(&newest) {
+            self.index = ((self.index as usize + 1) % OBSERVATIONS) as u16;
+            self.len = (self.len + 1).min(OBSERVATIONS as u16);
+        }
+        self.observations[self.index as usize] = newest.advance(timestamp, price_x, price_y).into();
+    }
+
+    /// Q64.64 average prices of x in y and of y in x over the `window` seconds
+    /// up to `timestamp`, given the pool's curve and current reserves
+    pub fn twap(
+        &self,
+        timestamp: i64,
+        window: u32,
+        curve: &Curve,
+        reserve_x: u64,
+        reserve_y: u64,
+    ) -> Result<(u128, u128), AmmError> {
+        let (price_x, price_y) = curve.spot_prices(reserve_x, reserve_y);
+        let current = self.newest().advance(timestamp, price_x, price_y);
+
+        let start = oracle::observe(
+            OBSERVATIONS,
+            self.len as usize,
+            self.index as usize,
+            |slot| self.observations[slot].into(),
+            &current,
+            timestamp - window as i64,
+        )
+        .ok_or(AmmError::InsufficientHistory)?;
+
+        Ok(oracle::twap(&start, &current)?)
+    }
+}
@@ -0,0 +1,27 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{instruction::ProposeAuthority, state::Config};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let propose_authority = ProposeAuthority::try_from(data)?;
+    let new_authority = propose_authority.authority;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config_data = config.data.borrow_mut();
+    let config_account = Config::load_mut(&mut config_data)?;
+
+    config_account.check_authority(config, authority)?;
+
+    // The handoff only completes once the new authority signs AcceptAuthority.
+    // Proposing the default pubkey cancels a pending handoff
+    config_account.pending_authority = new_authority;
+
+    config_account.emit_updated(config.key, authority.key);
+
+    Ok(())
+}
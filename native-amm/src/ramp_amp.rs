@@ -0,0 +1,43 @@
+use amm_math::stable;
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+use crate::{
+    error::AmmError,
+    instruction::RampAmp,
+    state::{Config, Curve},
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let ramp_amp = RampAmp::try_from(data)?;
+    let target_amp = ramp_amp.target_amp;
+    let ramp_end = ramp_amp.ramp_end;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config_data = config.data.borrow_mut();
+    let config_account = Config::load_mut(&mut config_data)?;
+
+    config_account.check_authority(config, authority)?;
+
+    let Curve::Stable { amp } = config_account.curve()? else {
+        return Err(AmmError::InvalidCurve.into());
+    };
+
+    // A new ramp starts wherever the current one has got to
+    let now = Clock::get()?.unix_timestamp;
+    stable::check_ramp(amp, target_amp, now, ramp_end).map_err(AmmError::from)?;
+
+    config_account.initial_amp = amp;
+    config_account.target_amp = target_amp;
+    config_account.ramp_start = now;
+    config_account.ramp_end = ramp_end;
+
+    config_account.emit_updated(config.key, authority.key);
+
+    Ok(())
+}
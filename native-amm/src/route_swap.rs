@@ -0,0 +1,197 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::AmmError,
+    instruction::RouteSwap,
+    state::Config,
+    utils::{
+        amount_after_transfer_fee, check_mint_lp, check_mint_token_program, check_signer, deposit,
+        emit, perform_basic_checks, unpack_mint, unpack_token_account, withdraw,
+    },
+};
+
+/// Output of one hop, still in its pool's vault until the next hop or the user takes it
+struct Output<'a, 'b> {
+    amount: u64,
+    vault: &'b AccountInfo<'a>,
+    mint: &'b AccountInfo<'a>,
+    token_program: &'b Pubkey,
+    config: &'b AccountInfo<'a>,
+    /// A copy, as the config's data is not borrowed while tokens move
+    config_account: Config,
+    vault_x: &'b AccountInfo<'a>,
+    vault_y: &'b AccountInfo<'a>,
+    /// The hop, logged once its output has left and its reserves are final
+    event: amm_events::Swap,
+}
+
+impl<'a> Output<'a, '_> {
+    fn send_to(mut self, destination: &AccountInfo<'a>) -> ProgramResult {
+        withdraw(
+            self.token_program,
+            self.vault,
+            self.mint,
+            destination,
+            self.config,
+            self.amount,
+            unpack_mint(self.mint)?.decimals,
+            &self.config_account.signer_seeds(),
+        )?;
+
+        self.event.reserve_x = unpack_token_account(self.vault_x)?.amount;
+        self.event.reserve_y = unpack_token_account(self.vault_y)?.amount;
+        emit(&self.event);
+
+        Ok(())
+    }
+}
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let route_swap = RouteSwap::try_from(data)?;
+    let amount = route_swap.amount;
+    let min = route_swap.min;
+    let expiration = route_swap.expiration;
+
+    let [user, user_in, user_out, hops @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(user)?;
+
+    let hops = hops.chunks_exact(RouteSwap::HOP_ACCOUNTS);
+    if hops.len() == 0 || !hops.remainder().is_empty() {
+        return Err(AmmError::InvalidRoute.into());
+    }
+
+    // Each hop reads its reserves before the previous hop's output arrives, so a
+    // pool visited twice would be priced on stale reserves
+    for (i, hop) in hops.clone().enumerate() {
+        if hops
+            .clone()
+            .skip(i + 1)
+            .any(|other| other[0].key == hop[0].key)
+        {
+            return Err(AmmError::InvalidRoute.into());
+        }
+    }
+
+    let mut mint_in_key = unpack_token_account(user_in)?.mint;
+    let mut amount_in = amount;
+    let mut previous: Option<Output> = None;
+
+    for hop in hops {
+        let [config, oracle, mint_x, mint_y, mint_lp, vault_x, vault_y, token_program_x, token_program_y] =
+            hop
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_mint_token_program(token_program_x.key, mint_x)?;
+        check_mint_token_program(token_program_y.key, mint_y)?;
+
+        let config_data = config.data.borrow();
+        let config_account = Config::load(&config_data)?;
+
+        perform_basic_checks(config_account, expiration, config, vault_x, vault_y)?;
+        check_mint_lp(config_account, config, mint_lp)?;
+
+        if mint_x.key != &config_account.mint_x || mint_y.key != &config_account.mint_y {
+            return Err(AmmError::InvalidRoute.into());
+        }
+
+        // The hop sells whatever the one before it bought
+        let from_x = if mint_in_key == config_account.mint_x {
+            true
+        } else if mint_in_key == config_account.mint_y {
+            false
+        } else {
+            return Err(AmmError::InvalidRoute.into());
+        };
+
+        let (mint_in, mint_out, vault_in, vault_out, token_program_in, token_program_out) =
+            if from_x {
+                (
+                    mint_x,
+                    mint_y,
+                    vault_x,
+                    vault_y,
+                    token_program_x,
+                    token_program_y,
+                )
+            } else {
+                (
+                    mint_y,
+                    mint_x,
+                    vault_y,
+                    vault_x,
+                    token_program_y,
+                    token_program_x,
+                )
+            };
+
+        let amount_out = Config::quote_swap(
+            config_account,
+            amount_in,
+            from_x,
+            mint_x,
+            mint_y,
+            vault_x,
+            vault_y,
+            config,
+            oracle,
+        )?;
+
+        // Intermediate tokens move vault to vault and never pass through the user
+        match previous {
+            None => deposit(
+                token_program_in.key,
+                user_in,
+                mint_in,
+                vault_in,
+                user,
+                amount_in,
+                unpack_mint(mint_in)?.decimals,
+            )?,
+            Some(output) => output.send_to(vault_in)?,
+        }
+
+        let amount_received = amount_after_transfer_fee(mint_in, amount_in)?;
+
+        previous = Some(Output {
+            amount: amount_out,
+            vault: vault_out,
+            mint: mint_out,
+            token_program: token_program_out.key,
+            config,
+            config_account: *config_account,
+            vault_x,
+            vault_y,
+            event: amm_events::Swap {
+                pool: *config.key,
+                user: *user.key,
+                from_x,
+                amount_in: amount_received,
+                amount_out,
+                fee_paid: amm_math::swap_fee(amount_received, config_account.fee)
+                    .map_err(AmmError::from)?,
+                reserve_x: 0,
+                reserve_y: 0,
+                lp_supply: unpack_mint(mint_lp)?.supply,
+            },
+        });
+        mint_in_key = *mint_out.key;
+        amount_in = amount_out;
+    }
+
+    let output = previous.ok_or(AmmError::InvalidRoute)?;
+
+    // Only the final output is protected, as only it reaches the user
+    if amount_after_transfer_fee(output.mint, output.amount)? < min {
+        return Err(AmmError::SlippageExceeded.into());
+    }
+
+    output.send_to(user_out)
+}
@@ -0,0 +1,29 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{error::AmmError, instruction::SetFee, state::Config};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let set_fee = SetFee::try_from(data)?;
+    let fee = set_fee.fee;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config_data = config.data.borrow_mut();
+    let config_account = Config::load_mut(&mut config_data)?;
+
+    config_account.check_authority(config, authority)?;
+
+    if fee >= 10_000 {
+        return Err(AmmError::InvalidFee.into());
+    }
+
+    config_account.fee = fee;
+
+    config_account.emit_updated(config.key, authority.key);
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{factory::Factory, instruction::SetFeeTier, utils::emit};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let set_fee_tier = SetFeeTier::try_from(data)?;
+    let fee = set_fee_tier.fee;
+    let enabled = set_fee_tier.enabled != 0;
+
+    let [admin, factory] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut factory_data = factory.data.borrow_mut();
+    let factory_account = Factory::load_mut(&mut factory_data)?;
+
+    factory_account.check_admin(factory, admin)?;
+
+    factory_account.set_fee_tier(fee, enabled)?;
+
+    emit(&amm_events::FeeTierSet {
+        factory: *factory.key,
+        admin: *admin.key,
+        fee,
+        enabled,
+    });
+
+    Ok(())
+}
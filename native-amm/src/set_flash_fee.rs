@@ -0,0 +1,29 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{error::AmmError, instruction::SetFlashFee, state::Config};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let set_flash_fee = SetFlashFee::try_from(data)?;
+    let flash_fee = set_flash_fee.flash_fee;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config_data = config.data.borrow_mut();
+    let config_account = Config::load_mut(&mut config_data)?;
+
+    config_account.check_authority(config, authority)?;
+
+    if flash_fee >= 10_000 {
+        return Err(AmmError::InvalidFee.into());
+    }
+
+    config_account.flash_fee = flash_fee;
+
+    config_account.emit_updated(config.key, authority.key);
+
+    Ok(())
+}
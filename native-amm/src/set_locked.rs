@@ -0,0 +1,25 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{instruction::SetLocked, state::Config};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let set_locked = SetLocked::try_from(data)?;
+    let locked = set_locked.locked;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut config_data = config.data.borrow_mut();
+    let config_account = Config::load_mut(&mut config_data)?;
+
+    config_account.check_authority(config, authority)?;
+
+    config_account.locked = (locked != 0) as u8;
+
+    config_account.emit_updated(config.key, authority.key);
+
+    Ok(())
+}
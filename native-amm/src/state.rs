@@ -1,21 +1,58 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use amm_math::{stable, weighted};
+use bytemuck::{Pod, Zeroable};
 use solana_program::account_info::AccountInfo;
+use solana_program::clock::Clock;
 use solana_program::entrypoint::ProgramResult;
 use solana_program::program::invoke_signed;
 use solana_program::program_error::ProgramError;
-use solana_program::program_pack::Pack;
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
 use solana_program::system_instruction::create_account;
 use solana_program::sysvar::Sysvar;
-use spl_token::state::Mint;
 
-use crate::utils::{check_pda_and_get_bump, deposit, mint, withdraw};
+use crate::error::AmmError;
+use crate::oracle::Oracle;
+use crate::utils::{
+    amount_after_transfer_fee, amount_before_transfer_fee, burn, check_config_owner,
+    check_pda_and_get_bump, check_signer, deposit, emit, mint, unpack_mint, unpack_token_account,
+    withdraw,
+};
 
-#[derive(BorshSerialize, BorshDeserialize)]
+pub use amm_math::Curve;
+
+/// LP locked forever on the first deposit so the pool can never be fully drained
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// Values of `Config::curve`
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CurveType {
+    /// Weighted product, which is `x * y = k` at even weights
+    ConstantProduct = 0,
+    Stable = 1,
+}
+
+impl TryFrom<u8> for CurveType {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::ConstantProduct),
+            1 => Ok(Self::Stable),
+            _ => Err(AmmError::InvalidCurve.into()),
+        }
+    }
+}
+
+/// A pool created with its own seed is at the PDA `["config", seed]`, and a
+/// canonical pool from the factory at `["config", mint_x, mint_y, fee_tier]`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Config {
+    /// Zero for a canonical pool
     pub seed: u64,
     pub authority: Pubkey,
+    pub pending_authority: Pubkey,
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
     pub fee: u16,
@@ -24,18 +61,143 @@ pub struct Config {
     pub lp_bump: u8,
     pub x_bump: u8,
     pub y_bump: u8,
+    /// A `CurveType`
+    pub curve: u8,
+    /// StableSwap amplification, ramping linearly from `initial_amp` at
+    /// `ramp_start` to `target_amp` at `ramp_end`. Zero for constant product
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start: i64,
+    pub ramp_end: i64,
+    /// Share of the pool's value held in x, in basis points. Always
+    /// `EVEN_WEIGHT` for StableSwap
+    pub weight_x: u16,
+    /// Fee on flash loans in basis points, zero until the authority sets one
+    pub flash_fee: u16,
+    /// The fee a canonical pool was created at, which stays in its address
+    /// when the authority changes `fee`. Zero otherwise
+    pub fee_tier: u16,
+    /// One for a canonical pool
+    pub factory: u8,
     pub padding: [u8; 1],
 }
 
 impl Config {
-    pub fn try_from(data: &[u8]) -> Result<Self, ProgramError> {
-        Self::try_from_slice(data).map_err(|_| ProgramError::InvalidAccountData)
+    pub const LEN: usize = std::mem::size_of::<Config>();
+
+    /// Views the account data as a `Config` in place, without copying
+    #[inline]
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        bytemuck::try_from_bytes(
+            data.get(..Self::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    #[inline]
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        bytemuck::try_from_bytes_mut(
+            data.get_mut(..Self::LEN)
+                .ok_or(ProgramError::InvalidAccountData)?,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    #[inline]
+    pub fn check_authority(&self, config: &AccountInfo, authority: &AccountInfo) -> ProgramResult {
+        check_config_owner(config)?;
+
+        check_signer(authority)?;
+
+        if authority.key != &self.authority {
+            return Err(AmmError::Unauthorized.into());
+        }
+
+        Ok(())
     }
 
+    /// Seeds of the config PDA, without its bump. The integers are stored
+    /// little-endian, as the seeds spell them
+    pub fn seeds(&self) -> Vec<&[u8]> {
+        if self.factory == 1 {
+            vec![
+                b"config",
+                self.mint_x.as_ref(),
+                self.mint_y.as_ref(),
+                bytemuck::bytes_of(&self.fee_tier),
+            ]
+        } else {
+            vec![b"config", bytemuck::bytes_of(&self.seed)]
+        }
+    }
+
+    /// Seeds the config signs for the vaults and the LP mint with
+    pub fn signer_seeds(&self) -> Vec<&[u8]> {
+        let mut seeds = self.seeds();
+        seeds.push(std::slice::from_ref(&self.config_bump));
+        seeds
+    }
+
+    /// Logs the pool's settings after an admin instruction `user` signed
+    pub fn emit_updated(&self, config: &Pubkey, user: &Pubkey) {
+        emit(&amm_events::ConfigUpdated {
+            pool: *config,
+            user: *user,
+            authority: self.authority,
+            pending_authority: self.pending_authority,
+            fee: self.fee,
+            flash_fee: self.flash_fee,
+            locked: self.locked != 0,
+            target_amp: self.target_amp,
+            ramp_end: self.ramp_end,
+        });
+    }
+
+    /// The pool's curve, at the amplification in effect now
+    #[inline]
+    pub fn curve(&self) -> Result<Curve, ProgramError> {
+        self.curve_at(Clock::get()?.unix_timestamp)
+    }
+
+    /// The pool's curve, at the amplification in effect at `now`
+    pub fn curve_at(&self, now: i64) -> Result<Curve, ProgramError> {
+        Ok(match CurveType::try_from(self.curve)? {
+            CurveType::ConstantProduct if self.weight_x == weighted::EVEN_WEIGHT => {
+                Curve::ConstantProduct
+            }
+            CurveType::ConstantProduct => Curve::Weighted {
+                weight_x: self.weight_x,
+            },
+            CurveType::Stable => Curve::Stable {
+                amp: stable::current_amp(
+                    self.initial_amp,
+                    self.target_amp,
+                    self.ramp_start,
+                    self.ramp_end,
+                    now,
+                ),
+            },
+        })
+    }
+
+    /// LP supply including the minimum liquidity locked by the first deposit,
+    /// which is never minted but still owns its share of the vaults
+    #[inline]
+    pub fn total_liquidity(lp_supply: u64) -> u64 {
+        lp_supply.saturating_add(MINIMUM_LIQUIDITY)
+    }
+
+    /// Creates the config of a pool with its own `seed`, or of the canonical
+    /// pool for the mints at `fee` when `factory` is set
     pub fn initialize<'a>(
         seed: u64,
+        factory: bool,
         authority: Pubkey,
         fee: u16,
+        curve: u8,
+        weight_x: u16,
+        amp: u64,
         lp_bump: u8,
         x_bump: u8,
         y_bump: u8,
@@ -44,48 +206,73 @@ impl Config {
         initializer: &AccountInfo<'a>,
         config: &AccountInfo<'a>,
     ) -> ProgramResult {
-        let config_bump = check_pda_and_get_bump(
-            &[b"config", seed.to_le_bytes().as_ref()],
-            &crate::ID,
-            config.key,
-        )?;
+        if fee >= 10_000 {
+            return Err(AmmError::InvalidFee.into());
+        }
 
-        assert!(fee < 10_000);
+        if mint_x.key == mint_y.key {
+            return Err(AmmError::IdenticalMints.into());
+        }
 
-        let _ = spl_token::state::Mint::unpack(&mint_x.try_borrow_data()?);
-        let _ = spl_token::state::Mint::unpack(&mint_y.try_borrow_data()?);
+        // Only StableSwap pools have an amplification, and only constant-product
+        // pools can weigh their tokens unevenly
+        match CurveType::try_from(curve)? {
+            CurveType::ConstantProduct if amp != 0 => return Err(AmmError::InvalidAmp.into()),
+            CurveType::ConstantProduct => {
+                weighted::check_weight(weight_x).map_err(AmmError::from)?
+            }
+            CurveType::Stable if weight_x != weighted::EVEN_WEIGHT => {
+                return Err(AmmError::InvalidWeight.into())
+            }
+            CurveType::Stable => stable::check_amp(amp).map_err(AmmError::from)?,
+        }
 
-        let config_space = std::mem::size_of::<Config>() + 32; // Extra space for Borsh serialization
-        let config_rent = Rent::get()?.minimum_balance(config_space);
+        unpack_mint(mint_x)?;
+        unpack_mint(mint_y)?;
 
-        invoke_signed(
-            &create_account(
-                initializer.key,
-                config.key,
-                config_rent,
-                config_space as u64,
-                &crate::ID,
-            ),
-            &[initializer.clone(), config.clone()],
-            &[&[b"config", seed.to_le_bytes().as_ref(), &[config_bump]]],
-        )?;
-
-        let config_data = Config {
+        let mut config_account = Config {
             seed,
             authority,
+            pending_authority: Pubkey::default(),
             mint_x: *mint_x.key,
             mint_y: *mint_y.key,
             fee,
             locked: 0,
-            config_bump,
+            config_bump: 0,
             lp_bump,
             x_bump,
             y_bump,
+            curve,
+            initial_amp: amp,
+            target_amp: amp,
+            ramp_start: 0,
+            ramp_end: 0,
+            weight_x,
+            flash_fee: 0,
+            fee_tier: if factory { fee } else { 0 },
+            factory: factory as u8,
             padding: [0; 1],
         };
 
-        config_data.serialize(&mut *config.data.borrow_mut())
-            .map_err(|_| ProgramError::InvalidAccountData)?;
+        config_account.config_bump =
+            check_pda_and_get_bump(&config_account.seeds(), &crate::ID, config.key)?;
+
+        let config_space = Config::LEN;
+        let config_rent = Rent::get()?.minimum_balance(config_space);
+
+        invoke_signed(
+            &create_account(
+                initializer.key,
+                config.key,
+                config_rent,
+                config_space as u64,
+                &crate::ID,
+            ),
+            &[initializer.clone(), config.clone()],
+            &[&config_account.signer_seeds()],
+        )?;
+
+        *Config::load_mut(&mut config.data.borrow_mut())? = config_account;
 
         Ok(())
     }
@@ -96,6 +283,8 @@ impl Config {
         max_y: u64,
         config_account: &Config,
         token_program: &Pubkey,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
         user_x: &AccountInfo<'a>,
         user_y: &AccountInfo<'a>,
         user_lp: &AccountInfo<'a>,
@@ -107,31 +296,102 @@ impl Config {
         config: &AccountInfo<'a>,
         user: &AccountInfo<'a>,
     ) -> ProgramResult {
-        let mint_lp_account = spl_token::state::Mint::unpack(&mint_lp.try_borrow_data()?)?;
+        let mint_lp_account = unpack_mint(mint_lp)?;
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+
+        let mint_x_decimals = unpack_mint(mint_x)?.decimals;
+        let mint_y_decimals = unpack_mint(mint_y)?.decimals;
+
+        let curve = config_account.curve()?;
 
-        assert!(amount <= max_x);
-        assert!(amount <= max_y);
+        let (amount_x, amount_y, lp_amount) = if mint_lp_account.supply == 0 {
+            // First deposit sets the price: the depositor provides both maxima and
+            // receives the curve's initial liquidity, the weighted geometric mean
+            // x^wx * y^wy or StableSwap's D, minus the permanently locked minimum
+            // liquidity.
+            // LP is priced on what the vaults receive after any transfer fee
+            let received_x = amount_after_transfer_fee(mint_x, max_x)?;
+            let received_y = amount_after_transfer_fee(mint_y, max_y)?;
+            let lp_amount = curve
+                .initial_liquidity(received_x, received_y)
+                .map_err(AmmError::from)?
+                .checked_sub(MINIMUM_LIQUIDITY)
+                .filter(|lp_amount| *lp_amount > 0)
+                .ok_or(AmmError::InsufficientLiquidity)?;
 
-        let mint_x_decimals = Mint::unpack(mint_x.data.borrow().as_ref())?.decimals;
-        let mint_y_decimals = Mint::unpack(mint_y.data.borrow().as_ref())?.decimals;
+            if lp_amount < amount {
+                return Err(AmmError::SlippageExceeded.into());
+            }
+
+            (max_x, max_y, lp_amount)
+        } else if let Curve::Stable { amp } = curve {
+            // StableSwap takes deposits in any ratio: both maxima are deposited and
+            // LP is minted on how much they grow the invariant, at least `amount`
+            let received_x = amount_after_transfer_fee(mint_x, max_x)?;
+            let received_y = amount_after_transfer_fee(mint_y, max_y)?;
+            let lp_amount = stable::deposit_liquidity(
+                received_x,
+                received_y,
+                vault_x_account.amount,
+                vault_y_account.amount,
+                Config::total_liquidity(mint_lp_account.supply),
+                config_account.fee,
+                amp,
+            )
+            .map_err(AmmError::from)?;
+
+            if lp_amount < amount {
+                return Err(AmmError::SlippageExceeded.into());
+            }
+
+            (max_x, max_y, lp_amount)
+        } else {
+            if amount == 0 {
+                return Err(AmmError::InvalidAmount.into());
+            }
+
+            let lp_supply = Config::total_liquidity(mint_lp_account.supply);
+
+            // Deposits into a product pool are pro rata, which keeps the value
+            // split its weights set. Round the required deposits up so minting
+            // never dilutes existing LPs, then gross them up so the vaults
+            // receive them net of transfer fees
+            let amount_x = amount_before_transfer_fee(
+                mint_x,
+                amm_math::deposit_share(amount, vault_x_account.amount, lp_supply)
+                    .map_err(AmmError::from)?,
+            )?;
+            let amount_y = amount_before_transfer_fee(
+                mint_y,
+                amm_math::deposit_share(amount, vault_y_account.amount, lp_supply)
+                    .map_err(AmmError::from)?,
+            )?;
+
+            if amount_x > max_x || amount_y > max_y {
+                return Err(AmmError::SlippageExceeded.into());
+            }
+
+            (amount_x, amount_y, amount)
+        };
 
         deposit(
-            token_program,
+            token_program_x,
             user_x,
             mint_x,
             vault_x,
             user,
-            amount,
+            amount_x,
             mint_x_decimals,
         )?;
 
         deposit(
-            token_program,
+            token_program_y,
             user_y,
             mint_y,
             vault_y,
             user,
-            amount,
+            amount_y,
             mint_y_decimals,
         )?;
 
@@ -140,86 +400,354 @@ impl Config {
             mint_lp,
             user_lp,
             config,
+            lp_amount,
+            mint_lp_account.decimals,
+            &config_account.signer_seeds(),
+        )?;
+
+        emit(&amm_events::Deposit {
+            pool: *config.key,
+            user: *user.key,
+            amount_x,
+            amount_y,
+            lp_minted: lp_amount,
+            reserve_x: unpack_token_account(vault_x)?.amount,
+            reserve_y: unpack_token_account(vault_y)?.amount,
+            lp_supply: unpack_mint(mint_lp)?.supply,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_liquidity<'a>(
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        config_account: &Config,
+        token_program: &Pubkey,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        user_x: &AccountInfo<'a>,
+        user_y: &AccountInfo<'a>,
+        user_lp: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let mint_lp_account = unpack_mint(mint_lp)?;
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+
+        let mint_x_decimals = unpack_mint(mint_x)?.decimals;
+        let mint_y_decimals = unpack_mint(mint_y)?.decimals;
+
+        if amount == 0 || amount > mint_lp_account.supply {
+            return Err(AmmError::InvalidAmount.into());
+        }
+
+        let lp_supply = Config::total_liquidity(mint_lp_account.supply);
+
+        // Each LP token is a claim on an equal share of both vaults
+        let amount_x = amm_math::withdraw_share(amount, vault_x_account.amount, lp_supply)
+            .map_err(AmmError::from)?;
+        let amount_y = amm_math::withdraw_share(amount, vault_y_account.amount, lp_supply)
+            .map_err(AmmError::from)?;
+
+        // Slippage applies to what the user receives after any transfer fee
+        if amount_after_transfer_fee(mint_x, amount_x)? < min_x
+            || amount_after_transfer_fee(mint_y, amount_y)? < min_y
+        {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        burn(
+            token_program,
+            user_lp,
+            mint_lp,
+            user,
             amount,
             mint_lp_account.decimals,
-            &[
-                b"config",
-                config_account.seed.to_le_bytes().as_ref(),
-                &[config_account.config_bump],
-            ],
-        )
+        )?;
+
+        withdraw(
+            token_program_x,
+            vault_x,
+            mint_x,
+            user_x,
+            config,
+            amount_x,
+            mint_x_decimals,
+            &config_account.signer_seeds(),
+        )?;
+
+        withdraw(
+            token_program_y,
+            vault_y,
+            mint_y,
+            user_y,
+            config,
+            amount_y,
+            mint_y_decimals,
+            &config_account.signer_seeds(),
+        )?;
+
+        emit(&amm_events::Withdraw {
+            pool: *config.key,
+            user: *user.key,
+            lp_burned: amount,
+            amount_x,
+            amount_y,
+            reserve_x: unpack_token_account(vault_x)?.amount,
+            reserve_y: unpack_token_account(vault_y)?.amount,
+            lp_supply: unpack_mint(mint_lp)?.supply,
+        });
+
+        Ok(())
     }
 
     pub fn perform_swap<'a>(
         config_account: &Config,
-        token_program: &Pubkey,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
         amount: u64,
         min: u64,
+        from_x: bool,
         mint_x: &AccountInfo<'a>,
         mint_y: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
         vault_x: &AccountInfo<'a>,
         vault_y: &AccountInfo<'a>,
-        user_from: &AccountInfo<'a>,
-        user_to: &AccountInfo<'a>,
+        user_x: &AccountInfo<'a>,
+        user_y: &AccountInfo<'a>,
         config: &AccountInfo<'a>,
+        oracle: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
     ) -> ProgramResult {
-        let vault_x_account = spl_token::state::Account::unpack(vault_x.data.borrow().as_ref())?;
-        let vault_y_account = spl_token::state::Account::unpack(vault_y.data.borrow().as_ref())?;
+        let output_amount = Config::quote_swap(
+            config_account,
+            amount,
+            from_x,
+            mint_x,
+            mint_y,
+            vault_x,
+            vault_y,
+            config,
+            oracle,
+        )?;
+
+        let mint_out = if from_x { mint_y } else { mint_x };
+
+        if amount_after_transfer_fee(mint_out, output_amount)? < min {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        Config::settle_swap(
+            config_account,
+            token_program_x,
+            token_program_y,
+            amount,
+            output_amount,
+            from_x,
+            mint_x,
+            mint_y,
+            mint_lp,
+            vault_x,
+            vault_y,
+            user_x,
+            user_y,
+            config,
+            user,
+        )
+    }
+
+    /// Prices selling `amount` of the input token, as sent by the trader, against
+    /// the current reserves and records those reserves in the oracle. Returns what
+    /// the output vault has to send
+    pub fn quote_swap<'a>(
+        config_account: &Config,
+        amount: u64,
+        from_x: bool,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        oracle: &AccountInfo<'a>,
+    ) -> Result<u64, ProgramError> {
+        let curve = config_account.curve()?;
+        let reserve_x = unpack_token_account(vault_x)?.amount;
+        let reserve_y = unpack_token_account(vault_y)?.amount;
+
+        // The oracle sees the price every swap started from
+        Oracle::update(oracle, config, &curve, reserve_x, reserve_y)?;
+
+        let (mint_in, reserve_in, reserve_out) = if from_x {
+            (mint_x, reserve_x, reserve_y)
+        } else {
+            (mint_y, reserve_y, reserve_x)
+        };
+
+        // The curve only sees what the vault actually receives
+        let amount_received = amount_after_transfer_fee(mint_in, amount)?;
 
-        let mint_x_decimals = Mint::unpack(mint_x.data.borrow().as_ref())?.decimals;
-        let mint_y_decimals = Mint::unpack(mint_y.data.borrow().as_ref())?.decimals;
+        Ok(curve
+            .swap_exact_in(
+                amount_received,
+                reserve_in,
+                reserve_out,
+                config_account.fee,
+                from_x,
+            )
+            .map_err(AmmError::from)?)
+    }
 
-        let x_reserve = vault_x_account.amount;
-        let y_reserve = vault_y_account.amount;
+    pub fn perform_swap_exact_out<'a>(
+        config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        amount_out: u64,
+        max_in: u64,
+        from_x: bool,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        user_x: &AccountInfo<'a>,
+        user_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        oracle: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let (mint_in, mint_out, vault_in, vault_out) = if from_x {
+            (mint_x, mint_y, vault_x, vault_y)
+        } else {
+            (mint_y, mint_x, vault_y, vault_x)
+        };
+
+        let curve = config_account.curve()?;
+        let reserve_in = unpack_token_account(vault_in)?.amount;
+        let reserve_out = unpack_token_account(vault_out)?.amount;
 
-        let fee_numerator = config_account.fee as u64;
-        let fee_denominator = 10_000u64;
+        // The oracle sees the price every swap started from
+        let (reserve_x, reserve_y) = if from_x {
+            (reserve_in, reserve_out)
+        } else {
+            (reserve_out, reserve_in)
+        };
+        Oracle::update(oracle, config, &curve, reserve_x, reserve_y)?;
 
-        let amount_with_fee = amount
-            .checked_mul(fee_denominator - fee_numerator)
-            .ok_or(ProgramError::InvalidArgument)?
-            .checked_div(fee_denominator)
-            .ok_or(ProgramError::InvalidArgument)?;
+        // The vault has to send enough for amount_out to arrive after any transfer fee
+        let amount_sent = amount_before_transfer_fee(mint_out, amount_out)?;
 
-        let denominator = x_reserve
-            .checked_add(amount_with_fee)
-            .ok_or(ProgramError::InvalidArgument)?;
+        // Rounds up so the trader always pays for the rounding
+        let input_amount = curve
+            .swap_exact_out(
+                amount_sent,
+                reserve_in,
+                reserve_out,
+                config_account.fee,
+                from_x,
+            )
+            .map_err(AmmError::from)?;
 
-        let output_amount = y_reserve
-            .checked_mul(amount_with_fee)
-            .ok_or(ProgramError::InvalidArgument)?
-            .checked_div(denominator)
-            .ok_or(ProgramError::InvalidArgument)?;
+        let input_amount = amount_before_transfer_fee(mint_in, input_amount)?;
 
-        if output_amount < min {
-            return Err(ProgramError::InsufficientFunds);
+        if input_amount > max_in {
+            return Err(AmmError::SlippageExceeded.into());
         }
 
-        deposit(
-            token_program,
-            user_from,
+        Config::settle_swap(
+            config_account,
+            token_program_x,
+            token_program_y,
+            input_amount,
+            amount_sent,
+            from_x,
             mint_x,
+            mint_y,
+            mint_lp,
             vault_x,
-            user_to,
-            amount,
-            mint_x_decimals,
+            vault_y,
+            user_x,
+            user_y,
+            config,
+            user,
+        )
+    }
+
+    fn settle_swap<'a>(
+        config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        amount_in: u64,
+        amount_out: u64,
+        from_x: bool,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        user_x: &AccountInfo<'a>,
+        user_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let (mint_in, mint_out, vault_in, vault_out, user_from, user_to) = if from_x {
+            (mint_x, mint_y, vault_x, vault_y, user_x, user_y)
+        } else {
+            (mint_y, mint_x, vault_y, vault_x, user_y, user_x)
+        };
+
+        let (token_program_in, token_program_out) = if from_x {
+            (token_program_x, token_program_y)
+        } else {
+            (token_program_y, token_program_x)
+        };
+
+        let mint_in_decimals = unpack_mint(mint_in)?.decimals;
+        let mint_out_decimals = unpack_mint(mint_out)?.decimals;
+
+        deposit(
+            token_program_in,
+            user_from,
+            mint_in,
+            vault_in,
+            user,
+            amount_in,
+            mint_in_decimals,
         )?;
 
         withdraw(
-            token_program,
-            vault_y,
-            mint_y,
+            token_program_out,
+            vault_out,
+            mint_out,
             user_to,
             config,
-            output_amount,
-            mint_y_decimals,
-            &[
-                b"config",
-                config_account.seed.to_le_bytes().as_ref(),
-                &[config_account.config_bump],
-            ],
+            amount_out,
+            mint_out_decimals,
+            &config_account.signer_seeds(),
         )?;
 
+        let amount_received = amount_after_transfer_fee(mint_in, amount_in)?;
+
+        emit(&amm_events::Swap {
+            pool: *config.key,
+            user: *user.key,
+            from_x,
+            amount_in: amount_received,
+            amount_out,
+            fee_paid: amm_math::swap_fee(amount_received, config_account.fee)
+                .map_err(AmmError::from)?,
+            reserve_x: unpack_token_account(vault_x)?.amount,
+            reserve_y: unpack_token_account(vault_y)?.amount,
+            lp_supply: unpack_mint(mint_lp)?.supply,
+        });
+
         Ok(())
     }
 }
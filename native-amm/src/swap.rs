@@ -2,45 +2,51 @@ use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
 };
 
-use crate::{instruction::Swap, state::Config, utils::perform_basic_checks};
+use crate::{
+    instruction::Swap,
+    state::Config,
+    utils::{check_mint_lp, check_mint_token_program, check_signer, perform_basic_checks},
+};
 
 pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let swap = Swap::try_from(data)?;
     let amount = swap.amount;
     let min = swap.min;
     let expiration = swap.expiration;
+    let from_x = swap.from_x != 0;
 
-    let [user, mint_x, mint_y, vault_x, vault_y, user_x, user_y, config, token_program, _system_program] =
+    let [user, mint_x, mint_y, mint_lp, vault_x, vault_y, user_x, user_y, config, oracle, token_program_x, token_program_y, _system_program] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    assert!(user.is_signer);
-    assert_eq!(token_program.key, &spl_token::ID);
+    check_signer(user)?;
+    check_mint_token_program(token_program_x.key, mint_x)?;
+    check_mint_token_program(token_program_y.key, mint_y)?;
 
-    let config_account = Config::try_from(config.data.borrow().as_ref())?;
+    let config_data = config.data.borrow();
+    let config_account = Config::load(&config_data)?;
 
-    perform_basic_checks(
-        &config_account,
-        expiration,
-        config,
-        mint_x,
-        vault_x,
-        vault_y,
-    )?;
+    perform_basic_checks(config_account, expiration, config, vault_x, vault_y)?;
+    check_mint_lp(config_account, config, mint_lp)?;
 
     Config::perform_swap(
-        &config_account,
-        token_program.key,
+        config_account,
+        token_program_x.key,
+        token_program_y.key,
         amount,
         min,
+        from_x,
         mint_x,
         mint_y,
+        mint_lp,
         vault_x,
         vault_y,
         user_x,
         user_y,
         config,
+        oracle,
+        user,
     )
 }
@@ -0,0 +1,52 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{
+    instruction::SwapExactOut,
+    state::Config,
+    utils::{check_mint_lp, check_mint_token_program, check_signer, perform_basic_checks},
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let swap = SwapExactOut::try_from(data)?;
+    let amount_out = swap.amount_out;
+    let max_in = swap.max_in;
+    let expiration = swap.expiration;
+    let from_x = swap.from_x != 0;
+
+    let [user, mint_x, mint_y, mint_lp, vault_x, vault_y, user_x, user_y, config, oracle, token_program_x, token_program_y, _system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(user)?;
+    check_mint_token_program(token_program_x.key, mint_x)?;
+    check_mint_token_program(token_program_y.key, mint_y)?;
+
+    let config_data = config.data.borrow();
+    let config_account = Config::load(&config_data)?;
+
+    perform_basic_checks(config_account, expiration, config, vault_x, vault_y)?;
+    check_mint_lp(config_account, config, mint_lp)?;
+
+    Config::perform_swap_exact_out(
+        config_account,
+        token_program_x.key,
+        token_program_y.key,
+        amount_out,
+        max_in,
+        from_x,
+        mint_x,
+        mint_y,
+        mint_lp,
+        vault_x,
+        vault_y,
+        user_x,
+        user_y,
+        config,
+        oracle,
+        user,
+    )
+}
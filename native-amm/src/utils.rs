@@ -1,7 +1,10 @@
+use amm_events::Event;
 use solana_program::{
     account_info::AccountInfo,
+    bpf_loader_upgradeable,
     clock::Clock,
     entrypoint::ProgramResult,
+    log::sol_log_data,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
     program_pack::Pack,
@@ -10,11 +13,149 @@ use solana_program::{
     system_instruction::create_account,
     sysvar::Sysvar,
 };
-use spl_token::instruction::{
-    initialize_account3, initialize_mint2, mint_to_checked, transfer_checked,
+use spl_token_2022::{
+    extension::{
+        default_account_state::DefaultAccountState,
+        transfer_fee::{TransferFee, TransferFeeConfig},
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    instruction::{
+        burn_checked, initialize_account3, initialize_mint2, mint_to_checked, transfer_checked,
+    },
+    state::{Account, AccountState, Mint},
 };
 
-use crate::state::Config;
+use crate::{error::AmmError, state::Config};
+
+/// Logs `event` as a `Program data:` line, which `amm_events::parse_logs` reads back
+#[inline]
+pub fn emit<E: Event>(event: &E) {
+    sol_log_data(&[&event.to_bytes()]);
+}
+
+#[inline]
+pub fn check_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        return Err(AmmError::MissingSigner.into());
+    }
+
+    Ok(())
+}
+
+/// The LP mint is always a legacy SPL Token mint
+#[inline]
+pub fn check_token_program(token_program: &Pubkey) -> ProgramResult {
+    if token_program != &spl_token::ID {
+        return Err(AmmError::InvalidTokenProgram.into());
+    }
+
+    Ok(())
+}
+
+/// X and Y mints may live under either SPL Token or Token-2022, and the
+/// program passed for a mint must be the one that owns it
+#[inline]
+pub fn check_mint_token_program(token_program: &Pubkey, mint: &AccountInfo) -> ProgramResult {
+    if token_program != &spl_token::ID && token_program != &spl_token_2022::ID {
+        return Err(AmmError::InvalidTokenProgram.into());
+    }
+
+    if mint.owner != token_program {
+        return Err(AmmError::InvalidTokenProgram.into());
+    }
+
+    Ok(())
+}
+
+/// Token-2022 mint extensions a pool can hold. Anything else could let an
+/// outside authority move, freeze, gate or hide the tokens in its vaults
+const SUPPORTED_MINT_EXTENSIONS: &[ExtensionType] = &[
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::DefaultAccountState,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
+
+/// Checks that a Token-2022 mint only has supported extensions, and that new
+/// accounts for it, the pool's vaults among them, do not start frozen
+pub fn check_mint_extensions(mint: &AccountInfo) -> ProgramResult {
+    if mint.owner != &spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+
+    if mint_state
+        .get_extension_types()?
+        .iter()
+        .any(|extension| !SUPPORTED_MINT_EXTENSIONS.contains(extension))
+    {
+        return Err(AmmError::UnsupportedMintExtension.into());
+    }
+
+    if let Ok(default_account_state) = mint_state.get_extension::<DefaultAccountState>() {
+        if default_account_state.state != AccountState::Initialized as u8 {
+            return Err(AmmError::UnsupportedMintExtension.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Unpacks a mint owned by either token program, ignoring any extensions
+#[inline]
+pub fn unpack_mint(mint: &AccountInfo) -> Result<Mint, ProgramError> {
+    Ok(StateWithExtensions::<Mint>::unpack(&mint.try_borrow_data()?)?.base)
+}
+
+/// Unpacks a token account owned by either token program, ignoring any extensions
+#[inline]
+pub fn unpack_token_account(account: &AccountInfo) -> Result<Account, ProgramError> {
+    Ok(StateWithExtensions::<Account>::unpack(&account.try_borrow_data()?)?.base)
+}
+
+/// Checks that `authority` signed and is the upgrade authority recorded in
+/// `program_data`, this program's program data account
+pub fn check_upgrade_authority(
+    program_data: &AccountInfo,
+    authority: &AccountInfo,
+) -> ProgramResult {
+    check_signer(authority)?;
+
+    if program_data.owner != &bpf_loader_upgradeable::ID
+        || program_data.key != &bpf_loader_upgradeable::get_program_data_address(&crate::ID)
+    {
+        return Err(AmmError::InvalidPda.into());
+    }
+
+    // Serialized as the variant tag 3 as a u32, the deployment slot and then
+    // the authority as an `Option`
+    let data = program_data.try_borrow_data()?;
+    if data.get(..4) != Some(&3u32.to_le_bytes()[..])
+        || data.get(12) != Some(&1)
+        || data.get(13..45) != Some(authority.key.as_ref())
+    {
+        return Err(AmmError::Unauthorized.into());
+    }
+
+    Ok(())
+}
+
+#[inline]
+pub fn check_config_owner(config: &AccountInfo) -> ProgramResult {
+    if config.owner != &crate::ID {
+        return Err(AmmError::InvalidConfigOwner.into());
+    }
+
+    Ok(())
+}
 
 #[inline]
 pub fn check_pda_with_bump(
@@ -22,8 +163,14 @@ pub fn check_pda_with_bump(
     program_id: &Pubkey,
     address: &Pubkey,
 ) -> Result<(), ProgramError> {
-    let derived_address = Pubkey::create_program_address(seeds, program_id)?;
-    Ok(assert!(derived_address.eq(address)))
+    let derived_address =
+        Pubkey::create_program_address(seeds, program_id).map_err(|_| AmmError::InvalidPda)?;
+
+    if !derived_address.eq(address) {
+        return Err(AmmError::InvalidPda.into());
+    }
+
+    Ok(())
 }
 
 #[inline]
@@ -32,12 +179,57 @@ pub fn check_pda_and_get_bump(
     program_id: &Pubkey,
     address: &Pubkey,
 ) -> Result<u8, ProgramError> {
-    let (derived_address, bump) = Pubkey::try_find_program_address(seeds, program_id)
-        .ok_or(ProgramError::InvalidAccountData)?;
-    assert!(derived_address.eq(address));
+    let (derived_address, bump) =
+        Pubkey::try_find_program_address(seeds, program_id).ok_or(AmmError::InvalidPda)?;
+
+    if !derived_address.eq(address) {
+        return Err(AmmError::InvalidPda.into());
+    }
+
     Ok(bump)
 }
 
+/// Transfer fee a Token-2022 mint charges in the current epoch, if it has one
+#[inline]
+fn epoch_transfer_fee(mint: &AccountInfo) -> Result<Option<TransferFee>, ProgramError> {
+    if mint.owner != &spl_token_2022::ID {
+        return Ok(None);
+    }
+
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+
+    let Ok(transfer_fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        *transfer_fee_config.get_epoch_fee(Clock::get()?.epoch),
+    ))
+}
+
+/// Amount that arrives at the destination when `amount` of `mint` is transferred
+#[inline]
+pub fn amount_after_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    match epoch_transfer_fee(mint)? {
+        Some(transfer_fee) => Ok(transfer_fee
+            .calculate_post_fee_amount(amount)
+            .ok_or(AmmError::MathOverflow)?),
+        None => Ok(amount),
+    }
+}
+
+/// Amount of `mint` that must be transferred for `amount` to arrive at the destination
+#[inline]
+pub fn amount_before_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    match epoch_transfer_fee(mint)? {
+        Some(transfer_fee) => Ok(transfer_fee
+            .calculate_pre_fee_amount(amount)
+            .ok_or(AmmError::MathOverflow)?),
+        None => Ok(amount),
+    }
+}
+
 pub fn create_token_account<'a>(
     seeds: &[&[u8]],
     token_program: &Pubkey,
@@ -46,7 +238,15 @@ pub fn create_token_account<'a>(
     mint: &AccountInfo<'a>,
     authority: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let token_space = spl_token::state::Account::LEN;
+    // Token-2022 mints can require extensions on every account that holds them
+    let token_space = {
+        let mint_data = mint.try_borrow_data()?;
+        let mint_extensions =
+            StateWithExtensions::<Mint>::unpack(&mint_data)?.get_extension_types()?;
+        let account_extensions =
+            ExtensionType::get_required_init_account_extensions(&mint_extensions);
+        ExtensionType::try_calculate_account_len::<Account>(&account_extensions)?
+    };
     let token_rent = Rent::get()?.minimum_balance(token_space);
 
     invoke_signed(
@@ -55,7 +255,7 @@ pub fn create_token_account<'a>(
             ta.key,
             token_rent,
             token_space as u64,
-            &spl_token::ID,
+            token_program,
         ),
         &[payer.clone(), ta.clone()],
         &[seeds],
@@ -74,7 +274,7 @@ pub fn create_mint<'a>(
     mint: &AccountInfo<'a>,
     authority: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let mint_space = spl_token::state::Mint::LEN;
+    let mint_space = Mint::LEN;
     let mint_rent = Rent::get()?.minimum_balance(mint_space);
 
     invoke_signed(
@@ -83,7 +283,7 @@ pub fn create_mint<'a>(
             mint.key,
             mint_rent,
             mint_space as u64,
-            &spl_token::ID,
+            token_program,
         ),
         &[payer.clone(), mint.clone()],
         &[seeds],
@@ -91,7 +291,7 @@ pub fn create_mint<'a>(
 
     invoke(
         &initialize_mint2(token_program, mint.key, authority.key, None, 0)?,
-        &[mint.clone()],
+        std::slice::from_ref(mint),
     )
 }
 
@@ -100,22 +300,30 @@ pub fn perform_basic_checks(
     config_account: &Config,
     expiration: i64,
     config: &AccountInfo,
-    mint_lp: &AccountInfo,
     vault_x: &AccountInfo,
     vault_y: &AccountInfo,
 ) -> ProgramResult {
-    assert!(Clock::get()?.unix_timestamp <= expiration);
+    if Clock::get()?.unix_timestamp > expiration {
+        return Err(AmmError::Expired.into());
+    }
 
-    assert_eq!(config.owner, &crate::ID);
+    check_config_owner(config)?;
 
-    assert_ne!(config_account.locked, 1);
+    if config_account.locked == 1 {
+        return Err(AmmError::PoolLocked.into());
+    }
 
-    check_pda_with_bump(
-        &[config.key.as_ref(), &[config_account.lp_bump]],
-        &crate::ID,
-        mint_lp.key,
-    )?;
+    check_vaults(config_account, config, vault_x, vault_y)
+}
 
+/// Checks that the vaults are the pool's own, from the bumps stored at initialization
+#[inline]
+pub fn check_vaults(
+    config_account: &Config,
+    config: &AccountInfo,
+    vault_x: &AccountInfo,
+    vault_y: &AccountInfo,
+) -> ProgramResult {
     check_pda_with_bump(
         &[
             config_account.mint_x.as_ref(),
@@ -139,6 +347,19 @@ pub fn perform_basic_checks(
     Ok(())
 }
 
+#[inline]
+pub fn check_mint_lp(
+    config_account: &Config,
+    config: &AccountInfo,
+    mint_lp: &AccountInfo,
+) -> ProgramResult {
+    check_pda_with_bump(
+        &[config.key.as_ref(), &[config_account.lp_bump]],
+        &crate::ID,
+        mint_lp.key,
+    )
+}
+
 #[inline]
 pub fn deposit<'a>(
     token_program: &Pubkey,
@@ -190,6 +411,29 @@ pub fn mint<'a>(
     )
 }
 
+#[inline]
+pub fn burn<'a>(
+    token_program: &Pubkey,
+    from: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    decimals: u8,
+) -> ProgramResult {
+    invoke(
+        &burn_checked(
+            token_program,
+            from.key,
+            mint.key,
+            authority.key,
+            &[],
+            amount,
+            decimals,
+        )?,
+        &[from.clone(), mint.clone(), authority.clone()],
+    )
+}
+
 #[inline]
 pub fn withdraw<'a>(
     token_program: &Pubkey,
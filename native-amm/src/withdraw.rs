@@ -0,0 +1,59 @@
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+use crate::{
+    instruction::Withdraw,
+    state::Config,
+    utils::{
+        check_mint_lp, check_mint_token_program, check_signer, check_token_program,
+        perform_basic_checks,
+    },
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let withdraw = Withdraw::try_from(data)?;
+    let amount = withdraw.amount;
+    let min_x = withdraw.min_x;
+    let min_y = withdraw.min_y;
+    let expiration = withdraw.expiration;
+
+    let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program, token_program_x, token_program_y, _system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_signer(user)?;
+
+    check_token_program(token_program.key)?;
+    check_mint_token_program(token_program_x.key, mint_x)?;
+    check_mint_token_program(token_program_y.key, mint_y)?;
+
+    let config_data = config.data.borrow();
+    let config_account = Config::load(&config_data)?;
+
+    perform_basic_checks(config_account, expiration, config, vault_x, vault_y)?;
+
+    check_mint_lp(config_account, config, mint_lp)?;
+
+    Config::remove_liquidity(
+        amount,
+        min_x,
+        min_y,
+        config_account,
+        token_program.key,
+        token_program_x.key,
+        token_program_y.key,
+        user_x,
+        user_y,
+        user_lp,
+        vault_x,
+        vault_y,
+        mint_x,
+        mint_y,
+        mint_lp,
+        config,
+        user,
+    )
+}
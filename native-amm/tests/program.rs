@@ -0,0 +1,2880 @@
+//! End-to-end tests that run the program natively inside a `ProgramTest` bank,
+//! with the real SPL Token and Token-2022 programs handling every CPI.
+
+use amm_events::AmmEvent;
+use amm_math::{stable, weighted};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytemuck::{bytes_of, Pod};
+use native_amm::{
+    error::AmmError,
+    instruction::{
+        CreatePool, Deposit, FlashLoan, Initialize, InitializeFactory, Observe, RampAmp, RouteSwap,
+        SetFeeTier, SetFlashFee, SetLocked, Swap, SwapExactOut, Withdraw,
+    },
+    oracle::{Oracle, OBSERVATIONS, OBSERVATION_INTERVAL},
+    state::{Config, CurveType, MINIMUM_LIQUIDITY},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::invoke,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    bpf_loader_upgradeable,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_2022::{
+    extension::{
+        confidential_transfer,
+        default_account_state::instruction::initialize_default_account_state,
+        metadata_pointer,
+        transfer_fee::{instruction::initialize_transfer_fee_config, TransferFee},
+        transfer_hook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use std::sync::{Arc, Once, OnceLock};
+
+const FEE: u16 = 30;
+const SEED: u64 = 42;
+const USER_BALANCE: u64 = 10_000_000;
+const DEPOSIT: u64 = 1_000_000;
+const TRANSFER_FEE: u16 = 100;
+const RECEIVER_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+struct Pool {
+    seed: u64,
+    config: Pubkey,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    mint_lp: Pubkey,
+    oracle: Pubkey,
+    vault_x: Pubkey,
+    vault_y: Pubkey,
+    user_x: Pubkey,
+    user_y: Pubkey,
+    user_lp: Pubkey,
+    token_program_x: Pubkey,
+    token_program_y: Pubkey,
+}
+
+impl Pool {
+    fn new(seed: u64, mint_x: Pubkey, mint_y: Pubkey) -> Self {
+        let (config, _) = Pubkey::find_program_address(
+            &[b"config", seed.to_le_bytes().as_ref()],
+            &native_amm::ID,
+        );
+
+        Self::with_config(config, seed, mint_x, mint_y)
+    }
+
+    /// The factory's pool for the pair at `fee_tier`
+    fn canonical(mint_x: Pubkey, mint_y: Pubkey, fee_tier: u16) -> Self {
+        let (config, _) = Pubkey::find_program_address(
+            &[
+                b"config",
+                mint_x.as_ref(),
+                mint_y.as_ref(),
+                fee_tier.to_le_bytes().as_ref(),
+            ],
+            &native_amm::ID,
+        );
+
+        Self::with_config(config, 0, mint_x, mint_y)
+    }
+
+    fn with_config(config: Pubkey, seed: u64, mint_x: Pubkey, mint_y: Pubkey) -> Self {
+        let (vault_x, _) =
+            Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &native_amm::ID);
+        let (vault_y, _) =
+            Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &native_amm::ID);
+        let (mint_lp, _) = Pubkey::find_program_address(&[config.as_ref()], &native_amm::ID);
+        let (oracle, _) =
+            Pubkey::find_program_address(&[b"oracle", config.as_ref()], &native_amm::ID);
+
+        Self {
+            seed,
+            config,
+            mint_x,
+            mint_y,
+            mint_lp,
+            oracle,
+            vault_x,
+            vault_y,
+            user_x: Pubkey::default(),
+            user_y: Pubkey::default(),
+            user_lp: Pubkey::default(),
+            token_program_x: spl_token::ID,
+            token_program_y: spl_token::ID,
+        }
+    }
+
+    fn initialize_ix(&self, payer: &Pubkey) -> Instruction {
+        self.initialize_curve_ix(payer, CurveType::ConstantProduct, weighted::EVEN_WEIGHT, 0)
+    }
+
+    fn initialize_curve_ix(
+        &self,
+        payer: &Pubkey,
+        curve: CurveType,
+        weight_x: u16,
+        amp: u64,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(
+                0,
+                &Initialize {
+                    seed: self.seed,
+                    fee: FEE,
+                    authority: *payer,
+                    curve: curve as u8,
+                    padding: [0; 3],
+                    weight_x,
+                    amp,
+                },
+            ),
+            self.initialize_accounts(payer),
+        )
+    }
+
+    /// Creates the pool as the factory's constant-product pool at `fee_tier`
+    fn create_pool_ix(&self, payer: &Pubkey, fee_tier: u16) -> Instruction {
+        let mut accounts = self.initialize_accounts(payer);
+        accounts.push(AccountMeta::new_readonly(factory_address(), false));
+
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(
+                16,
+                &CreatePool {
+                    fee_tier,
+                    weight_x: weighted::EVEN_WEIGHT,
+                    curve: CurveType::ConstantProduct as u8,
+                    padding: [0; 3],
+                    amp: 0,
+                },
+            ),
+            accounts,
+        )
+    }
+
+    fn initialize_accounts(&self, payer: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(self.mint_x, false),
+            AccountMeta::new_readonly(self.mint_y, false),
+            AccountMeta::new(self.mint_lp, false),
+            AccountMeta::new(self.vault_x, false),
+            AccountMeta::new(self.vault_y, false),
+            AccountMeta::new(self.config, false),
+            AccountMeta::new(self.oracle, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(self.token_program_x, false),
+            AccountMeta::new_readonly(self.token_program_y, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    }
+
+    fn deposit_ix(&self, user: &Pubkey, deposit: Deposit) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(1, &deposit),
+            vec![
+                AccountMeta::new(*user, true),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new_readonly(self.mint_y, false),
+                AccountMeta::new(self.mint_lp, false),
+                AccountMeta::new(self.user_x, false),
+                AccountMeta::new(self.user_y, false),
+                AccountMeta::new(self.user_lp, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new(self.vault_y, false),
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new_readonly(self.token_program_y, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    fn withdraw_ix(&self, user: &Pubkey, withdraw: Withdraw) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(2, &withdraw),
+            vec![
+                AccountMeta::new(*user, true),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new_readonly(self.mint_y, false),
+                AccountMeta::new(self.mint_lp, false),
+                AccountMeta::new(self.user_x, false),
+                AccountMeta::new(self.user_y, false),
+                AccountMeta::new(self.user_lp, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new(self.vault_y, false),
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new_readonly(self.token_program_y, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    fn swap_ix(&self, user: &Pubkey, swap: Swap) -> Instruction {
+        Instruction::new_with_bytes(native_amm::ID, &data(3, &swap), self.swap_accounts(user))
+    }
+
+    fn swap_exact_out_ix(&self, user: &Pubkey, swap: SwapExactOut) -> Instruction {
+        Instruction::new_with_bytes(native_amm::ID, &data(4, &swap), self.swap_accounts(user))
+    }
+
+    fn swap_accounts(&self, user: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(self.mint_x, false),
+            AccountMeta::new_readonly(self.mint_y, false),
+            AccountMeta::new_readonly(self.mint_lp, false),
+            AccountMeta::new(self.vault_x, false),
+            AccountMeta::new(self.vault_y, false),
+            AccountMeta::new(self.user_x, false),
+            AccountMeta::new(self.user_y, false),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new(self.oracle, false),
+            AccountMeta::new_readonly(self.token_program_x, false),
+            AccountMeta::new_readonly(self.token_program_y, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    }
+
+    fn ramp_amp_ix(&self, authority: &Pubkey, target_amp: u64, ramp_end: i64) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(
+                11,
+                &RampAmp {
+                    target_amp,
+                    ramp_end,
+                },
+            ),
+            vec![
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(self.config, false),
+            ],
+        )
+    }
+
+    fn observe_ix(&self, window: u32) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(9, &Observe { window }),
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new_readonly(self.oracle, false),
+                AccountMeta::new_readonly(self.vault_x, false),
+                AccountMeta::new_readonly(self.vault_y, false),
+            ],
+        )
+    }
+
+    fn init_oracle_ix(&self, payer: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &[17],
+            vec![
+                AccountMeta::new(*payer, true),
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.oracle, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    fn set_locked_ix(&self, authority: &Pubkey, locked: bool) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(
+                6,
+                &SetLocked {
+                    locked: locked as u8,
+                },
+            ),
+            vec![
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(self.config, false),
+            ],
+        )
+    }
+
+    fn set_flash_fee_ix(&self, authority: &Pubkey, flash_fee: u16) -> Instruction {
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data(13, &SetFlashFee { flash_fee }),
+            vec![
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(self.config, false),
+            ],
+        )
+    }
+
+    /// Lends `amount` of x into `user_x`, from which `receive_flash_loan`
+    /// pays back `repay`
+    fn flash_loan_ix(&self, user: &Pubkey, amount: u64, repay: u64) -> Instruction {
+        let mut data = data(12, &FlashLoan { amount });
+        data.extend_from_slice(&repay.to_le_bytes());
+
+        Instruction::new_with_bytes(
+            native_amm::ID,
+            &data,
+            vec![
+                AccountMeta::new(self.config, false),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new(self.user_x, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new_readonly(RECEIVER_ID, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new(self.user_x, false),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new_readonly(*user, true),
+            ],
+        )
+    }
+}
+
+/// Sells `amount` of `user_in`'s token through `pools` in order, into `user_out`
+fn route_swap_ix(
+    user: &Pubkey,
+    user_in: &Pubkey,
+    user_out: &Pubkey,
+    pools: &[&Pool],
+    amount: u64,
+    min: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*user, true),
+        AccountMeta::new(*user_in, false),
+        AccountMeta::new(*user_out, false),
+    ];
+    for pool in pools {
+        accounts.extend([
+            AccountMeta::new_readonly(pool.config, false),
+            AccountMeta::new(pool.oracle, false),
+            AccountMeta::new_readonly(pool.mint_x, false),
+            AccountMeta::new_readonly(pool.mint_y, false),
+            AccountMeta::new_readonly(pool.mint_lp, false),
+            AccountMeta::new(pool.vault_x, false),
+            AccountMeta::new(pool.vault_y, false),
+            AccountMeta::new_readonly(pool.token_program_x, false),
+            AccountMeta::new_readonly(pool.token_program_y, false),
+        ]);
+    }
+
+    Instruction::new_with_bytes(
+        native_amm::ID,
+        &data(
+            10,
+            &RouteSwap {
+                amount,
+                min,
+                expiration: i64::MAX,
+            },
+        ),
+        accounts,
+    )
+}
+
+fn migrate_config_ix(config: &Pubkey, payer: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        native_amm::ID,
+        &[18],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*config, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+fn factory_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"factory"], &native_amm::ID).0
+}
+
+fn initialize_factory_ix(upgrade_authority: &Pubkey, admin: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        native_amm::ID,
+        &data(14, &InitializeFactory { admin: *admin }),
+        vec![
+            AccountMeta::new(*upgrade_authority, true),
+            AccountMeta::new(factory_address(), false),
+            AccountMeta::new_readonly(
+                bpf_loader_upgradeable::get_program_data_address(&native_amm::ID),
+                false,
+            ),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+fn set_fee_tier_ix(admin: &Pubkey, fee: u16, enabled: bool) -> Instruction {
+    Instruction::new_with_bytes(
+        native_amm::ID,
+        &data(
+            15,
+            &SetFeeTier {
+                fee,
+                enabled: enabled as u8,
+                padding: [0; 1],
+            },
+        ),
+        vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(factory_address(), false),
+        ],
+    )
+}
+
+fn data<T: Pod>(tag: u8, payload: &T) -> Vec<u8> {
+    [&[tag], bytes_of(payload)].concat()
+}
+
+fn deposit(amount: u64, max_x: u64, max_y: u64) -> Deposit {
+    Deposit {
+        amount,
+        max_x,
+        max_y,
+        expiration: i64::MAX,
+    }
+}
+
+fn withdraw(amount: u64, min_x: u64, min_y: u64) -> Withdraw {
+    Withdraw {
+        amount,
+        min_x,
+        min_y,
+        expiration: i64::MAX,
+    }
+}
+
+fn swap(amount: u64, min: u64, from_x: bool) -> Swap {
+    Swap {
+        amount,
+        min,
+        expiration: i64::MAX,
+        from_x: from_x as u8,
+        padding: [0; 7],
+    }
+}
+
+fn swap_exact_out(amount_out: u64, max_in: u64, from_x: bool) -> SwapExactOut {
+    SwapExactOut {
+        amount_out,
+        max_in,
+        expiration: i64::MAX,
+        from_x: from_x as u8,
+        padding: [0; 7],
+    }
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.banks_client.get_latest_blockhash().await?;
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+
+    context.banks_client.process_transaction(transaction).await
+}
+
+/// Processes the instructions and decodes the events the program logged
+async fn process_events(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+) -> Vec<AmmEvent> {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        blockhash,
+    );
+
+    // The logs come from a simulation, which takes no account locks. Processing
+    // with metadata runs on the bank directly and can find the previous
+    // transaction still holding them
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction.clone())
+        .await
+        .unwrap();
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // Put back the prefix `LogDataStubs` had to do without
+    let logs: Vec<String> = simulation
+        .simulation_details
+        .unwrap()
+        .logs
+        .into_iter()
+        .map(|log| match log.strip_prefix("Program log: data: ") {
+            Some(data) => format!("Program data: {data}"),
+            None => log,
+        })
+        .collect();
+
+    amm_events::parse_logs(&logs, &native_amm::ID).unwrap()
+}
+
+fn assert_amm_error(result: Result<(), BanksClientError>, expected: AmmError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32)),
+    );
+}
+
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::ID,
+                &mint.pubkey(),
+                &payer,
+                None,
+                6,
+            )
+            .unwrap(),
+        ],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    mint.pubkey()
+}
+
+/// Creates a Token-2022 mint with `extensions`, which the instructions from
+/// `initialize_extensions` set up before the mint itself is initialized
+async fn create_extension_mint(
+    context: &mut ProgramTestContext,
+    extensions: &[ExtensionType],
+    initialize_extensions: impl Fn(&Pubkey) -> Vec<Instruction>,
+) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    let space = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+
+    let mut instructions = vec![system_instruction::create_account(
+        &payer,
+        &mint.pubkey(),
+        rent.minimum_balance(space),
+        space as u64,
+        &spl_token_2022::ID,
+    )];
+    instructions.extend(initialize_extensions(&mint.pubkey()));
+    instructions.push(
+        spl_token_2022::instruction::initialize_mint2(
+            &spl_token_2022::ID,
+            &mint.pubkey(),
+            &payer,
+            Some(&payer),
+            6,
+        )
+        .unwrap(),
+    );
+
+    process(context, &instructions, &[&mint]).await.unwrap();
+
+    mint.pubkey()
+}
+
+/// Creates a Token-2022 mint that withholds `basis_points` of every transfer
+async fn create_transfer_fee_mint(context: &mut ProgramTestContext, basis_points: u16) -> Pubkey {
+    create_extension_mint(context, &[ExtensionType::TransferFeeConfig], |mint| {
+        vec![initialize_transfer_fee_config(
+            &spl_token_2022::ID,
+            mint,
+            None,
+            None,
+            basis_points,
+            u64::MAX,
+        )
+        .unwrap()]
+    })
+    .await
+}
+
+/// The fee a mint from `create_transfer_fee_mint` charges
+fn transfer_fee(basis_points: u16) -> TransferFee {
+    TransferFee {
+        epoch: 0.into(),
+        maximum_fee: u64::MAX.into(),
+        transfer_fee_basis_points: basis_points.into(),
+    }
+}
+
+/// Creates a token account under whichever token program owns `mint`, with
+/// room for the extensions the mint requires of it
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+
+    let mint_account = context
+        .banks_client
+        .get_account(*mint)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_program = mint_account.owner;
+    let mint_extensions = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .unwrap()
+        .get_extension_types()
+        .unwrap();
+    let space = ExtensionType::try_calculate_account_len::<TokenAccount>(
+        &ExtensionType::get_required_init_account_extensions(&mint_extensions),
+    )
+    .unwrap();
+
+    let mut instructions = vec![
+        system_instruction::create_account(
+            &payer,
+            &account.pubkey(),
+            rent.minimum_balance(space),
+            space as u64,
+            &token_program,
+        ),
+        spl_token_2022::instruction::initialize_account3(
+            &token_program,
+            &account.pubkey(),
+            mint,
+            owner,
+        )
+        .unwrap(),
+    ];
+    if amount > 0 {
+        instructions.push(
+            spl_token_2022::instruction::mint_to(
+                &token_program,
+                mint,
+                &account.pubkey(),
+                &payer,
+                &[],
+                amount,
+            )
+            .unwrap(),
+        );
+    }
+
+    process(context, &instructions, &[&account]).await.unwrap();
+
+    account.pubkey()
+}
+
+async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    StateWithExtensions::<TokenAccount>::unpack(&account.data)
+        .unwrap()
+        .base
+        .amount
+}
+
+async fn lp_supply(context: &mut ProgramTestContext, mint: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*mint)
+        .await
+        .unwrap()
+        .unwrap();
+
+    Mint::unpack(&account.data).unwrap().supply
+}
+
+async fn config(context: &mut ProgramTestContext, config: &Pubkey) -> Config {
+    let account = context
+        .banks_client
+        .get_account(*config)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(account.owner, native_amm::ID);
+
+    *Config::load(&account.data).unwrap()
+}
+
+/// Gives the program a program data account naming `upgrade_authority`, which
+/// a program loaded with `processor!` does not have
+fn set_upgrade_authority(context: &mut ProgramTestContext, upgrade_authority: &Pubkey) {
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+
+    context.set_account(
+        &bpf_loader_upgradeable::get_program_data_address(&native_amm::ID),
+        &Account {
+            lamports: 1_000_000_000,
+            data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+}
+
+/// Moves the bank's clock forward by `seconds`
+async fn warp(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+/// Simulates `observe_ix` and decodes its return data. Observing writes
+/// nothing, and a simulation does not contend for the accounts the previous
+/// transaction may still hold
+async fn observe(context: &mut ProgramTestContext, pool: &Pool, window: u32) -> (u128, u128) {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[pool.observe_ix(window)],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        blockhash,
+    );
+
+    let result = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap().unwrap();
+
+    let data = result.simulation_details.unwrap().return_data.unwrap().data;
+    (
+        u128::from_le_bytes(data[..16].try_into().unwrap()),
+        u128::from_le_bytes(data[16..].try_into().unwrap()),
+    )
+}
+
+/// A flash loan receiver that transfers the amount in its instruction data
+/// from a token account back to the vault
+fn receive_flash_loan(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let [token_program, source, mint, vault, owner] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let repay = u64::from_le_bytes(instruction_data.try_into().unwrap());
+
+    invoke(
+        &spl_token::instruction::transfer_checked(
+            token_program.key,
+            source.key,
+            mint.key,
+            vault.key,
+            owner.key,
+            &[],
+            repay,
+            6,
+        )?,
+        &[source.clone(), mint.clone(), vault.clone(), owner.clone()],
+    )
+}
+
+/// `ProgramTest` runs the program natively, where its syscall stubs print
+/// `sol_log_data` to stdout rather than to the transaction's logs. These wrap
+/// them to log the data as `data: <base64>` instead, which `process_events`
+/// turns back into the `Program data:` line the runtime would have logged
+struct LogDataStubs(Arc<OnceLock<Box<dyn SyscallStubs>>>);
+
+impl LogDataStubs {
+    /// Installs the stubs over the ones `ProgramTest` installs when the first
+    /// test starts. Every test starts before it sends a transaction, so none
+    /// can be running while they are swapped
+    fn install() {
+        static INSTALL: Once = Once::new();
+
+        INSTALL.call_once(|| {
+            let inner = Arc::new(OnceLock::new());
+            let replaced = set_syscall_stubs(Box::new(LogDataStubs(inner.clone())));
+            let _ = inner.set(replaced);
+        });
+    }
+
+    fn inner(&self) -> &dyn SyscallStubs {
+        self.0.get().unwrap().as_ref()
+    }
+}
+
+impl SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        self.inner().sol_log(message)
+    }
+
+    fn sol_log_compute_units(&self) {
+        self.inner().sol_log_compute_units()
+    }
+
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.inner().sol_remaining_compute_units()
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.inner()
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_sysvar(
+        &self,
+        sysvar_id_addr: *const u8,
+        var_addr: *mut u8,
+        offset: u64,
+        length: u64,
+    ) -> u64 {
+        self.inner()
+            .sol_get_sysvar(sysvar_id_addr, var_addr, offset, length)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.inner().sol_get_last_restart_slot(var_addr)
+    }
+
+    fn sol_get_epoch_stake(&self, vote_address: *const u8) -> u64 {
+        self.inner().sol_get_epoch_stake(vote_address)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.inner().sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.inner().sol_set_return_data(data)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let words: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        self.inner().sol_log(&format!("data: {}", words.join(" ")))
+    }
+
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.inner().sol_get_processed_sibling_instruction(index)
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        self.inner().sol_get_stack_height()
+    }
+}
+
+async fn start() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new(
+        "native_amm",
+        native_amm::ID,
+        processor!(native_amm::process_instruction),
+    );
+    program_test.add_program(
+        "flash_receiver",
+        RECEIVER_ID,
+        processor!(receive_flash_loan),
+    );
+    program_test.prefer_bpf(false);
+
+    let context = program_test.start_with_context().await;
+    LogDataStubs::install();
+
+    context
+}
+
+/// Creates the mints and the user's token accounts, without initializing the pool
+async fn setup(context: &mut ProgramTestContext) -> Pool {
+    let mint_x = create_mint(context).await;
+    let mint_y = create_mint(context).await;
+    let user = context.payer.pubkey();
+
+    let mut pool = Pool::new(SEED, mint_x, mint_y);
+    pool.user_x = create_token_account(context, &mint_x, &user, USER_BALANCE).await;
+    pool.user_y = create_token_account(context, &mint_y, &user, USER_BALANCE).await;
+
+    pool
+}
+
+/// Initializes the pool and seeds it with `DEPOSIT` of each token
+async fn setup_pool(context: &mut ProgramTestContext) -> Pool {
+    let mut pool = setup(context).await;
+    let user = context.payer.pubkey();
+
+    process(context, &[pool.initialize_ix(&user)], &[])
+        .await
+        .unwrap();
+
+    pool.user_lp = create_token_account(context, &pool.mint_lp, &user, 0).await;
+
+    process(
+        context,
+        &[pool.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    pool
+}
+
+/// Initializes an empty pool whose x mint withholds `TRANSFER_FEE` basis
+/// points of every transfer
+async fn setup_transfer_fee_pool(context: &mut ProgramTestContext) -> Pool {
+    let mint_x = create_transfer_fee_mint(context, TRANSFER_FEE).await;
+    let mint_y = create_mint(context).await;
+    let user = context.payer.pubkey();
+
+    let mut pool = Pool::new(SEED, mint_x, mint_y);
+    pool.token_program_x = spl_token_2022::ID;
+    pool.user_x = create_token_account(context, &mint_x, &user, USER_BALANCE).await;
+    pool.user_y = create_token_account(context, &mint_y, &user, USER_BALANCE).await;
+
+    process(context, &[pool.initialize_ix(&user)], &[])
+        .await
+        .unwrap();
+
+    pool.user_lp = create_token_account(context, &pool.mint_lp, &user, 0).await;
+
+    pool
+}
+
+#[tokio::test]
+async fn initialize_deposit_and_swap() {
+    let mut context = start().await;
+    let mut pool = setup(&mut context).await;
+    let user = context.payer.pubkey();
+
+    process(&mut context, &[pool.initialize_ix(&user)], &[])
+        .await
+        .unwrap();
+
+    let config_account = config(&mut context, &pool.config).await;
+    assert_eq!(config_account.seed, SEED);
+    assert_eq!(config_account.authority, user);
+    assert_eq!(config_account.pending_authority, Pubkey::default());
+    assert_eq!(config_account.mint_x, pool.mint_x);
+    assert_eq!(config_account.mint_y, pool.mint_y);
+    assert_eq!(config_account.fee, FEE);
+    assert_eq!(config_account.locked, 0);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, 0);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, 0);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, 0);
+
+    // The first deposit takes both maxima and mints sqrt(x * y) minus the locked minimum
+    pool.user_lp = create_token_account(&mut context, &pool.mint_lp, &user, 0).await;
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let lp_amount = DEPOSIT - MINIMUM_LIQUIDITY;
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, DEPOSIT);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, DEPOSIT);
+    assert_eq!(token_balance(&mut context, &pool.user_lp).await, lp_amount);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, lp_amount);
+
+    // Later deposits are priced pro rata against the locked minimum as well
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(DEPOSIT, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        2 * DEPOSIT
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_y).await,
+        2 * DEPOSIT
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_lp).await,
+        lp_amount + DEPOSIT
+    );
+
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(amount_in, 2 * DEPOSIT, 2 * DEPOSIT, FEE).unwrap();
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        2 * DEPOSIT + amount_in
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_y).await,
+        2 * DEPOSIT - amount_out
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await,
+        USER_BALANCE - 2 * DEPOSIT - amount_in
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_y).await,
+        USER_BALANCE - 2 * DEPOSIT + amount_out
+    );
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_vault_that_is_not_the_pda() {
+    let mut context = start().await;
+    let mut pool = setup(&mut context).await;
+    let user = context.payer.pubkey();
+
+    pool.vault_x = Pubkey::new_unique();
+
+    assert_amm_error(
+        process(&mut context, &[pool.initialize_ix(&user)], &[]).await,
+        AmmError::InvalidPda,
+    );
+}
+
+/// Initializes a pool whose x mint is a Token-2022 mint with `extensions`
+async fn initialize_extension_pool(
+    context: &mut ProgramTestContext,
+    extensions: &[ExtensionType],
+    initialize_extensions: impl Fn(&Pubkey) -> Vec<Instruction>,
+) -> Result<(), BanksClientError> {
+    let mint_x = create_extension_mint(context, extensions, initialize_extensions).await;
+    let mint_y = create_mint(context).await;
+    let user = context.payer.pubkey();
+
+    let mut pool = Pool::new(SEED, mint_x, mint_y);
+    pool.token_program_x = spl_token_2022::ID;
+
+    process(context, &[pool.initialize_ix(&user)], &[]).await
+}
+
+#[tokio::test]
+async fn initialize_accepts_metadata_and_unfrozen_accounts() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    initialize_extension_pool(
+        &mut context,
+        &[
+            ExtensionType::MetadataPointer,
+            ExtensionType::DefaultAccountState,
+        ],
+        |mint| {
+            vec![
+                metadata_pointer::instruction::initialize(
+                    &spl_token_2022::ID,
+                    mint,
+                    Some(payer),
+                    Some(*mint),
+                )
+                .unwrap(),
+                initialize_default_account_state(
+                    &spl_token_2022::ID,
+                    mint,
+                    &AccountState::Initialized,
+                )
+                .unwrap(),
+            ]
+        },
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_permanent_delegate_mint() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    assert_amm_error(
+        initialize_extension_pool(&mut context, &[ExtensionType::PermanentDelegate], |mint| {
+            vec![spl_token_2022::instruction::initialize_permanent_delegate(
+                &spl_token_2022::ID,
+                mint,
+                &payer,
+            )
+            .unwrap()]
+        })
+        .await,
+        AmmError::UnsupportedMintExtension,
+    );
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_transfer_hook_mint() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    assert_amm_error(
+        initialize_extension_pool(&mut context, &[ExtensionType::TransferHook], |mint| {
+            vec![transfer_hook::instruction::initialize(
+                &spl_token_2022::ID,
+                mint,
+                Some(payer),
+                Some(RECEIVER_ID),
+            )
+            .unwrap()]
+        })
+        .await,
+        AmmError::UnsupportedMintExtension,
+    );
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_non_transferable_mint() {
+    let mut context = start().await;
+
+    assert_amm_error(
+        initialize_extension_pool(&mut context, &[ExtensionType::NonTransferable], |mint| {
+            vec![
+                spl_token_2022::instruction::initialize_non_transferable_mint(
+                    &spl_token_2022::ID,
+                    mint,
+                )
+                .unwrap(),
+            ]
+        })
+        .await,
+        AmmError::UnsupportedMintExtension,
+    );
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_mint_whose_accounts_start_frozen() {
+    let mut context = start().await;
+
+    assert_amm_error(
+        initialize_extension_pool(
+            &mut context,
+            &[ExtensionType::DefaultAccountState],
+            |mint| {
+                vec![initialize_default_account_state(
+                    &spl_token_2022::ID,
+                    mint,
+                    &AccountState::Frozen,
+                )
+                .unwrap()]
+            },
+        )
+        .await,
+        AmmError::UnsupportedMintExtension,
+    );
+}
+
+#[tokio::test]
+async fn initialize_rejects_a_confidential_transfer_mint() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    assert_amm_error(
+        initialize_extension_pool(
+            &mut context,
+            &[ExtensionType::ConfidentialTransferMint],
+            |mint| {
+                vec![confidential_transfer::instruction::initialize_mint(
+                    &spl_token_2022::ID,
+                    mint,
+                    Some(payer),
+                    true,
+                    None,
+                )
+                .unwrap()]
+            },
+        )
+        .await,
+        AmmError::UnsupportedMintExtension,
+    );
+}
+
+#[tokio::test]
+async fn swap_rejects_a_vault_that_is_not_the_pda() {
+    let mut context = start().await;
+    let mut pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    pool.vault_y = pool.user_y;
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(1_000, 0, true))],
+            &[],
+        )
+        .await,
+        AmmError::InvalidPda,
+    );
+}
+
+#[tokio::test]
+async fn deposit_rejects_the_wrong_token_program() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    let mut instruction = pool.deposit_ix(&user, deposit(1_000, DEPOSIT, DEPOSIT));
+    instruction.accounts[11].pubkey = spl_token_2022::ID;
+
+    assert_amm_error(
+        process(&mut context, &[instruction], &[]).await,
+        AmmError::InvalidTokenProgram,
+    );
+}
+
+#[tokio::test]
+async fn swap_rejects_a_locked_pool() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    process(&mut context, &[pool.set_locked_ix(&user, true)], &[])
+        .await
+        .unwrap();
+    assert_eq!(config(&mut context, &pool.config).await.locked, 1);
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(1_000, 0, true))],
+            &[],
+        )
+        .await,
+        AmmError::PoolLocked,
+    );
+}
+
+#[tokio::test]
+async fn swap_rejects_an_expired_deadline() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    let mut expired = swap(1_000, 0, true);
+    expired.expiration = 0;
+
+    assert_amm_error(
+        process(&mut context, &[pool.swap_ix(&user, expired)], &[]).await,
+        AmmError::Expired,
+    );
+}
+
+#[tokio::test]
+async fn swap_rejects_slippage() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap();
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(amount_in, amount_out + 1, true))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+}
+
+#[tokio::test]
+async fn swap_exact_out_pays_the_exact_output_in_both_directions() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    let amount_out = 10_000;
+    let (mut reserve_x, mut reserve_y) = (DEPOSIT, DEPOSIT);
+
+    for from_x in [true, false] {
+        let (reserve_in, reserve_out) = if from_x {
+            (reserve_x, reserve_y)
+        } else {
+            (reserve_y, reserve_x)
+        };
+        let amount_in = amm_math::swap_exact_out(amount_out, reserve_in, reserve_out, FEE).unwrap();
+
+        assert_amm_error(
+            process(
+                &mut context,
+                &[
+                    pool.swap_exact_out_ix(
+                        &user,
+                        swap_exact_out(amount_out, amount_in - 1, from_x),
+                    ),
+                ],
+                &[],
+            )
+            .await,
+            AmmError::SlippageExceeded,
+        );
+
+        let user_x_before = token_balance(&mut context, &pool.user_x).await;
+        let user_y_before = token_balance(&mut context, &pool.user_y).await;
+        process(
+            &mut context,
+            &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, amount_in, from_x))],
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let user_x = token_balance(&mut context, &pool.user_x).await;
+        let user_y = token_balance(&mut context, &pool.user_y).await;
+        if from_x {
+            assert_eq!(user_x_before - user_x, amount_in);
+            assert_eq!(user_y - user_y_before, amount_out);
+            (reserve_x, reserve_y) = (reserve_x + amount_in, reserve_y - amount_out);
+        } else {
+            assert_eq!(user_y_before - user_y, amount_in);
+            assert_eq!(user_x - user_x_before, amount_out);
+            (reserve_x, reserve_y) = (reserve_x - amount_out, reserve_y + amount_in);
+        }
+
+        assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+        assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+    }
+}
+
+#[tokio::test]
+async fn swap_exact_out_prices_stable_and_weighted_pools() {
+    let mut context = start().await;
+    let user = context.payer.pubkey();
+    let amount_out = 10_000;
+
+    for (seed, curve, weight_x, amp, reserve_x) in [
+        (SEED, CurveType::Stable, weighted::EVEN_WEIGHT, 100, DEPOSIT),
+        (SEED + 1, CurveType::ConstantProduct, 8_000, 0, DEPOSIT * 4),
+    ] {
+        let mints = setup(&mut context).await;
+        let mut pool = Pool {
+            user_x: mints.user_x,
+            user_y: mints.user_y,
+            ..Pool::new(seed, mints.mint_x, mints.mint_y)
+        };
+
+        process(
+            &mut context,
+            &[pool.initialize_curve_ix(&user, curve, weight_x, amp)],
+            &[],
+        )
+        .await
+        .unwrap();
+        pool.user_lp = create_token_account(&mut context, &pool.mint_lp, &user, 0).await;
+        process(
+            &mut context,
+            &[pool.deposit_ix(&user, deposit(0, reserve_x, DEPOSIT))],
+            &[],
+        )
+        .await
+        .unwrap();
+
+        // Each direction is priced by the pool's own curve, not the constant product
+        let (mut reserve_x, mut reserve_y) = (reserve_x, DEPOSIT);
+        for from_x in [true, false] {
+            let (reserve_in, reserve_out, weight_in) = if from_x {
+                (reserve_x, reserve_y, weight_x)
+            } else {
+                (
+                    reserve_y,
+                    reserve_x,
+                    weighted::WEIGHT_DENOMINATOR - weight_x,
+                )
+            };
+            let amount_in = match curve {
+                CurveType::Stable => {
+                    stable::swap_exact_out(amount_out, reserve_in, reserve_out, FEE, amp)
+                }
+                CurveType::ConstantProduct => {
+                    weighted::swap_exact_out(amount_out, reserve_in, reserve_out, FEE, weight_in)
+                }
+            }
+            .unwrap();
+            assert_ne!(
+                amount_in,
+                amm_math::swap_exact_out(amount_out, reserve_in, reserve_out, FEE).unwrap()
+            );
+
+            assert_amm_error(
+                process(
+                    &mut context,
+                    &[pool.swap_exact_out_ix(
+                        &user,
+                        swap_exact_out(amount_out, amount_in - 1, from_x),
+                    )],
+                    &[],
+                )
+                .await,
+                AmmError::SlippageExceeded,
+            );
+            process(
+                &mut context,
+                &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, amount_in, from_x))],
+                &[],
+            )
+            .await
+            .unwrap();
+
+            if from_x {
+                (reserve_x, reserve_y) = (reserve_x + amount_in, reserve_y - amount_out);
+            } else {
+                (reserve_x, reserve_y) = (reserve_x - amount_out, reserve_y + amount_in);
+            }
+            assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+            assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+        }
+    }
+}
+
+#[tokio::test]
+async fn deposit_rejects_slippage() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    // Minting DEPOSIT LP costs exactly DEPOSIT of each token at a 1:1 price
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.deposit_ix(&user, deposit(DEPOSIT, DEPOSIT - 1, DEPOSIT))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+}
+
+#[tokio::test]
+async fn withdraw_pays_out_a_pro_rata_share_of_both_vaults() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    // Tilt the reserves so the two shares differ
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap();
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let (reserve_x, reserve_y) = (DEPOSIT + amount_in, DEPOSIT - amount_out);
+    let lp_before = lp_supply(&mut context, &pool.mint_lp).await;
+    let amount = lp_before / 4;
+
+    // The locked minimum keeps its share of the vaults
+    let amount_x =
+        amm_math::withdraw_share(amount, reserve_x, lp_before + MINIMUM_LIQUIDITY).unwrap();
+    let amount_y =
+        amm_math::withdraw_share(amount, reserve_y, lp_before + MINIMUM_LIQUIDITY).unwrap();
+    assert_ne!(amount_x, amount_y);
+
+    for slipped in [
+        withdraw(amount, amount_x + 1, amount_y),
+        withdraw(amount, amount_x, amount_y + 1),
+    ] {
+        assert_amm_error(
+            process(&mut context, &[pool.withdraw_ix(&user, slipped)], &[]).await,
+            AmmError::SlippageExceeded,
+        );
+    }
+
+    let mut expired = withdraw(amount, 0, 0);
+    expired.expiration = 0;
+    assert_amm_error(
+        process(&mut context, &[pool.withdraw_ix(&user, expired)], &[]).await,
+        AmmError::Expired,
+    );
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.withdraw_ix(&user, withdraw(lp_before + 1, 0, 0))],
+            &[],
+        )
+        .await,
+        AmmError::InvalidAmount,
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(
+        &mut context,
+        &[pool.withdraw_ix(&user, withdraw(amount, amount_x, amount_y))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await - user_x_before,
+        amount_x
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_y).await - user_y_before,
+        amount_y
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        reserve_x - amount_x
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_y).await,
+        reserve_y - amount_y
+    );
+    assert_eq!(
+        lp_supply(&mut context, &pool.mint_lp).await,
+        lp_before - amount
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_lp).await,
+        lp_before - amount
+    );
+}
+
+#[tokio::test]
+async fn transfer_fee_mint_deposits_and_withdraws_what_arrives() {
+    let mut context = start().await;
+    let pool = setup_transfer_fee_pool(&mut context).await;
+    let user = context.payer.pubkey();
+    let fee = transfer_fee(TRANSFER_FEE);
+
+    // The first deposit mints LP on what the vault receives
+    let received_x = fee.calculate_post_fee_amount(DEPOSIT).unwrap();
+    assert!(received_x < DEPOSIT);
+    let initial_lp = amm_math::initial_liquidity(received_x, DEPOSIT) - MINIMUM_LIQUIDITY;
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.deposit_ix(&user, deposit(initial_lp + 1, DEPOSIT, DEPOSIT))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(initial_lp, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, received_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, DEPOSIT);
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, initial_lp);
+
+    // Later deposits gross x up so the vault still receives its pro-rata share,
+    // and max_x caps what leaves the user
+    let amount = initial_lp / 2;
+    let total = initial_lp + MINIMUM_LIQUIDITY;
+    let share_x = amm_math::deposit_share(amount, received_x, total).unwrap();
+    let amount_y = amm_math::deposit_share(amount, DEPOSIT, total).unwrap();
+    let amount_x = fee.calculate_pre_fee_amount(share_x).unwrap();
+    assert!(amount_x > share_x);
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.deposit_ix(&user, deposit(amount, amount_x - 1, amount_y))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(amount, amount_x, amount_y))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let reserve_x = token_balance(&mut context, &pool.vault_x).await;
+    assert_eq!(
+        user_x_before - token_balance(&mut context, &pool.user_x).await,
+        amount_x
+    );
+    assert_eq!(
+        reserve_x,
+        received_x + fee.calculate_post_fee_amount(amount_x).unwrap()
+    );
+    assert!(reserve_x >= received_x + share_x);
+
+    // Withdrawals hold min_x to what the user receives after the fee
+    let reserve_y = DEPOSIT + amount_y;
+    let lp_before = lp_supply(&mut context, &pool.mint_lp).await;
+    let amount = lp_before / 4;
+    let paid_x =
+        amm_math::withdraw_share(amount, reserve_x, lp_before + MINIMUM_LIQUIDITY).unwrap();
+    let amount_y =
+        amm_math::withdraw_share(amount, reserve_y, lp_before + MINIMUM_LIQUIDITY).unwrap();
+    let amount_x = fee.calculate_post_fee_amount(paid_x).unwrap();
+    assert!(amount_x < paid_x);
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.withdraw_ix(&user, withdraw(amount, amount_x + 1, amount_y))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.withdraw_ix(&user, withdraw(amount, amount_x, amount_y))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await - user_x_before,
+        amount_x
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        reserve_x - paid_x
+    );
+}
+
+#[tokio::test]
+async fn transfer_fee_mint_swaps_on_what_arrives_in_both_directions() {
+    let mut context = start().await;
+    let pool = setup_transfer_fee_pool(&mut context).await;
+    let user = context.payer.pubkey();
+    let fee = transfer_fee(TRANSFER_FEE);
+
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+    let (mut reserve_x, mut reserve_y) = (fee.calculate_post_fee_amount(DEPOSIT).unwrap(), DEPOSIT);
+
+    // Selling x prices the swap on what reaches the vault
+    let amount_in = 10_000;
+    let received = fee.calculate_post_fee_amount(amount_in).unwrap();
+    let amount_out = amm_math::swap_exact_in(received, reserve_x, reserve_y, FEE).unwrap();
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(amount_in, amount_out + 1, true))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    (reserve_x, reserve_y) = (reserve_x + received, reserve_y - amount_out);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+
+    // Buying x holds min to what the user receives after the fee
+    let paid = amm_math::swap_exact_in(amount_in, reserve_y, reserve_x, FEE).unwrap();
+    let amount_out = fee.calculate_post_fee_amount(paid).unwrap();
+    assert!(amount_out < paid);
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(amount_in, amount_out + 1, false))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, false))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await - user_x_before,
+        amount_out
+    );
+    (reserve_x, reserve_y) = (reserve_x - paid, reserve_y + amount_in);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+
+    // Buying an exact amount of x sends enough for it to arrive after the fee
+    let amount_out = 10_000;
+    let paid = fee.calculate_pre_fee_amount(amount_out).unwrap();
+    let amount_in = amm_math::swap_exact_out(paid, reserve_y, reserve_x, FEE).unwrap();
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, amount_in - 1, false))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, amount_in, false))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let amount_received = token_balance(&mut context, &pool.user_x).await - user_x_before;
+    assert_eq!(
+        amount_received,
+        fee.calculate_post_fee_amount(paid).unwrap()
+    );
+    assert!(amount_received >= amount_out);
+    (reserve_x, reserve_y) = (reserve_x - paid, reserve_y + amount_in);
+    assert_eq!(token_balance(&mut context, &pool.vault_x).await, reserve_x);
+    assert_eq!(token_balance(&mut context, &pool.vault_y).await, reserve_y);
+
+    // Selling x for an exact amount holds max_in to what leaves the user,
+    // including the fee
+    let curve_in = amm_math::swap_exact_out(amount_out, reserve_x, reserve_y, FEE).unwrap();
+    let amount_in = fee.calculate_pre_fee_amount(curve_in).unwrap();
+    assert!(amount_in > curve_in);
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, amount_in - 1, true))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(
+        &mut context,
+        &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, amount_in, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        user_x_before - token_balance(&mut context, &pool.user_x).await,
+        amount_in
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_y).await - user_y_before,
+        amount_out
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        reserve_x + fee.calculate_post_fee_amount(amount_in).unwrap()
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_y).await,
+        reserve_y - amount_out
+    );
+}
+
+#[tokio::test]
+async fn swaps_feed_the_oracle_twap() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    // The pool sits at 1:1 for 100 seconds, then at the post-swap price for 100 more
+    warp(&mut context, 100).await;
+
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap();
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    warp(&mut context, 100).await;
+
+    let before = (1u128 << 64, 1u128 << 64);
+    let after = (
+        amm_math::spot_price(DEPOSIT + amount_in, DEPOSIT - amount_out).unwrap(),
+        amm_math::spot_price(DEPOSIT - amount_out, DEPOSIT + amount_in).unwrap(),
+    );
+
+    assert_eq!(observe(&mut context, &pool, 100).await, after);
+    assert_eq!(observe(&mut context, &pool, 50).await, after);
+    assert_eq!(
+        observe(&mut context, &pool, 200).await,
+        ((before.0 + after.0) / 2, (before.1 + after.1) / 2),
+    );
+
+    // Nothing was recorded before the pool was created
+    assert_amm_error(
+        process(&mut context, &[pool.observe_ix(201)], &[]).await,
+        AmmError::InsufficientHistory,
+    );
+}
+
+async fn load_oracle(context: &mut ProgramTestContext, pool: &Pool) -> Oracle {
+    let account = context
+        .banks_client
+        .get_account(pool.oracle)
+        .await
+        .unwrap()
+        .unwrap();
+
+    *Oracle::load(&account.data).unwrap()
+}
+
+/// Swaps `amount_in` of x for y, or of y for x, at the reserves the test
+/// tracks, and moves them on
+async fn swap_tracked(
+    context: &mut ProgramTestContext,
+    pool: &Pool,
+    amount_in: u64,
+    from_x: bool,
+    reserves: &mut (u64, u64),
+) {
+    let user = context.payer.pubkey();
+    let (reserve_in, reserve_out) = if from_x {
+        (reserves.0, reserves.1)
+    } else {
+        (reserves.1, reserves.0)
+    };
+    let amount_out = amm_math::swap_exact_in(amount_in, reserve_in, reserve_out, FEE).unwrap();
+
+    process(
+        context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, from_x))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    *reserves = if from_x {
+        (reserves.0 + amount_in, reserves.1 - amount_out)
+    } else {
+        (reserves.0 - amount_out, reserves.1 + amount_in)
+    };
+}
+
+#[tokio::test]
+async fn oracle_keeps_hours_of_history_however_often_the_pool_trades() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let mut reserves = (DEPOSIT, DEPOSIT);
+
+    // A swap every second only ever advances the newest slot. Sizes differ so
+    // no two transactions are the same
+    for i in 0..20 {
+        warp(&mut context, 1).await;
+        swap_tracked(&mut context, &pool, 1_000 + i, i % 2 == 0, &mut reserves).await;
+    }
+    assert_eq!(load_oracle(&mut context, &pool).await.len, 2);
+
+    // A swap every interval takes a slot each, and wraps the buffer. The last
+    // `OBSERVATIONS` of them each set the price for one interval of the window
+    let mut prices = Vec::new();
+    for i in 0..OBSERVATIONS as u64 + 8 {
+        warp(&mut context, OBSERVATION_INTERVAL).await;
+        swap_tracked(&mut context, &pool, 2_000 + i, i % 2 == 0, &mut reserves).await;
+        prices.push((
+            amm_math::spot_price(reserves.0, reserves.1).unwrap(),
+            amm_math::spot_price(reserves.1, reserves.0).unwrap(),
+        ));
+    }
+    warp(&mut context, OBSERVATION_INTERVAL).await;
+
+    let oracle = load_oracle(&mut context, &pool).await;
+    assert_eq!(oracle.len as usize, OBSERVATIONS);
+    let slot = |age: usize| {
+        oracle.observations[(oracle.index as usize + OBSERVATIONS - age) % OBSERVATIONS]
+    };
+    for age in 1..OBSERVATIONS {
+        assert_eq!(
+            slot(age - 1).timestamp - slot(age).timestamp,
+            OBSERVATION_INTERVAL
+        );
+    }
+
+    let window = prices.len() - OBSERVATIONS..;
+    let average = |price: fn(&(u128, u128)) -> u128| {
+        prices[window.clone()].iter().map(price).sum::<u128>() / OBSERVATIONS as u128
+    };
+    let full_window = (OBSERVATIONS as i64 * OBSERVATION_INTERVAL) as u32;
+    assert_eq!(
+        observe(&mut context, &pool, full_window).await,
+        (average(|price| price.0), average(|price| price.1)),
+    );
+
+    // The oldest slot is the start of the history still held
+    assert_amm_error(
+        process(&mut context, &[pool.observe_ix(full_window + 1)], &[]).await,
+        AmmError::InsufficientHistory,
+    );
+}
+
+/// The config as the first release of the program wrote it: seed, authority,
+/// mints, fee, lock and bumps serialized with Borsh, in an account allocated
+/// 32 bytes more than that
+fn legacy_config_data(config: &Config) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&config.seed.to_le_bytes());
+    data.extend_from_slice(config.authority.as_ref());
+    data.extend_from_slice(config.mint_x.as_ref());
+    data.extend_from_slice(config.mint_y.as_ref());
+    data.extend_from_slice(&config.fee.to_le_bytes());
+    data.extend_from_slice(&[
+        config.locked,
+        config.config_bump,
+        config.lp_bump,
+        config.x_bump,
+        config.y_bump,
+        0,
+    ]);
+    data.resize(data.len() + 32, 0);
+    data
+}
+
+#[tokio::test]
+async fn migrated_legacy_pool_swaps_once_it_has_an_oracle() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+    let current = config(&mut context, &pool.config).await;
+
+    // A pool from the first release, whose config predates every field added
+    // since and which had no oracle
+    let data = legacy_config_data(&current);
+    assert_eq!(data.len(), 144);
+    let rent = context.banks_client.get_rent().await.unwrap();
+    context.set_account(
+        &pool.config,
+        &Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: native_amm::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+    context.set_account(&pool.oracle, &Account::default().into());
+
+    for instruction in [
+        pool.swap_ix(&user, swap(10_000, 0, true)),
+        pool.init_oracle_ix(&user),
+    ] {
+        assert_eq!(
+            process(&mut context, &[instruction], &[])
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(0, InstructionError::InvalidAccountData),
+        );
+    }
+
+    // Anyone can pay to migrate it, and it keeps everything it had
+    let payer = Keypair::new();
+    process(
+        &mut context,
+        &[system_instruction::transfer(
+            &user,
+            &payer.pubkey(),
+            1_000_000_000,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    process(
+        &mut context,
+        &[migrate_config_ix(&pool.config, &payer.pubkey())],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+
+    let migrated = config(&mut context, &pool.config).await;
+    assert_eq!(bytes_of(&migrated), bytes_of(&current));
+
+    // Only once
+    assert_eq!(
+        process(
+            &mut context,
+            &[migrate_config_ix(&pool.config, &payer.pubkey())],
+            &[&payer],
+        )
+        .await
+        .unwrap_err()
+        .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidAccountData),
+    );
+
+    // Swaps still need its oracle, which anyone can pay for too
+    assert_eq!(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(10_000, 0, true))],
+            &[]
+        )
+        .await
+        .unwrap_err()
+        .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidAccountData),
+    );
+
+    process(
+        &mut context,
+        &[pool.init_oracle_ix(&payer.pubkey())],
+        &[&payer],
+    )
+    .await
+    .unwrap();
+
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let account = context
+        .banks_client
+        .get_account(pool.oracle)
+        .await
+        .unwrap()
+        .unwrap();
+    let oracle = Oracle::load(&account.data).unwrap();
+    assert_eq!(account.owner, native_amm::ID);
+    assert_eq!(oracle.config, pool.config);
+    assert_eq!(oracle.len, 1);
+    assert_eq!(oracle.observations[0].timestamp, now);
+
+    // Its history starts now, and swaps extend it
+    warp(&mut context, 10).await;
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(10_000, 0, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(observe(&mut context, &pool, 10).await, (1 << 64, 1 << 64));
+
+    // The system program refuses to create it twice
+    assert_eq!(
+        process(&mut context, &[pool.init_oracle_ix(&user)], &[])
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(0)),
+    );
+}
+
+#[tokio::test]
+async fn route_swap_chains_pools_without_paying_out_the_middle_token() {
+    let mut context = start().await;
+    let pool_ab = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    // A second pool pairs the first pool's y with a new token
+    let mint_c = create_mint(&mut context).await;
+    let mut pool_bc = Pool::new(SEED + 1, pool_ab.mint_y, mint_c);
+    pool_bc.user_x = pool_ab.user_y;
+    pool_bc.user_y = create_token_account(&mut context, &mint_c, &user, USER_BALANCE).await;
+
+    process(&mut context, &[pool_bc.initialize_ix(&user)], &[])
+        .await
+        .unwrap();
+    pool_bc.user_lp = create_token_account(&mut context, &pool_bc.mint_lp, &user, 0).await;
+    process(
+        &mut context,
+        &[pool_bc.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let amount_in = 10_000;
+    let amount_b = amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap();
+    let amount_out = amm_math::swap_exact_in(amount_b, DEPOSIT, DEPOSIT, FEE).unwrap();
+    let user_b = token_balance(&mut context, &pool_ab.user_y).await;
+
+    let route = |min| {
+        route_swap_ix(
+            &user,
+            &pool_ab.user_x,
+            &pool_bc.user_y,
+            &[&pool_ab, &pool_bc],
+            amount_in,
+            min,
+        )
+    };
+
+    assert_amm_error(
+        process(&mut context, &[route(amount_out + 1)], &[]).await,
+        AmmError::SlippageExceeded,
+    );
+
+    // A pool visited twice would price its second hop on stale reserves
+    assert_amm_error(
+        process(
+            &mut context,
+            &[route_swap_ix(
+                &user,
+                &pool_ab.user_x,
+                &pool_ab.user_x,
+                &[&pool_ab, &pool_ab],
+                amount_in,
+                0,
+            )],
+            &[],
+        )
+        .await,
+        AmmError::InvalidRoute,
+    );
+
+    // Each hop logs its own swap, with the reserves it left behind
+    let events = process_events(&mut context, &[route(amount_out)]).await;
+    let [AmmEvent::Swap(first), AmmEvent::Swap(second)] = events.as_slice() else {
+        panic!("expected a swap per hop, got {events:?}");
+    };
+    assert_eq!(
+        (first.pool, first.from_x, first.amount_in, first.amount_out),
+        (pool_ab.config, true, amount_in, amount_b)
+    );
+    assert_eq!(
+        (first.reserve_x, first.reserve_y),
+        (DEPOSIT + amount_in, DEPOSIT - amount_b)
+    );
+    assert_eq!(
+        (
+            second.pool,
+            second.from_x,
+            second.amount_in,
+            second.amount_out
+        ),
+        (pool_bc.config, true, amount_b, amount_out)
+    );
+    assert_eq!(
+        (second.reserve_x, second.reserve_y),
+        (DEPOSIT + amount_b, DEPOSIT - amount_out)
+    );
+    assert_eq!(
+        (first.lp_supply, second.lp_supply),
+        (
+            lp_supply(&mut context, &pool_ab.mint_lp).await,
+            lp_supply(&mut context, &pool_bc.mint_lp).await
+        )
+    );
+
+    assert_eq!(
+        token_balance(&mut context, &pool_ab.user_x).await,
+        USER_BALANCE - DEPOSIT - amount_in
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool_bc.user_y).await,
+        USER_BALANCE - DEPOSIT + amount_out
+    );
+    assert_eq!(token_balance(&mut context, &pool_ab.user_y).await, user_b);
+    assert_eq!(
+        token_balance(&mut context, &pool_ab.vault_y).await,
+        DEPOSIT - amount_b
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool_bc.vault_x).await,
+        DEPOSIT + amount_b
+    );
+}
+
+#[tokio::test]
+async fn stable_pool_trades_near_the_peg_and_ramps_its_amplification() {
+    let mut context = start().await;
+    let mut pool = setup(&mut context).await;
+    let user = context.payer.pubkey();
+    let amp = 100;
+
+    process(
+        &mut context,
+        &[pool.initialize_curve_ix(&user, CurveType::Stable, weighted::EVEN_WEIGHT, amp)],
+        &[],
+    )
+    .await
+    .unwrap();
+    pool.user_lp = create_token_account(&mut context, &pool.mint_lp, &user, 0).await;
+
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let initial_lp = stable::initial_liquidity(amp, DEPOSIT, DEPOSIT).unwrap() - MINIMUM_LIQUIDITY;
+    assert_eq!(lp_supply(&mut context, &pool.mint_lp).await, initial_lp);
+
+    // Near the peg the output beats the constant-product curve
+    let amount_in = 10_000;
+    let amount_out = stable::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE, amp).unwrap();
+    assert!(amount_out > amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap());
+
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.user_y).await - user_y_before,
+        amount_out
+    );
+
+    // One-sided deposits mint on the growth of the invariant
+    let (reserve_x, reserve_y) = (DEPOSIT + amount_in, DEPOSIT - amount_out);
+    let minted = stable::deposit_liquidity(
+        0,
+        amount_in,
+        reserve_x,
+        reserve_y,
+        initial_lp + MINIMUM_LIQUIDITY,
+        FEE,
+        amp,
+    )
+    .unwrap();
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.deposit_ix(&user, deposit(minted + 1, 0, amount_in))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(minted, 0, amount_in))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        lp_supply(&mut context, &pool.mint_lp).await,
+        initial_lp + minted
+    );
+
+    // Ramps must be gradual
+    let now = context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    for (target_amp, duration) in [(1_000, 3_600), (amp * 11, 2 * 86_400), (0, 2 * 86_400)] {
+        assert_amm_error(
+            process(
+                &mut context,
+                &[pool.ramp_amp_ix(&user, target_amp, now + duration)],
+                &[],
+            )
+            .await,
+            AmmError::InvalidAmp,
+        );
+    }
+
+    process(
+        &mut context,
+        &[pool.ramp_amp_ix(&user, 1_000, now + 2 * 86_400)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config_account = config(&mut context, &pool.config).await;
+    assert_eq!(config_account.initial_amp, amp);
+    assert_eq!(config_account.target_amp, 1_000);
+    assert_eq!(config_account.ramp_start, now);
+
+    // Halfway through, swaps price at the halfway amplification, which shows
+    // once a trade moves the pool well off the peg
+    warp(&mut context, 86_400).await;
+    let amount_in = DEPOSIT / 2;
+    let halfway = 550;
+    assert_eq!(
+        stable::current_amp(amp, 1_000, now, now + 2 * 86_400, now + 86_400),
+        halfway
+    );
+
+    let (reserve_x, reserve_y) = (
+        token_balance(&mut context, &pool.vault_x).await,
+        token_balance(&mut context, &pool.vault_y).await,
+    );
+    let amount_out = stable::swap_exact_in(amount_in, reserve_y, reserve_x, FEE, halfway).unwrap();
+    assert_ne!(
+        amount_out,
+        stable::swap_exact_in(amount_in, reserve_y, reserve_x, FEE, amp).unwrap()
+    );
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, false))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await - user_x_before,
+        amount_out
+    );
+}
+
+#[tokio::test]
+async fn weighted_pool_mints_and_prices_by_its_weights() {
+    let mut context = start().await;
+    let mut pool = setup(&mut context).await;
+    let user = context.payer.pubkey();
+    let weight_x = 8_000;
+
+    // Weights must be in bounds, and StableSwap only prices even ones
+    for (curve, weight_x, amp) in [
+        (CurveType::ConstantProduct, weighted::MIN_WEIGHT - 1, 0),
+        (CurveType::ConstantProduct, weighted::MAX_WEIGHT + 1, 0),
+        (CurveType::Stable, weight_x, 100),
+    ] {
+        assert_amm_error(
+            process(
+                &mut context,
+                &[pool.initialize_curve_ix(&user, curve, weight_x, amp)],
+                &[],
+            )
+            .await,
+            AmmError::InvalidWeight,
+        );
+    }
+
+    process(
+        &mut context,
+        &[pool.initialize_curve_ix(&user, CurveType::ConstantProduct, weight_x, 0)],
+        &[],
+    )
+    .await
+    .unwrap();
+    pool.user_lp = create_token_account(&mut context, &pool.mint_lp, &user, 0).await;
+
+    // 80/20 by value, with x and y at par
+    let (reserve_x, reserve_y) = (DEPOSIT * 4, DEPOSIT);
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(0, reserve_x, reserve_y))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let initial_lp = weighted::initial_liquidity(weight_x, reserve_x, reserve_y).unwrap();
+    assert!(initial_lp > amm_math::initial_liquidity(reserve_x, reserve_y));
+    assert_eq!(
+        lp_supply(&mut context, &pool.mint_lp).await,
+        initial_lp - MINIMUM_LIQUIDITY
+    );
+
+    // Each direction is priced with the weight of the token sold
+    let amount_in = 10_000;
+    let amount_out =
+        weighted::swap_exact_in(amount_in, reserve_y, reserve_x, FEE, 10_000 - weight_x).unwrap();
+    assert!(amount_out < amm_math::swap_exact_in(amount_in, reserve_y, reserve_x, FEE).unwrap());
+    assert!(amount_out.abs_diff(amount_in) < amount_in / 100);
+
+    let user_x_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, false))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await - user_x_before,
+        amount_out
+    );
+
+    let (reserve_x, reserve_y) = (reserve_x - amount_out, reserve_y + amount_in);
+    let amount_out =
+        weighted::swap_exact_in(amount_in, reserve_x, reserve_y, FEE, weight_x).unwrap();
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.swap_ix(&user, swap(amount_in, amount_out + 1, true))],
+            &[],
+        )
+        .await,
+        AmmError::SlippageExceeded,
+    );
+
+    let user_y_before = token_balance(&mut context, &pool.user_y).await;
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.user_y).await - user_y_before,
+        amount_out
+    );
+}
+
+#[tokio::test]
+async fn ramp_amp_rejects_a_constant_product_pool() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    assert_amm_error(
+        process(&mut context, &[pool.ramp_amp_ix(&user, 100, i64::MAX)], &[]).await,
+        AmmError::InvalidCurve,
+    );
+}
+
+#[tokio::test]
+async fn flash_loan_is_repaid_with_its_fee() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    assert_amm_error(
+        process(&mut context, &[pool.set_flash_fee_ix(&user, 10_000)], &[]).await,
+        AmmError::InvalidFee,
+    );
+    process(&mut context, &[pool.set_flash_fee_ix(&user, 100)], &[])
+        .await
+        .unwrap();
+
+    let amount = 100_000;
+    let owed = amount + amm_math::flash_loan_fee(amount, 100).unwrap();
+    assert_eq!(owed, 101_000);
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.flash_loan_ix(&user, amount, owed - 1)],
+            &[],
+        )
+        .await,
+        AmmError::FlashLoanNotRepaid,
+    );
+
+    let vault_before = token_balance(&mut context, &pool.vault_x).await;
+    let user_before = token_balance(&mut context, &pool.user_x).await;
+    process(
+        &mut context,
+        &[pool.flash_loan_ix(&user, amount, owed)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut context, &pool.vault_x).await,
+        vault_before + owed - amount
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.user_x).await,
+        user_before + amount - owed
+    );
+    assert_eq!(config(&mut context, &pool.config).await.locked, 0);
+}
+
+#[tokio::test]
+async fn flash_loan_rejects_the_amm_as_receiver_and_a_locked_pool() {
+    let mut context = start().await;
+    let pool = setup_pool(&mut context).await;
+    let user = context.payer.pubkey();
+
+    let mut instruction = pool.flash_loan_ix(&user, 1_000, 1_000);
+    instruction.accounts[5].pubkey = native_amm::ID;
+    assert_amm_error(
+        process(&mut context, &[instruction], &[]).await,
+        AmmError::InvalidReceiver,
+    );
+
+    process(&mut context, &[pool.set_locked_ix(&user, true)], &[])
+        .await
+        .unwrap();
+    assert_amm_error(
+        process(
+            &mut context,
+            &[pool.flash_loan_ix(&user, 1_000, 1_000)],
+            &[],
+        )
+        .await,
+        AmmError::PoolLocked,
+    );
+}
+
+#[tokio::test]
+async fn instructions_emit_events_for_indexers() {
+    let mut context = start().await;
+    let mut pool = setup(&mut context).await;
+    let user = context.payer.pubkey();
+
+    let events = process_events(&mut context, &[pool.initialize_ix(&user)]).await;
+    let [AmmEvent::PoolInitialized(initialized)] = events.as_slice() else {
+        panic!("expected PoolInitialized, got {events:?}");
+    };
+    assert_eq!(initialized.pool, pool.config);
+    assert_eq!(initialized.user, user);
+    assert_eq!(
+        (initialized.mint_x, initialized.mint_y),
+        (pool.mint_x, pool.mint_y)
+    );
+    assert_eq!(initialized.fee, FEE);
+    assert_eq!(initialized.weight_x, weighted::EVEN_WEIGHT);
+
+    pool.user_lp = create_token_account(&mut context, &pool.mint_lp, &user, 0).await;
+    let events = process_events(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+    )
+    .await;
+    let [AmmEvent::Deposit(deposited)] = events.as_slice() else {
+        panic!("expected Deposit, got {events:?}");
+    };
+    assert_eq!((deposited.amount_x, deposited.amount_y), (DEPOSIT, DEPOSIT));
+    assert_eq!(deposited.lp_minted, DEPOSIT - MINIMUM_LIQUIDITY);
+    assert_eq!(
+        (deposited.reserve_x, deposited.reserve_y),
+        (DEPOSIT, DEPOSIT)
+    );
+    assert_eq!(
+        deposited.lp_supply,
+        lp_supply(&mut context, &pool.mint_lp).await
+    );
+
+    let amount_in = 10_000;
+    let events = process_events(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, 0, false))],
+    )
+    .await;
+    let [AmmEvent::Swap(swapped)] = events.as_slice() else {
+        panic!("expected Swap, got {events:?}");
+    };
+    assert_eq!(swapped.user, user);
+    assert!(!swapped.from_x);
+    assert_eq!(swapped.amount_in, amount_in);
+    assert_eq!(
+        swapped.amount_out,
+        amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap()
+    );
+    assert_eq!(
+        swapped.fee_paid,
+        amm_math::swap_fee(amount_in, FEE).unwrap()
+    );
+    assert_eq!(
+        (swapped.reserve_x, swapped.reserve_y),
+        (
+            token_balance(&mut context, &pool.vault_x).await,
+            token_balance(&mut context, &pool.vault_y).await
+        )
+    );
+    assert_eq!(swapped.lp_supply, deposited.lp_supply);
+
+    let amount_out = 5_000;
+    let events = process_events(
+        &mut context,
+        &[pool.swap_exact_out_ix(&user, swap_exact_out(amount_out, u64::MAX, true))],
+    )
+    .await;
+    let [AmmEvent::Swap(swapped)] = events.as_slice() else {
+        panic!("expected Swap, got {events:?}");
+    };
+    assert!(swapped.from_x);
+    assert_eq!(swapped.amount_out, amount_out);
+    assert_eq!(
+        (swapped.reserve_x, swapped.reserve_y, swapped.lp_supply),
+        (
+            token_balance(&mut context, &pool.vault_x).await,
+            token_balance(&mut context, &pool.vault_y).await,
+            deposited.lp_supply
+        )
+    );
+
+    let events = process_events(&mut context, &[pool.set_flash_fee_ix(&user, 25)]).await;
+    let [AmmEvent::ConfigUpdated(updated)] = events.as_slice() else {
+        panic!("expected ConfigUpdated, got {events:?}");
+    };
+    assert_eq!((updated.pool, updated.authority), (pool.config, user));
+    assert_eq!((updated.fee, updated.flash_fee), (FEE, 25));
+    assert!(!updated.locked);
+
+    set_upgrade_authority(&mut context, &user);
+    process(&mut context, &[initialize_factory_ix(&user, &user)], &[])
+        .await
+        .unwrap();
+    let events = process_events(&mut context, &[set_fee_tier_ix(&user, 5, true)]).await;
+    let [AmmEvent::FeeTierSet(tier)] = events.as_slice() else {
+        panic!("expected FeeTierSet, got {events:?}");
+    };
+    assert_eq!((tier.factory, tier.admin), (factory_address(), user));
+    assert_eq!((tier.fee, tier.enabled), (5, true));
+}
+
+#[tokio::test]
+async fn factory_creates_one_canonical_pool_per_sorted_pair_and_fee_tier() {
+    let mut context = start().await;
+    let user = context.payer.pubkey();
+    let admin = Keypair::new();
+    set_upgrade_authority(&mut context, &user);
+
+    // Only the upgrade authority creates the factory, and only the admin sets its tiers
+    assert_amm_error(
+        process(
+            &mut context,
+            &[initialize_factory_ix(&admin.pubkey(), &admin.pubkey())],
+            &[&admin],
+        )
+        .await,
+        AmmError::Unauthorized,
+    );
+    process(
+        &mut context,
+        &[initialize_factory_ix(&user, &admin.pubkey())],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_amm_error(
+        process(&mut context, &[set_fee_tier_ix(&user, FEE, true)], &[]).await,
+        AmmError::Unauthorized,
+    );
+    process(
+        &mut context,
+        &[set_fee_tier_ix(&admin.pubkey(), FEE, true)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let mint_a = create_mint(&mut context).await;
+    let mint_b = create_mint(&mut context).await;
+    let (mint_x, mint_y) = (mint_a.min(mint_b), mint_a.max(mint_b));
+
+    assert_amm_error(
+        process(
+            &mut context,
+            &[Pool::canonical(mint_y, mint_x, FEE).create_pool_ix(&user, FEE)],
+            &[],
+        )
+        .await,
+        AmmError::UnsortedMints,
+    );
+    assert_amm_error(
+        process(
+            &mut context,
+            &[Pool::canonical(mint_x, mint_x, FEE).create_pool_ix(&user, FEE)],
+            &[],
+        )
+        .await,
+        AmmError::IdenticalMints,
+    );
+    assert_amm_error(
+        process(
+            &mut context,
+            &[Pool::canonical(mint_x, mint_y, 5).create_pool_ix(&user, 5)],
+            &[],
+        )
+        .await,
+        AmmError::InvalidFeeTier,
+    );
+
+    let mut pool = Pool::canonical(mint_x, mint_y, FEE);
+    process(&mut context, &[pool.create_pool_ix(&user, FEE)], &[])
+        .await
+        .unwrap();
+
+    let config_account = config(&mut context, &pool.config).await;
+    assert_eq!(config_account.factory, 1);
+    assert_eq!(config_account.fee_tier, FEE);
+    assert_eq!(config_account.fee, FEE);
+    assert_eq!(config_account.authority, admin.pubkey());
+
+    // The pair's pool at this tier already exists, whoever asks
+    process(
+        &mut context,
+        &[set_fee_tier_ix(&admin.pubkey(), 5, true)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    let pool_at_5 = Pool::canonical(mint_x, mint_y, 5);
+    assert!(process(
+        &mut context,
+        &[
+            pool.create_pool_ix(&user, FEE),
+            pool_at_5.create_pool_ix(&user, 5)
+        ],
+        &[],
+    )
+    .await
+    .is_err());
+    process(&mut context, &[pool_at_5.create_pool_ix(&user, 5)], &[])
+        .await
+        .unwrap();
+
+    // The canonical pool signs with its own seeds to mint LP and pay out swaps
+    pool.user_x = create_token_account(&mut context, &mint_x, &user, USER_BALANCE).await;
+    pool.user_y = create_token_account(&mut context, &mint_y, &user, USER_BALANCE).await;
+    pool.user_lp = create_token_account(&mut context, &pool.mint_lp, &user, 0).await;
+    process(
+        &mut context,
+        &[pool.deposit_ix(&user, deposit(0, DEPOSIT, DEPOSIT))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let amount_in = 10_000;
+    let amount_out = amm_math::swap_exact_in(amount_in, DEPOSIT, DEPOSIT, FEE).unwrap();
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, amount_out, true))],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut context, &pool.user_y).await,
+        USER_BALANCE - DEPOSIT + amount_out
+    );
+
+    // Withdrawing a tier stops new pools at it, but not the ones trading
+    process(
+        &mut context,
+        &[set_fee_tier_ix(&admin.pubkey(), FEE, false)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    process(
+        &mut context,
+        &[pool.swap_ix(&user, swap(amount_in, 0, false))],
+        &[],
+    )
+    .await
+    .unwrap();
+}